@@ -0,0 +1,192 @@
+// aerobic endurance markers of steady rides, samples are expected once per second
+// REF: Friel, "The Power Meter Handbook", efficiency factor and Pw:HR decoupling
+
+use std::fmt;
+
+use crate::analytics::{average, normalized_power};
+use crate::recording::Sample;
+
+// what the heart rate is compared against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    Power,
+    // for rides without a power meter
+    Speed
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AerobicAnalysis {
+    pub output: Output,
+    // NP (or average speed) per beat per minute
+    pub efficiency_factor: f32,
+    pub first_half: f32,
+    pub second_half: f32,
+    // % the efficiency factor dropped from the first to the second half
+    pub decoupling: f32,
+    // % the heart rate rose from the first to the second half while riding at the same power
+    pub heart_rate_drift: Option<f32>,
+    // variability low enough for decoupling to be meaningful
+    pub steady: bool
+}
+
+// shorter rides say little about aerobic endurance
+const MIN_DURATION: usize = 20 * 60;
+// 30 s power within this share of the average counts as constant
+const CONSTANT_POWER_TOLERANCE: f32 = 0.1;
+const ROLLING_WINDOW: usize = 30;
+// constant power riding needed in each half for the drift
+const MIN_CONSTANT_DURATION: usize = 5 * 60;
+const STEADY_VARIABILITY: f32 = 1.05;
+// decoupling below this is considered good aerobic fitness
+pub const DECOUPLING_THRESHOLD: f32 = 5.;
+
+impl AerobicAnalysis {
+    pub fn new(samples: &[Sample]) -> Option<AerobicAnalysis> {
+        if samples.len() < MIN_DURATION {
+            return None;
+        }
+
+        let output = match samples.iter().any(|x| x.power > 0) {
+            true => Output::Power,
+            false => Output::Speed
+        };
+        let (first, second) = samples.split_at(samples.len() / 2);
+        let first_half = efficiency(first, output)?;
+        let second_half = efficiency(second, output)?;
+
+        let steady = match output {
+            Output::Power => {
+                let power: Vec<f32> = samples.iter().map(|x| x.power as f32).collect();
+                match (normalized_power(&power), average(&power)) {
+                    (Some(np), Some(average)) => average > 0. && np / average <= STEADY_VARIABILITY,
+                    _ => false
+                }
+            }
+            Output::Speed => true
+        };
+
+        return Some(AerobicAnalysis {
+            output,
+            efficiency_factor: efficiency(samples, output)?,
+            first_half,
+            second_half,
+            decoupling: (first_half - second_half) / first_half * 100.,
+            heart_rate_drift: match output {
+                Output::Power => heart_rate_drift(samples),
+                Output::Speed => None
+            },
+            steady
+        });
+    }
+}
+
+fn efficiency(samples: &[Sample], output: Output) -> Option<f32> {
+    let heart_rate: Vec<f32> = samples.iter()
+        .filter(|x| x.heart_rate > 0)
+        .map(|x| x.heart_rate as f32)
+        .collect();
+    let heart_rate = average(&heart_rate)?;
+
+    let value = match output {
+        Output::Power => {
+            let power: Vec<f32> = samples.iter().map(|x| x.power as f32).collect();
+            normalized_power(&power)?
+        }
+        Output::Speed => {
+            let speed: Vec<f32> = samples.iter().map(|x| x.speed).filter(|x| *x > 0.).collect();
+            average(&speed)?
+        }
+    };
+
+    if value <= 0. {
+        return None;
+    }
+    return Some(value / heart_rate);
+}
+
+// average heart rate of the constant power parts of each half
+fn heart_rate_drift(samples: &[Sample]) -> Option<f32> {
+    let power: Vec<f32> = samples.iter().map(|x| x.power as f32).filter(|x| *x > 0.).collect();
+    let target = average(&power)?;
+
+    let mut sum = 0.;
+    let mut halves: [Vec<f32>; 2] = [Vec::new(), Vec::new()];
+    for (index, sample) in samples.iter().enumerate() {
+        sum += sample.power as f32;
+        if index >= ROLLING_WINDOW {
+            sum -= samples[index - ROLLING_WINDOW].power as f32;
+        }
+
+        let rolling = sum / (index + 1).min(ROLLING_WINDOW) as f32;
+        if index + 1 >= ROLLING_WINDOW && sample.heart_rate > 0 && (rolling - target).abs() <= target * CONSTANT_POWER_TOLERANCE {
+            halves[(index >= samples.len() / 2) as usize].push(sample.heart_rate as f32);
+        }
+    }
+
+    if halves.iter().any(|x| x.len() < MIN_CONSTANT_DURATION) {
+        return None;
+    }
+    let first = average(&halves[0])?;
+    let second = average(&halves[1])?;
+    return Some((second - first) / first * 100.);
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Output::Power => "Pw:HR",
+            Output::Speed => "Pace:HR"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::aerobic::{AerobicAnalysis, Output};
+    use crate::recording::Sample;
+
+    fn samples(halves: [(u16, u8); 2], speed: f32, seconds: u64) -> Vec<Sample> {
+        return (1..=seconds)
+            .map(|x| {
+                let (power, heart_rate) = halves[(x > seconds / 2) as usize];
+                Sample {
+                    elapsed: Duration::from_secs(x),
+                    heart_rate,
+                    power,
+                    cadence: 90,
+                    speed
+                }
+            })
+            .collect();
+    }
+
+    #[test]
+    fn heart_rate_rising_at_constant_power_decouples() {
+        let analysis = AerobicAnalysis::new(&samples([(250, 140), (250, 147)], 32., 3600)).unwrap();
+
+        assert_eq!(analysis.output, Output::Power);
+        assert!(analysis.steady);
+        assert!((analysis.efficiency_factor - 250. / 143.5).abs() < 0.01);
+        assert!((analysis.decoupling - 4.76).abs() < 0.05, "{}", analysis.decoupling);
+        assert!((analysis.heart_rate_drift.unwrap() - 5.).abs() < 0.05);
+    }
+
+    #[test]
+    fn speed_is_used_without_power() {
+        let analysis = AerobicAnalysis::new(&samples([(0, 130), (0, 130)], 30., 3600)).unwrap();
+
+        assert_eq!(analysis.output, Output::Speed);
+        assert!((analysis.efficiency_factor - 30. / 130.).abs() < 0.001);
+        assert!(analysis.decoupling.abs() < 0.001);
+        assert_eq!(analysis.heart_rate_drift, None);
+    }
+
+    #[test]
+    fn needs_a_long_ride_with_heart_rate() {
+        assert_eq!(AerobicAnalysis::new(&samples([(250, 140), (250, 140)], 30., 600)), None);
+        assert_eq!(AerobicAnalysis::new(&samples([(250, 0), (250, 0)], 30., 3600)), None);
+    }
+}
@@ -0,0 +1,165 @@
+// power based ride metrics, samples are expected once per second
+
+use std::time::Duration;
+
+use crate::recording::Sample;
+
+// rolling window used by normalized power
+const NP_WINDOW: usize = 30;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RideSummary {
+    pub duration: Duration,
+    pub average_power: f32,
+    pub max_power: u16,
+    pub normalized_power: Option<f32>,
+    pub intensity_factor: Option<f32>,
+    pub training_stress_score: Option<f32>,
+    pub variability_index: Option<f32>,
+    // kJ
+    pub work: f32,
+    // m
+    pub distance: f32,
+    pub average_cadence: Option<f32>,
+    pub average_heart_rate: Option<f32>,
+    pub max_heart_rate: u8
+}
+
+impl RideSummary {
+    pub fn new(samples: &[Sample], ftp: u16) -> RideSummary {
+        let power: Vec<f32> = samples.iter().map(|x| x.power as f32).collect();
+        let average_power = average(&power).unwrap_or_default();
+        let normalized_power = normalized_power(&power);
+        let intensity_factor = normalized_power
+            .filter(|_| ftp > 0)
+            .map(|x| x / ftp as f32);
+        let duration = Duration::from_secs(samples.len() as u64);
+
+        return RideSummary {
+            duration,
+            average_power,
+            max_power: samples.iter().map(|x| x.power).max().unwrap_or_default(),
+            normalized_power,
+            intensity_factor,
+            training_stress_score: match (normalized_power, intensity_factor) {
+                (Some(np), Some(intensity)) => Some(training_stress_score(duration, np, intensity, ftp)),
+                _ => None
+            },
+            variability_index: normalized_power
+                .filter(|_| average_power > 0.)
+                .map(|x| x / average_power),
+            work: power.iter().sum::<f32>() / 1000.,
+            distance: distance(samples),
+            // coasting is not counted against the cadence
+            average_cadence: average(&nonzero(samples, |x| x.cadence as f32)),
+            average_heart_rate: average(&nonzero(samples, |x| x.heart_rate as f32)),
+            max_heart_rate: samples.iter().map(|x| x.heart_rate).max().unwrap_or_default()
+        };
+    }
+}
+
+// fourth root of the mean of the fourth powers of the 30 s rolling average
+pub fn normalized_power(power: &[f32]) -> Option<f32> {
+    if power.len() < NP_WINDOW {
+        return None;
+    }
+
+    let mut sum: f32 = power[..NP_WINDOW].iter().sum();
+    let mut total = (sum / NP_WINDOW as f32).powi(4) as f64;
+    for index in NP_WINDOW..power.len() {
+        sum += power[index] - power[index - NP_WINDOW];
+        total += (sum / NP_WINDOW as f32).powi(4) as f64;
+    }
+
+    let count = (power.len() - NP_WINDOW + 1) as f64;
+    return Some((total / count).powf(0.25) as f32);
+}
+
+// speed is in km/h
+pub fn distance(samples: &[Sample]) -> f32 {
+    return samples.iter().map(|x| x.speed / 3.6).sum();
+}
+
+pub fn training_stress_score(duration: Duration, normalized_power: f32, intensity_factor: f32, ftp: u16) -> f32 {
+    if ftp == 0 {
+        return 0.;
+    }
+    return duration.as_secs_f32() * normalized_power * intensity_factor / (ftp as f32 * 3600.) * 100.;
+}
+
+pub fn average(values: &[f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    return Some(values.iter().sum::<f32>() / values.len() as f32);
+}
+
+fn nonzero<F: Fn(&Sample) -> f32>(samples: &[Sample], value: F) -> Vec<f32> {
+    return samples.iter().map(value).filter(|x| *x > 0.).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::analytics::{normalized_power, RideSummary};
+    use crate::recording::Sample;
+
+    fn samples(power: &[u16], cadence: u8) -> Vec<Sample> {
+        return power.iter()
+            .enumerate()
+            .map(|(index, power)| Sample {
+                elapsed: Duration::from_secs(index as u64 + 1),
+                heart_rate: 0,
+                power: *power,
+                // coast whenever there is no power
+                cadence: if *power > 0 { cadence } else { 0 },
+                speed: 0.
+            })
+            .collect();
+    }
+
+    fn assert_close(actual: Option<f32>, expected: f32) {
+        let actual = actual.unwrap();
+        assert!((actual - expected).abs() < 0.05, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn steady_hour_at_ftp_is_100_tss() {
+        let summary = RideSummary::new(&samples(&[250; 3600], 90), 250);
+
+        assert_close(summary.normalized_power, 250.);
+        assert_close(summary.intensity_factor, 1.);
+        assert_close(summary.training_stress_score, 100.);
+        assert_close(summary.variability_index, 1.);
+        assert_eq!(summary.work, 900.);
+        assert_eq!(summary.average_cadence, Some(90.));
+    }
+
+    #[test]
+    fn intervals_raise_normalized_power() {
+        // 30 min at 300 W then 30 min coasting
+        let mut power = vec![300; 1800];
+        power.extend(vec![0; 1800]);
+        let summary = RideSummary::new(&samples(&power, 95), 250);
+
+        assert_eq!(summary.average_power, 150.);
+        assert_eq!(summary.max_power, 300);
+        assert_eq!(summary.average_cadence, Some(95.));
+        assert_close(summary.normalized_power, 251.95);
+        assert_close(summary.intensity_factor, 1.0078);
+        assert_close(summary.training_stress_score, 101.57);
+        assert_close(summary.variability_index, 1.6797);
+        assert_eq!(summary.work, 540.);
+    }
+
+    #[test]
+    fn short_rides_have_no_normalized_power() {
+        let summary = RideSummary::new(&samples(&[200; 20], 80), 250);
+
+        assert_eq!(normalized_power(&[200.; 29]), None);
+        assert_eq!(summary.normalized_power, None);
+        assert_eq!(summary.training_stress_score, None);
+        assert_eq!(summary.average_power, 200.);
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::profile::Profile;
+use crate::state::State;
+use crate::stopwatch::StopwatchState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    Power,
+    Cadence,
+    Speed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AutoPauseAction {
+    Start,
+    Pause,
+    Resume
+}
+
+#[derive(Debug, Clone)]
+pub struct AutoPauseConfig {
+    pub enabled: bool,
+    pub trigger: Trigger,
+    pub threshold: f32,
+    // how long the value has to stay above the threshold before starting/resuming
+    pub start_delay: Duration,
+    // how long the value has to stay below the threshold before pausing
+    pub stop_delay: Duration
+}
+
+#[derive(Debug, Clone)]
+pub struct AutoPause {
+    pub config: AutoPauseConfig,
+    pub manual_override: bool,
+    // the override ends by itself once the value drops below the threshold
+    release_when_stopped: bool,
+    active_since: Option<Instant>,
+    inactive_since: Option<Instant>
+}
+
+impl Trigger {
+    pub const ALL: [Trigger; 3] = [Trigger::Power, Trigger::Cadence, Trigger::Speed];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Trigger::Power => "power",
+            Trigger::Cadence => "cadence",
+            Trigger::Speed => "speed"
+        }
+    }
+
+    // returns None when the trainer has gone quiet, so a stale
+    // value does not keep the ride running. Also None until an FTMS
+    // trainer has sent indoor bike data, which auto pause depends on
+    pub fn value(&self, state: &State, now: Instant, timeout: Duration) -> Option<f32> {
+        let updated_at = state.trainer_updated_at?;
+        if now.duration_since(updated_at) > timeout {
+            return None;
+        }
+
+        return match self {
+            Trigger::Power => Some(state.power as f32),
+            Trigger::Cadence => Some(state.cadence as f32),
+            Trigger::Speed => Some(state.speed)
+        };
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Trigger::Power => "Power (W)",
+            Trigger::Cadence => "Cadence (rpm)",
+            Trigger::Speed => "Speed (km/h)"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+impl AutoPauseConfig {
+    pub fn new(profile: &Profile) -> AutoPauseConfig {
+        return AutoPauseConfig {
+            enabled: true,
+            trigger: profile.auto_pause_trigger,
+            threshold: profile.auto_pause_threshold,
+            start_delay: Duration::from_secs(profile.auto_pause_start_delay as u64),
+            stop_delay: Duration::from_secs(profile.auto_pause_stop_delay as u64)
+        }
+    }
+}
+
+impl AutoPause {
+    pub fn new(config: AutoPauseConfig) -> AutoPause {
+        return AutoPause {
+            config,
+            manual_override: false,
+            release_when_stopped: false,
+            active_since: None,
+            inactive_since: None
+        }
+    }
+
+    // manual override keeps the stopwatch in whatever state the rider put it in
+    pub fn set_manual_override(&mut self, value: bool) {
+        self.manual_override = value;
+        self.release_when_stopped = false;
+        self.active_since = None;
+        self.inactive_since = None;
+    }
+
+    // after a finished or discarded ride, a rider still pedalling must not start a new one
+    pub fn hold_until_stopped(&mut self) {
+        self.set_manual_override(true);
+        self.release_when_stopped = true;
+    }
+
+    pub fn update(&mut self, value: Option<f32>, now: Instant, stopwatch: &StopwatchState) -> Option<AutoPauseAction> {
+        let is_active = value.is_some_and(|x| x >= self.config.threshold);
+        if self.release_when_stopped && !is_active {
+            self.set_manual_override(false);
+        }
+
+        if !self.config.enabled || self.manual_override {
+            return None;
+        }
+
+        if is_active {
+            self.inactive_since = None;
+            let since = *self.active_since.get_or_insert(now);
+            if now.duration_since(since) < self.config.start_delay {
+                return None;
+            }
+
+            return match stopwatch {
+                StopwatchState::Idle => Some(AutoPauseAction::Start),
+                StopwatchState::Paused => Some(AutoPauseAction::Resume),
+                StopwatchState::Ticking { .. } => None
+            };
+        }
+
+        self.active_since = None;
+        let since = *self.inactive_since.get_or_insert(now);
+        if now.duration_since(since) < self.config.stop_delay {
+            return None;
+        }
+
+        return match stopwatch {
+            StopwatchState::Ticking { .. } => Some(AutoPauseAction::Pause),
+            _ => None
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::autopause::{AutoPause, AutoPauseAction, AutoPauseConfig, Trigger};
+    use crate::profile::Profile;
+    use crate::state::State;
+    use crate::stopwatch::StopwatchState;
+
+    #[test]
+    fn starts_after_start_delay_and_pauses_after_stop_delay() {
+        let mut auto_pause = AutoPause::new(AutoPauseConfig::new(&Profile::new()));
+        let start = Instant::now();
+        let idle = StopwatchState::Idle;
+
+        assert_eq!(auto_pause.update(Some(150.), start, &idle), None);
+        assert_eq!(auto_pause.update(Some(150.), start + Duration::from_secs(3), &idle), Some(AutoPauseAction::Start));
+
+        let ticking = StopwatchState::Ticking { last_tick: start };
+        assert_eq!(auto_pause.update(None, start + Duration::from_secs(4), &ticking), None);
+        assert_eq!(auto_pause.update(Some(0.), start + Duration::from_secs(9), &ticking), Some(AutoPauseAction::Pause));
+
+        let paused = StopwatchState::Paused;
+        assert_eq!(auto_pause.update(Some(150.), start + Duration::from_secs(10), &paused), None);
+        assert_eq!(auto_pause.update(Some(150.), start + Duration::from_secs(13), &paused), Some(AutoPauseAction::Resume));
+    }
+
+    #[test]
+    fn manual_override_disables_transitions() {
+        let mut auto_pause = AutoPause::new(AutoPauseConfig::new(&Profile::new()));
+        auto_pause.set_manual_override(true);
+        let start = Instant::now();
+        let ticking = StopwatchState::Ticking { last_tick: start };
+
+        assert_eq!(auto_pause.update(None, start, &ticking), None);
+        assert_eq!(auto_pause.update(None, start + Duration::from_secs(60), &ticking), None);
+    }
+
+    #[test]
+    fn holds_until_the_rider_stops() {
+        let mut auto_pause = AutoPause::new(AutoPauseConfig::new(&Profile::new()));
+        auto_pause.hold_until_stopped();
+        let start = Instant::now();
+        let idle = StopwatchState::Idle;
+
+        assert_eq!(auto_pause.update(Some(150.), start, &idle), None);
+        assert_eq!(auto_pause.update(Some(150.), start + Duration::from_secs(10), &idle), None);
+        assert!(auto_pause.manual_override);
+
+        assert_eq!(auto_pause.update(Some(0.), start + Duration::from_secs(11), &idle), None);
+        assert!(!auto_pause.manual_override);
+        assert_eq!(auto_pause.update(Some(150.), start + Duration::from_secs(12), &idle), None);
+        assert_eq!(auto_pause.update(Some(150.), start + Duration::from_secs(15), &idle), Some(AutoPauseAction::Start));
+    }
+
+    #[test]
+    fn follows_the_trigger_from_the_profile() {
+        let mut profile = Profile::new();
+        profile.auto_pause_trigger = Trigger::Cadence;
+        profile.auto_pause_threshold = 30.;
+        profile.auto_pause_start_delay = 1;
+        let config = AutoPauseConfig::new(&profile);
+        let start = Instant::now();
+
+        let mut state = State::new();
+        state.power = 200;
+        state.cadence = 25;
+        state.trainer_updated_at = Some(start);
+
+        let value = config.trigger.value(&state, start, config.stop_delay);
+        assert_eq!(value, Some(25.));
+
+        let mut auto_pause = AutoPause::new(config);
+        let idle = StopwatchState::Idle;
+        assert_eq!(auto_pause.update(value, start, &idle), None);
+        assert_eq!(auto_pause.update(Some(90.), start + Duration::from_secs(1), &idle), None);
+        assert_eq!(auto_pause.update(Some(90.), start + Duration::from_secs(2), &idle), Some(AutoPauseAction::Start));
+    }
+}
@@ -1,18 +1,29 @@
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use btleplug::api::{Central, Manager as _, Peripheral, ScanFilter, CharPropFlags, CentralEvent};
-use btleplug::platform::{Manager, Adapter};
+use btleplug::api::{Central, Manager as _, Peripheral as _, ScanFilter, CharPropFlags, CentralEvent, Characteristic, Service, WriteType};
+use btleplug::platform::{Manager, Adapter, Peripheral};
 use btleplug::api::bleuuid::uuid_from_u16;
 use futures::stream::StreamExt;
 use thiserror::Error;
 use tokio::time;
 
 use crate::device::{Device, MinorDeviceClass};
+use crate::csc;
+use crate::ftms;
+use crate::heart_rate;
 use crate::state::State;
 
 const HEART_RATE_SERVICE: uuid::Uuid = uuid_from_u16(0x180D);
 const HEART_RATE_CHARACTERISTICS: uuid::Uuid = uuid_from_u16(0x2A37);
+const FITNESS_MACHINE_SERVICE: uuid::Uuid = uuid_from_u16(0x1826);
+const INDOOR_BIKE_DATA_CHARACTERISTICS: uuid::Uuid = uuid_from_u16(0x2AD2);
+const FITNESS_MACHINE_CONTROL_POINT: uuid::Uuid = uuid_from_u16(0x2AD9);
+const CYCLING_SPEED_AND_CADENCE_SERVICE: uuid::Uuid = uuid_from_u16(0x1816);
+const CSC_MEASUREMENT_CHARACTERISTICS: uuid::Uuid = uuid_from_u16(0x2A5B);
+const BATTERY_LEVEL_CHARACTERISTICS: uuid::Uuid = uuid_from_u16(0x2A19);
+// not every sensor notifies battery changes, reading it now and then works for all of them
+const BATTERY_POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
 
 #[derive(Error, Debug, Clone)]
 pub enum BluetoothError {
@@ -28,6 +39,33 @@ pub struct Btle {
     pub adapter: Adapter,
 }
 
+// controllable trainer, targets are written to the FTMS control point
+#[derive(Debug, Clone)]
+pub struct Trainer {
+    pub peripheral: Peripheral,
+    pub control_point: Characteristic
+}
+
+impl Trainer {
+    async fn write(&self, data: Vec<u8>) -> Result<(), BluetoothError> {
+        return self.peripheral.write(&self.control_point, &data, WriteType::WithResponse).await
+            .map_err(|err| BluetoothError::UnexpectedError(err.to_string()));
+    }
+
+    pub async fn request_control(self) -> Result<(), BluetoothError> {
+        self.write(ftms::request_control()).await?;
+        return self.write(ftms::start_or_resume()).await;
+    }
+
+    pub async fn set_target_power(self, watts: u16) -> Result<(), BluetoothError> {
+        return self.write(ftms::set_target_power(watts as i16)).await;
+    }
+
+    pub async fn set_simulation(self, grade: f32, rolling_resistance: f32, wind_resistance: f32) -> Result<(), BluetoothError> {
+        return self.write(ftms::set_indoor_bike_simulation(grade, rolling_resistance, wind_resistance)).await;
+    }
+}
+
 impl Btle {
     pub async fn init() -> Result<Btle, BluetoothError> {
         let manager = Manager::new().await.expect("Failed to initialize manager!");
@@ -100,18 +138,44 @@ pub async fn listen_events(adapter: Adapter, state: Arc<Mutex<State>>) -> Result
     };
 
     tokio::spawn(async move {
-        println!("Starting a new listening thread");
-
         while let Some(event) = events.next().await {
             match event {
                 CentralEvent::DeviceConnected(id) => {
                     // TODO: go through this abomination only when the correct devices are
                     // connected and start listening their notifications
-                    println!("connected to device {:?}", id);
 
                     let peripehral = adapter.peripheral(&id).await.unwrap();
                     peripehral.discover_services().await.unwrap();
 
+                    let name = peripheral_name(&peripehral).await;
+                    {
+                        let mut state_lock = state.lock().unwrap();
+                        if !state_lock.connected_devices.contains(&name) {
+                            state_lock.connected_devices.push(name.clone());
+                        }
+                    }
+
+                    let battery_level = peripehral.characteristics()
+                        .into_iter()
+                        .find(|ch| ch.uuid == BATTERY_LEVEL_CHARACTERISTICS);
+
+                    if let Some(battery_level) = battery_level {
+                        let peripheral = peripehral.clone();
+                        let name = name.clone();
+                        let state_clone = Arc::clone(&state);
+                        // stops once the sensor is gone, a reconnect starts a new one
+                        tokio::spawn(async move {
+                            while let Ok(value) = peripheral.read(&battery_level).await {
+                                if let Some(level) = value.first().copied() {
+                                    let mut state_lock = state_clone.lock().unwrap();
+                                    state_lock.battery_levels.retain(|(x, _)| x != &name);
+                                    state_lock.battery_levels.push((name.clone(), level));
+                                }
+                                time::sleep(BATTERY_POLL_INTERVAL).await;
+                            }
+                        });
+                    }
+
                     let heart_rate_service = peripehral.services()
                         .into_iter()
                         .find(|service| service.uuid == HEART_RATE_SERVICE);
@@ -123,8 +187,8 @@ pub async fn listen_events(adapter: Adapter, state: Arc<Mutex<State>>) -> Result
                                 && charateristic.uuid == HEART_RATE_CHARACTERISTICS);
 
                         if ch.is_none() {
-                            println!("Heart Rate Characteristic not found!");
-                            break;
+                            state.lock().unwrap().errors.push(format!("{} has no heart rate measurement", name));
+                            continue;
                         }
 
                         let hr_characteristic = &ch.unwrap();
@@ -136,27 +200,56 @@ pub async fn listen_events(adapter: Adapter, state: Arc<Mutex<State>>) -> Result
 
                         let state_clone = Arc::clone(&state);
                         tokio::spawn(async move {
-                            println!("Starting a new getting data thread");
-
                             while let Some(notification) = notification_stream.next().await {
                                 if notification.uuid == heart_rate_ch.uuid {
-                                    let data = notification.value;
-
-                                    if data.len() < 3 { //TODO: not sure if i need this check
-                                        println!("Invalid data - [{}] {:?}", notification.uuid, data);
+                                    // malformed packets are dropped, the next beat follows within a second
+                                    let Some(data) = heart_rate::parse_heart_rate_measurement(&notification.value) else {
                                         continue;
-                                    }
+                                    };
 
-                                    //TODO: read => https://stackoverflow.com/questions/65443033/heart-rate-value-in-ble/65458794?noredirect=1#comment118474300_65458794
-                                    let res = data[1]; // heart rate
+                                    let res = data.heart_rate.min(u8::MAX as u16) as u8;
 
                                     let mut state_lock = state_clone.lock().unwrap();
                                     state_lock.heart_rate = res;
                                     state_lock.heart_rate_history.push(res);
+                                    state_lock.rr_intervals.extend(data.rr_intervals);
                                 }
                             }
                         });
                     }
+
+                    let fitness_machine_service = peripehral.services()
+                        .into_iter()
+                        .find(|service| service.uuid == FITNESS_MACHINE_SERVICE);
+
+                    if let Some(service) = fitness_machine_service {
+                        if let Err(err) = listen_trainer(&peripehral, &service, &state).await {
+                            state.lock().unwrap().errors.push(err.to_string());
+                        }
+                    }
+
+                    let speed_service = peripehral.services()
+                        .into_iter()
+                        .find(|service| service.uuid == CYCLING_SPEED_AND_CADENCE_SERVICE);
+
+                    if let Some(service) = speed_service {
+                        if let Err(err) = listen_wheel(&peripehral, &service, &state).await {
+                            state.lock().unwrap().errors.push(err.to_string());
+                        }
+                    }
+                }
+                CentralEvent::DeviceDisconnected(id) => {
+                    let Ok(peripheral) = adapter.peripheral(&id).await else {
+                        continue;
+                    };
+
+                    let name = peripheral_name(&peripheral).await;
+                    let mut state_lock = state.lock().unwrap();
+                    state_lock.connected_devices.retain(|x| x != &name);
+                    // writes to a dead trainer fail silently, it is set again when it connects
+                    if state_lock.trainer.as_ref().is_some_and(|x| x.peripheral.id() == id) {
+                        state_lock.trainer = None;
+                    }
                 }
                 _ => {
                 }
@@ -167,3 +260,103 @@ pub async fn listen_events(adapter: Adapter, state: Arc<Mutex<State>>) -> Result
     return Ok(());
 }
 
+// trainer data goes into the state, the control point becomes the trainer
+async fn listen_trainer(peripheral: &Peripheral, service: &Service, state: &Arc<Mutex<State>>) -> Result<(), BluetoothError> {
+    let bike_data = service.characteristics
+        .iter()
+        .find(|ch| ch.uuid == INDOOR_BIKE_DATA_CHARACTERISTICS)
+        .cloned();
+    let control_point = service.characteristics
+        .iter()
+        .find(|ch| ch.uuid == FITNESS_MACHINE_CONTROL_POINT)
+        .cloned();
+
+    if let Some(bike_data) = bike_data {
+        peripheral.subscribe(&bike_data).await
+            .map_err(|err| BluetoothError::UnexpectedError(err.to_string()))?;
+        let mut notification_stream = peripheral.notifications().await
+            .map_err(|err| BluetoothError::UnexpectedError(err.to_string()))?;
+
+        let state_clone = Arc::clone(state);
+        tokio::spawn(async move {
+            while let Some(notification) = notification_stream.next().await {
+                if notification.uuid != bike_data.uuid {
+                    continue;
+                }
+
+                // malformed packets are dropped, the next one follows within a second
+                let Some(data) = ftms::parse_indoor_bike_data(&notification.value) else {
+                    continue;
+                };
+
+                let mut state_lock = state_clone.lock().unwrap();
+                state_lock.speed = data.speed.unwrap_or(state_lock.speed);
+                state_lock.cadence = data.cadence.map_or(state_lock.cadence, |x| x as u8);
+                state_lock.power = data.power.map_or(state_lock.power, |x| x.max(0) as u16);
+                state_lock.trainer_updated_at = Some(Instant::now());
+            }
+        });
+    }
+
+    if let Some(control_point) = control_point {
+        // the control point answers with indications
+        peripheral.subscribe(&control_point).await
+            .map_err(|err| BluetoothError::UnexpectedError(err.to_string()))?;
+        let trainer = Trainer {
+            peripheral: peripheral.clone(),
+            control_point
+        };
+
+        // the trainer is still usable for its data, ERG and routes just will not work
+        if let Err(err) = trainer.clone().request_control().await {
+            state.lock().unwrap().errors.push(format!("Failed to take control of the trainer: {}", err));
+        }
+
+        state.lock().unwrap().trainer = Some(trainer);
+    }
+
+    return Ok(());
+}
+
+// wheel revolutions for trainers without FTMS speed, turned into speed with the wheel circumference
+async fn listen_wheel(peripheral: &Peripheral, service: &Service, state: &Arc<Mutex<State>>) -> Result<(), BluetoothError> {
+    let Some(measurement) = service.characteristics
+        .iter()
+        .find(|ch| ch.uuid == CSC_MEASUREMENT_CHARACTERISTICS)
+        .cloned() else {
+        return Ok(());
+    };
+
+    peripheral.subscribe(&measurement).await
+        .map_err(|err| BluetoothError::UnexpectedError(err.to_string()))?;
+    let mut notification_stream = peripheral.notifications().await
+        .map_err(|err| BluetoothError::UnexpectedError(err.to_string()))?;
+
+    let state_clone = Arc::clone(state);
+    tokio::spawn(async move {
+        let mut previous = None;
+        while let Some(notification) = notification_stream.next().await {
+            if notification.uuid != measurement.uuid {
+                continue;
+            }
+
+            let Some(current) = csc::parse_csc_measurement(&notification.value) else {
+                continue;
+            };
+
+            if let Some(rate) = previous.and_then(|x| csc::wheel_rate(x, current)) {
+                state_clone.lock().unwrap().wheel_rate = Some(rate);
+            }
+            previous = Some(current);
+        }
+    });
+
+    return Ok(());
+}
+
+async fn peripheral_name(peripheral: &Peripheral) -> String {
+    return match peripheral.properties().await {
+        Ok(Some(props)) => props.local_name.unwrap_or(peripheral.address().to_string()),
+        _ => peripheral.address().to_string()
+    };
+}
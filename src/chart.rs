@@ -0,0 +1,285 @@
+use std::fmt;
+use std::time::Duration;
+
+use iced::widget::canvas::{self, Canvas, Cursor, Frame, Geometry, Path, Stroke};
+use iced::{Color, Element, Length, Point, Rectangle, Renderer, Size, Theme};
+
+use crate::recording::Sample;
+use crate::zones::Zone;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartWindow {
+    OneMinute,
+    FiveMinutes,
+    TwentyMinutes,
+    WholeRide
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Smoothing {
+    Off,
+    ThreeSeconds,
+    TenSeconds,
+    ThirtySeconds
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    HeartRate,
+    Power,
+    Cadence
+}
+
+// background band, e.g. a training zone
+#[derive(Debug, Clone)]
+pub struct Band {
+    pub from: f32,
+    pub to: f32,
+    pub color: Color
+}
+
+#[derive(Debug, Clone)]
+pub struct Chart {
+    pub color: Color,
+    pub window: ChartWindow,
+    pub smoothing: Smoothing,
+    pub points: Vec<(f32, f32)>, // (seconds, value)
+    pub bands: Vec<Band>,
+    pub target: Option<f32>
+}
+
+impl ChartWindow {
+    pub const ALL: [ChartWindow; 4] = [
+        ChartWindow::OneMinute,
+        ChartWindow::FiveMinutes,
+        ChartWindow::TwentyMinutes,
+        ChartWindow::WholeRide
+    ];
+
+    pub fn duration(&self) -> Option<Duration> {
+        match self {
+            ChartWindow::OneMinute => Some(Duration::from_secs(60)),
+            ChartWindow::FiveMinutes => Some(Duration::from_secs(5 * 60)),
+            ChartWindow::TwentyMinutes => Some(Duration::from_secs(20 * 60)),
+            ChartWindow::WholeRide => None
+        }
+    }
+}
+
+impl fmt::Display for ChartWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            ChartWindow::OneMinute => "1 min",
+            ChartWindow::FiveMinutes => "5 min",
+            ChartWindow::TwentyMinutes => "20 min",
+            ChartWindow::WholeRide => "Whole ride"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+impl Smoothing {
+    pub const ALL: [Smoothing; 4] = [
+        Smoothing::Off,
+        Smoothing::ThreeSeconds,
+        Smoothing::TenSeconds,
+        Smoothing::ThirtySeconds
+    ];
+
+    pub fn seconds(&self) -> usize {
+        match self {
+            Smoothing::Off => 1,
+            Smoothing::ThreeSeconds => 3,
+            Smoothing::TenSeconds => 10,
+            Smoothing::ThirtySeconds => 30
+        }
+    }
+}
+
+impl fmt::Display for Smoothing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Smoothing::Off => "No smoothing",
+            Smoothing::ThreeSeconds => "3 s",
+            Smoothing::TenSeconds => "10 s",
+            Smoothing::ThirtySeconds => "30 s"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+impl Metric {
+    pub fn value(&self, sample: &Sample) -> f32 {
+        match self {
+            Metric::HeartRate => sample.heart_rate as f32,
+            Metric::Power => sample.power as f32,
+            Metric::Cadence => sample.cadence as f32
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Metric::HeartRate => Color::from_rgb8(0xd6, 0x27, 0x28),
+            Metric::Power => Color::from_rgb8(0x1f, 0x77, 0xb4),
+            Metric::Cadence => Color::from_rgb8(0x2c, 0xa0, 0x2c)
+        }
+    }
+}
+
+impl Chart {
+    pub fn new(metric: Metric, window: ChartWindow, smoothing: Smoothing, samples: &[Sample]) -> Chart {
+        let points = samples.iter()
+            .map(|sample| (sample.elapsed.as_secs_f32(), metric.value(sample)))
+            .collect();
+
+        return Chart::from_points(metric.color(), window, smoothing, points);
+    }
+
+    // for series that are derived from the samples
+    pub fn from_points(color: Color, window: ChartWindow, smoothing: Smoothing, points: Vec<(f32, f32)>) -> Chart {
+        return Chart {
+            color,
+            window,
+            smoothing,
+            points,
+            bands: Vec::new(),
+            target: None
+        }
+    }
+
+    pub fn with_bands(mut self, bands: Vec<Band>) -> Chart {
+        self.bands = bands;
+        return self;
+    }
+
+    pub fn with_target(mut self, target: Option<f32>) -> Chart {
+        self.target = target;
+        return self;
+    }
+
+    pub fn view<'a, Message: 'a>(self) -> Element<'a, Message> {
+        return Canvas::new(self)
+            .width(Length::Fill)
+            .height(Length::Fixed(120.))
+            .into();
+    }
+
+    fn visible_points(&self) -> Vec<(f32, f32)> {
+        let values: Vec<f32> = self.points.iter().map(|(_, value)| *value).collect();
+        let smoothed = smooth(&values, self.smoothing.seconds());
+        let last = self.points.last().map_or(0., |(time, _)| *time);
+        let from = match self.window.duration() {
+            Some(duration) => last - duration.as_secs_f32(),
+            None => 0.
+        };
+
+        return self.points.iter()
+            .zip(smoothed)
+            .map(|((time, _), value)| (*time, value))
+            .filter(|(time, _)| *time >= from)
+            .collect();
+    }
+}
+
+impl<Message> canvas::Program<Message> for Chart {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: Cursor
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let points = self.visible_points();
+
+        let max_value = points.iter()
+            .map(|(_, value)| *value)
+            .chain(self.target)
+            .fold(0., f32::max)
+            .max(1.) * 1.1;
+
+        // the x axis always spans the whole window so the line scrolls in from the right
+        let last = points.last().map_or(0., |(time, _)| *time);
+        let (start, span) = match self.window.duration() {
+            Some(duration) => (last - duration.as_secs_f32(), duration.as_secs_f32()),
+            None => (0., last.max(1.))
+        };
+
+        let width = bounds.width;
+        let height = bounds.height;
+        let to_y = |value: f32| height - (value.min(max_value) / max_value) * height;
+        let to_x = |time: f32| ((time - start) / span) * width;
+
+        for band in &self.bands {
+            if band.from >= max_value {
+                continue;
+            }
+
+            let top = to_y(band.to);
+            let bottom = to_y(band.from);
+            frame.fill_rectangle(Point::new(0., top), Size::new(width, bottom - top), band.color);
+        }
+
+        if let Some(target) = self.target {
+            let y = to_y(target);
+            let target_line = Path::line(Point::new(0., y), Point::new(width, y));
+            frame.stroke(&target_line, Stroke::default().with_width(1.).with_color(Color::BLACK));
+        }
+
+        if points.len() > 1 {
+            let line = Path::new(|builder| {
+                let (time, value) = points[0];
+                builder.move_to(Point::new(to_x(time), to_y(value)));
+                for (time, value) in &points[1..] {
+                    builder.line_to(Point::new(to_x(*time), to_y(*value)));
+                }
+            });
+            frame.stroke(&line, Stroke::default().with_width(2.).with_color(self.color));
+        }
+
+        return vec![frame.into_geometry()];
+    }
+}
+
+pub fn zone_color(index: usize) -> Color {
+    match index {
+        0 => Color::from_rgb8(0x9e, 0x9e, 0x9e),
+        1 => Color::from_rgb8(0x1e, 0x88, 0xe5),
+        2 => Color::from_rgb8(0x43, 0xa0, 0x47),
+        3 => Color::from_rgb8(0xfd, 0xd8, 0x35),
+        4 => Color::from_rgb8(0xfb, 0x8c, 0x00),
+        5 => Color::from_rgb8(0xe5, 0x39, 0x35),
+        _ => Color::from_rgb8(0x8e, 0x24, 0xaa)
+    }
+}
+
+pub fn zone_bands(zones: &[Zone]) -> Vec<Band> {
+    return zones.iter()
+        .enumerate()
+        .map(|(index, zone)| Band {
+            from: zone.from,
+            to: zone.to,
+            color: Color { a: 0.15, ..zone_color(index) }
+        })
+        .collect();
+}
+
+// trailing moving average, the first values average over what is available
+pub fn smooth(values: &[f32], window: usize) -> Vec<f32> {
+    let window = window.max(1);
+    let mut result = Vec::with_capacity(values.len());
+    let mut sum = 0.;
+
+    for (index, value) in values.iter().enumerate() {
+        sum += value;
+        if index >= window {
+            sum -= values[index - window];
+        }
+        result.push(sum / (index + 1).min(window) as f32);
+    }
+
+    return result;
+}
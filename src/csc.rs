@@ -0,0 +1,72 @@
+// Cycling Speed and Cadence Service helpers, for trainers and sensors that only count wheel revolutions
+// REF: https://www.bluetooth.com/specifications/specs/cycling-speed-and-cadence-service-1-0/
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WheelRevolutions {
+    pub revolutions: u32,
+    // last wheel event in 1/1024 s, wraps every 64 s
+    pub event_time: u16
+}
+
+// CSC Measurement (0x2A5B), None when it carries no wheel data
+pub fn parse_csc_measurement(data: &[u8]) -> Option<WheelRevolutions> {
+    let flags = *data.first()?;
+    if flags & 0x01 == 0 {
+        return None;
+    }
+
+    let bytes = data.get(1..7)?;
+    return Some(WheelRevolutions {
+        revolutions: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        event_time: u16::from_le_bytes([bytes[4], bytes[5]])
+    });
+}
+
+// revolutions per second between two measurements, both counters wrap around
+pub fn wheel_rate(previous: WheelRevolutions, current: WheelRevolutions) -> Option<f32> {
+    let revolutions = current.revolutions.wrapping_sub(previous.revolutions);
+    if revolutions == 0 {
+        return Some(0.);
+    }
+
+    let ticks = current.event_time.wrapping_sub(previous.event_time);
+    if ticks == 0 {
+        return None;
+    }
+
+    return Some(revolutions as f32 * 1024. / ticks as f32);
+}
+
+// km/h, circumference in mm
+pub fn speed(wheel_rate: f32, circumference: u16) -> f32 {
+    return wheel_rate * circumference as f32 / 1000. * 3.6;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::csc::{WheelRevolutions, parse_csc_measurement, speed, wheel_rate};
+
+    #[test]
+    fn parses_wheel_revolutions() {
+        // flags: wheel and crank data
+        let data = [0x03, 0x10, 0x00, 0x00, 0x00, 0x00, 0x04, 0x05, 0x00, 0x00, 0x02];
+
+        let result = parse_csc_measurement(&data).unwrap();
+
+        assert_eq!(result, WheelRevolutions { revolutions: 16, event_time: 1024 });
+        assert_eq!(parse_csc_measurement(&[0x02, 0x05, 0x00, 0x00, 0x02]), None);
+    }
+
+    #[test]
+    fn speed_from_wheel_revolutions() {
+        let previous = WheelRevolutions { revolutions: u32::MAX, event_time: 65_000 };
+        // 4 revolutions in one second, across both wraps
+        let current = WheelRevolutions { revolutions: 3, event_time: 65_000u16.wrapping_add(1024) };
+
+        let rate = wheel_rate(previous, current).unwrap();
+
+        assert_eq!(rate, 4.);
+        assert!((speed(rate, 2105) - 30.31).abs() < 0.01);
+        assert_eq!(wheel_rate(current, current), Some(0.));
+    }
+}
@@ -0,0 +1,196 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use iced::widget::canvas::{self, Canvas, Cursor, Frame, Geometry, Path, Stroke};
+use iced::widget::{button, column, pick_list, row, text};
+use iced::{Alignment, Color, Element, Length, Point, Rectangle, Renderer, Theme};
+
+use crate::power_curve::{CriticalPower, DateRange, PowerCurve};
+use crate::profile::Date;
+use crate::recording::Recording;
+
+#[derive(Debug, Clone)]
+pub enum CurveMessage {
+    RangeSelected(DateRange),
+    Close
+}
+
+#[derive(Debug, Clone)]
+pub enum CurveEvent {
+    Closed
+}
+
+// best efforts across the ride history
+#[derive(Debug, Clone)]
+pub struct Curve {
+    // one curve per saved ride, oldest first
+    rides: Vec<(SystemTime, PowerCurve)>,
+    range: DateRange,
+    curve: PowerCurve,
+    two_parameter: Option<CriticalPower>,
+    three_parameter: Option<CriticalPower>
+}
+
+// drawn on a log scale, (curve, color)
+struct CurveChart {
+    lines: Vec<(Vec<(f32, f32)>, Color)>
+}
+
+const BEST_EFFORTS: [(u32, &str); 6] = [
+    (5, "5 s"),
+    (60, "1 min"),
+    (300, "5 min"),
+    (1200, "20 min"),
+    (3600, "60 min"),
+    (7200, "2 h")
+];
+
+impl Curve {
+    pub fn new(rides: &[Recording]) -> Curve {
+        let rides = rides.iter()
+            .map(|ride| {
+                let power: Vec<f32> = ride.samples.iter().map(|x| x.power as f32).collect();
+                (ride.started_at, PowerCurve::new(&power, ride.started_at))
+            })
+            .collect();
+
+        let mut curve = Curve {
+            rides,
+            range: DateRange::ThreeMonths,
+            curve: PowerCurve::default(),
+            two_parameter: None,
+            three_parameter: None
+        };
+        curve.refresh();
+        return curve;
+    }
+
+    pub fn update(&mut self, message: CurveMessage) -> Option<CurveEvent> {
+        match message {
+            CurveMessage::RangeSelected(range) => {
+                self.range = range;
+                self.refresh();
+            }
+            CurveMessage::Close => return Some(CurveEvent::Closed)
+        }
+        return None;
+    }
+
+    fn refresh(&mut self) {
+        let now = SystemTime::now();
+        let mut curve = PowerCurve::default();
+        for (_, ride) in self.rides.iter().filter(|(at, _)| self.range.contains(*at, now)) {
+            curve.merge(ride);
+        }
+
+        self.two_parameter = CriticalPower::fit_two_parameter(&curve);
+        self.three_parameter = CriticalPower::fit_three_parameter(&curve);
+        self.curve = curve;
+    }
+
+    pub fn view(&self) -> Element<CurveMessage> {
+        let to_points = |curve: &PowerCurve| -> Vec<(f32, f32)> {
+            return curve.efforts.iter().map(|x| (x.duration as f32, x.power)).collect();
+        };
+
+        let mut lines = Vec::new();
+        if let Some((_, latest)) = self.rides.last() {
+            lines.push((to_points(latest), Color::from_rgb8(0xbd, 0xbd, 0xbd)));
+        }
+        if let Some(model) = self.three_parameter {
+            let model_points = self.curve.efforts.iter()
+                .map(|x| (x.duration as f32, model.power(x.duration as f32)))
+                .collect();
+            lines.push((model_points, Color::from_rgb8(0xfb, 0x8c, 0x00)));
+        }
+        lines.push((to_points(&self.curve), Color::from_rgb8(0x1e, 0x88, 0xe5)));
+
+        let best_efforts = row(
+            BEST_EFFORTS.iter()
+                .filter_map(|(duration, label)| {
+                    let effort = self.curve.best(*duration)?;
+                    let days = effort.recorded_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86_400;
+                    Some(column![
+                        text(label),
+                        text(format!("{:.0} W", effort.power)).size(24),
+                        text(Date::from_unix_days(days as i64).to_string())
+                    ]
+                    .align_items(Alignment::Center)
+                    .into())
+                })
+                .collect()
+        )
+        .spacing(20);
+
+        let models = column![
+            text(self.two_parameter.map_or(String::from("2-parameter: not enough efforts between 2 and 20 min"), |x| format!(
+                "2-parameter: CP {:.0} W, W' {:.1} kJ",
+                x.cp,
+                x.w_prime / 1000.
+            ))),
+            text(self.three_parameter.map_or(String::from("3-parameter: not enough efforts"), |x| format!(
+                "3-parameter: CP {:.0} W, W' {:.1} kJ, Pmax {:.0} W",
+                x.cp,
+                x.w_prime / 1000.,
+                x.p_max.unwrap_or_default()
+            )))
+        ]
+        .spacing(5);
+
+        return column![
+            row![
+                text("Power curve").size(30),
+                pick_list(&DateRange::ALL[..], Some(self.range), CurveMessage::RangeSelected),
+                button("Close").on_press(CurveMessage::Close).padding(5.)
+            ]
+            .spacing(20)
+            .align_items(Alignment::Center),
+            Canvas::new(CurveChart { lines }).width(Length::Fill).height(Length::Fixed(300.)),
+            best_efforts,
+            models
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .into();
+    }
+}
+
+impl<Message> canvas::Program<Message> for CurveChart {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: Cursor
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+
+        let points = self.lines.iter().flat_map(|(points, _)| points);
+        let max_duration = points.clone().map(|(duration, _)| *duration).fold(2., f32::max);
+        let max_value = points.map(|(_, value)| *value).fold(1., f32::max) * 1.1;
+
+        let width = bounds.width;
+        let height = bounds.height;
+        // 1 s sits on the left edge
+        let to_x = |duration: f32| duration.max(1.).ln() / max_duration.ln() * width;
+        let to_y = |value: f32| height - (value.min(max_value) / max_value) * height;
+
+        for (points, color) in &self.lines {
+            if points.len() < 2 {
+                continue;
+            }
+
+            let line = Path::new(|builder| {
+                builder.move_to(Point::new(to_x(points[0].0), to_y(points[0].1)));
+                for (duration, value) in points.iter().skip(1) {
+                    builder.line_to(Point::new(to_x(*duration), to_y(*value)));
+                }
+            });
+            frame.stroke(&line, Stroke::default().with_width(2.).with_color(*color));
+        }
+
+        return vec![frame.into_geometry()];
+    }
+}
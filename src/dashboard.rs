@@ -0,0 +1,348 @@
+// live metrics as tiles, which ones, their size and order are kept in the profile
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::profile::Units;
+use crate::stopwatch::format_duration;
+
+pub const COLUMNS: [usize; 6] = [1, 2, 3, 4, 5, 6];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileMetric {
+    HeartRate,
+    Power,
+    ThreeSecondPower,
+    Cadence,
+    Speed,
+    Distance,
+    Zone,
+    Time,
+    LapTime,
+    NormalizedPower,
+    Work,
+    WPrimeBalance,
+    Target
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileSize {
+    Small,
+    Medium,
+    Large
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub metric: TileMetric,
+    pub size: TileSize
+}
+
+// tiles fill the rows left to right
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dashboard {
+    pub tiles: Vec<Tile>,
+    pub columns: usize
+}
+
+// values shown on the tiles, taken every tick
+#[derive(Debug, Clone)]
+pub struct Readings {
+    pub heart_rate: u8,
+    pub power: u16,
+    pub three_second_power: f32,
+    pub cadence: u8,
+    pub speed: f32, // km/h
+    pub distance: f32, // m
+    pub heart_rate_zone: Option<usize>,
+    pub power_zone: Option<usize>,
+    pub elapsed: Duration,
+    pub lap: usize,
+    pub lap_time: Duration,
+    pub normalized_power: Option<f32>,
+    pub work: f32, // kJ
+    pub w_prime_balance: f32, // J
+    pub target_power: Option<u16>
+}
+
+#[derive(Debug, Clone)]
+pub enum DashboardMessage {
+    Add(TileMetric),
+    Remove(usize),
+    MoveLeft(usize),
+    MoveRight(usize),
+    SizeSelected(usize, TileSize),
+    ColumnsSelected(usize),
+    Reset
+}
+
+impl Dashboard {
+    pub fn new() -> Dashboard {
+        return Dashboard {
+            tiles: vec![
+                Tile { metric: TileMetric::HeartRate, size: TileSize::Large },
+                Tile { metric: TileMetric::Power, size: TileSize::Large },
+                Tile { metric: TileMetric::Zone, size: TileSize::Medium },
+                Tile { metric: TileMetric::Time, size: TileSize::Large },
+                Tile { metric: TileMetric::Cadence, size: TileSize::Medium },
+                Tile { metric: TileMetric::Speed, size: TileSize::Medium },
+                Tile { metric: TileMetric::Distance, size: TileSize::Medium },
+                Tile { metric: TileMetric::LapTime, size: TileSize::Medium }
+            ],
+            columns: 4
+        };
+    }
+
+    pub fn update(&mut self, message: DashboardMessage) {
+        match message {
+            DashboardMessage::Add(metric) => {
+                if !self.tiles.iter().any(|x| x.metric == metric) {
+                    self.tiles.push(Tile { metric, size: TileSize::Medium });
+                }
+            }
+            DashboardMessage::Remove(index) => {
+                if index < self.tiles.len() {
+                    self.tiles.remove(index);
+                }
+            }
+            DashboardMessage::MoveLeft(index) => {
+                if index > 0 && index < self.tiles.len() {
+                    self.tiles.swap(index - 1, index);
+                }
+            }
+            DashboardMessage::MoveRight(index) => {
+                if index + 1 < self.tiles.len() {
+                    self.tiles.swap(index, index + 1);
+                }
+            }
+            DashboardMessage::SizeSelected(index, size) => {
+                if let Some(tile) = self.tiles.get_mut(index) {
+                    tile.size = size;
+                }
+            }
+            DashboardMessage::ColumnsSelected(columns) => {
+                self.columns = columns.clamp(1, COLUMNS.len());
+            }
+            DashboardMessage::Reset => {
+                *self = Dashboard::new();
+            }
+        }
+    }
+
+    // metrics that can still be added
+    pub fn available(&self) -> Vec<TileMetric> {
+        return TileMetric::ALL.into_iter()
+            .filter(|metric| !self.tiles.iter().any(|x| x.metric == *metric))
+            .collect();
+    }
+
+    // e.g. heart_rate:large,power:medium
+    pub fn key(&self) -> String {
+        return self.tiles.iter()
+            .map(|x| format!("{}:{}", x.metric.key(), x.size.key()))
+            .collect::<Vec<String>>()
+            .join(",");
+    }
+}
+
+impl TileMetric {
+    pub const ALL: [TileMetric; 13] = [
+        TileMetric::HeartRate,
+        TileMetric::Power,
+        TileMetric::ThreeSecondPower,
+        TileMetric::Cadence,
+        TileMetric::Speed,
+        TileMetric::Distance,
+        TileMetric::Zone,
+        TileMetric::Time,
+        TileMetric::LapTime,
+        TileMetric::NormalizedPower,
+        TileMetric::Work,
+        TileMetric::WPrimeBalance,
+        TileMetric::Target
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            TileMetric::HeartRate => "heart_rate",
+            TileMetric::Power => "power",
+            TileMetric::ThreeSecondPower => "power_3s",
+            TileMetric::Cadence => "cadence",
+            TileMetric::Speed => "speed",
+            TileMetric::Distance => "distance",
+            TileMetric::Zone => "zone",
+            TileMetric::Time => "time",
+            TileMetric::LapTime => "lap_time",
+            TileMetric::NormalizedPower => "normalized_power",
+            TileMetric::Work => "work",
+            TileMetric::WPrimeBalance => "w_prime_balance",
+            TileMetric::Target => "target"
+        }
+    }
+
+    pub fn value(&self, readings: &Readings, units: Units) -> String {
+        return match self {
+            TileMetric::HeartRate => format!("{} bpm", readings.heart_rate),
+            TileMetric::Power => format!("{} W", readings.power),
+            TileMetric::ThreeSecondPower => format!("{:.0} W", readings.three_second_power),
+            TileMetric::Cadence => format!("{} rpm", readings.cadence),
+            TileMetric::Speed => units.format_speed(readings.speed),
+            TileMetric::Distance => units.format_distance(readings.distance),
+            TileMetric::Zone => format!(
+                "Z{} / Z{}",
+                readings.power_zone.map_or(String::from("-"), |x| (x + 1).to_string()),
+                readings.heart_rate_zone.map_or(String::from("-"), |x| (x + 1).to_string())
+            ),
+            TileMetric::Time => format_duration(readings.elapsed),
+            TileMetric::LapTime => format!("{}  {}", readings.lap, format_duration(readings.lap_time)),
+            TileMetric::NormalizedPower => readings.normalized_power.map_or(String::from("-"), |x| format!("{:.0} W", x)),
+            TileMetric::Work => format!("{:.0} kJ", readings.work),
+            TileMetric::WPrimeBalance => format!("{:.1} kJ", readings.w_prime_balance.max(0.) / 1000.),
+            TileMetric::Target => readings.target_power.map_or(String::from("-"), |x| format!("{} W", x))
+        };
+    }
+}
+
+impl fmt::Display for TileMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            TileMetric::HeartRate => "Heart rate",
+            TileMetric::Power => "Power",
+            TileMetric::ThreeSecondPower => "3s power",
+            TileMetric::Cadence => "Cadence",
+            TileMetric::Speed => "Speed",
+            TileMetric::Distance => "Distance",
+            TileMetric::Zone => "Zone (power / HR)",
+            TileMetric::Time => "Time",
+            TileMetric::LapTime => "Lap",
+            TileMetric::NormalizedPower => "NP",
+            TileMetric::Work => "Work",
+            TileMetric::WPrimeBalance => "W' balance",
+            TileMetric::Target => "Target"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+impl TileSize {
+    pub const ALL: [TileSize; 3] = [TileSize::Small, TileSize::Medium, TileSize::Large];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            TileSize::Small => "small",
+            TileSize::Medium => "medium",
+            TileSize::Large => "large"
+        }
+    }
+
+    // width of the tile and text size of the value
+    pub fn dimensions(&self) -> (f32, u16) {
+        match self {
+            TileSize::Small => (130., 24),
+            TileSize::Medium => (190., 36),
+            TileSize::Large => (260., 56)
+        }
+    }
+}
+
+impl fmt::Display for TileSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            TileSize::Small => "Small",
+            TileSize::Medium => "Medium",
+            TileSize::Large => "Large"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+pub fn parse_tiles(value: &str) -> Option<Vec<Tile>> {
+    return value.split(',')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            let (metric, size) = x.split_once(':')?;
+            return Some(Tile {
+                metric: TileMetric::ALL.into_iter().find(|x| x.key() == metric)?,
+                size: TileSize::ALL.into_iter().find(|x| x.key() == size)?
+            });
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::dashboard::{parse_tiles, Dashboard, DashboardMessage, Readings, TileMetric, TileSize};
+    use crate::profile::Units;
+
+    fn readings() -> Readings {
+        return Readings {
+            heart_rate: 142,
+            power: 230,
+            three_second_power: 226.7,
+            cadence: 88,
+            speed: 32.2,
+            distance: 12_345.,
+            heart_rate_zone: Some(2),
+            power_zone: None,
+            elapsed: Duration::from_secs(3_725),
+            lap: 3,
+            lap_time: Duration::from_secs(95),
+            normalized_power: None,
+            work: 812.4,
+            w_prime_balance: -150.,
+            target_power: Some(250)
+        };
+    }
+
+    #[test]
+    fn layout_round_trips() {
+        let mut dashboard = Dashboard::new();
+        dashboard.update(DashboardMessage::Add(TileMetric::WPrimeBalance));
+        dashboard.update(DashboardMessage::SizeSelected(0, TileSize::Small));
+
+        assert_eq!(parse_tiles(&dashboard.key()), Some(dashboard.tiles.clone()));
+        assert_eq!(parse_tiles(""), Some(Vec::new()));
+        assert_eq!(parse_tiles("heart_rate:huge"), None);
+        assert_eq!(parse_tiles("altitude:small"), None);
+    }
+
+    #[test]
+    fn edits_tiles() {
+        let mut dashboard = Dashboard::new();
+        let count = dashboard.tiles.len();
+
+        dashboard.update(DashboardMessage::Add(TileMetric::Power));
+        assert_eq!(dashboard.tiles.len(), count);
+
+        dashboard.update(DashboardMessage::MoveRight(0));
+        assert_eq!(dashboard.tiles[1].metric, TileMetric::HeartRate);
+        dashboard.update(DashboardMessage::MoveLeft(0));
+        dashboard.update(DashboardMessage::MoveRight(count - 1));
+        assert_eq!(dashboard.tiles[0].metric, TileMetric::Power);
+
+        dashboard.update(DashboardMessage::Remove(0));
+        assert!(dashboard.available().contains(&TileMetric::Power));
+
+        dashboard.update(DashboardMessage::ColumnsSelected(10));
+        assert_eq!(dashboard.columns, 6);
+
+        dashboard.update(DashboardMessage::Reset);
+        assert_eq!(dashboard, Dashboard::new());
+    }
+
+    #[test]
+    fn formats_values() {
+        let readings = readings();
+
+        assert_eq!(TileMetric::ThreeSecondPower.value(&readings, Units::Metric), "227 W");
+        assert_eq!(TileMetric::Speed.value(&readings, Units::Imperial), "20.0 mph");
+        assert_eq!(TileMetric::Zone.value(&readings, Units::Metric), "Z- / Z3");
+        assert_eq!(TileMetric::Time.value(&readings, Units::Metric), "01:02:05");
+        assert_eq!(TileMetric::NormalizedPower.value(&readings, Units::Metric), "-");
+        assert_eq!(TileMetric::WPrimeBalance.value(&readings, Units::Metric), "0.0 kJ");
+        assert_eq!(TileMetric::Target.value(&readings, Units::Metric), "250 W");
+    }
+}
@@ -0,0 +1,56 @@
+use iced::widget::canvas::{self, Cursor, Frame, Geometry, Path, Stroke};
+use iced::{Color, Point, Rectangle, Renderer, Theme};
+
+use crate::route::RoutePoint;
+
+// route profile with the rider's position
+pub struct ElevationProfile {
+    pub points: Vec<RoutePoint>,
+    // m along the route
+    pub position: f32
+}
+
+impl<Message> canvas::Program<Message> for ElevationProfile {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: Cursor
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+            return vec![frame.into_geometry()];
+        };
+
+        let min_elevation = self.points.iter().map(|x| x.elevation).fold(f32::MAX, f32::min);
+        let max_elevation = self.points.iter().map(|x| x.elevation).fold(f32::MIN, f32::max);
+        // keep flat routes from filling the whole height
+        let span = (max_elevation - min_elevation).max(50.);
+
+        let width = bounds.width;
+        let height = bounds.height;
+        let length = (last.distance - first.distance).max(1.);
+        let to_x = |distance: f32| (distance - first.distance) / length * width;
+        let to_y = |elevation: f32| height - (elevation - min_elevation) / span * height * 0.9;
+
+        let area = Path::new(|builder| {
+            builder.move_to(Point::new(0., height));
+            for point in &self.points {
+                builder.line_to(Point::new(to_x(point.distance), to_y(point.elevation)));
+            }
+            builder.line_to(Point::new(width, height));
+            builder.close();
+        });
+        frame.fill(&area, Color::from_rgba8(0x43, 0xa0, 0x47, 0.4));
+
+        let x = to_x(self.position.clamp(first.distance, last.distance));
+        let marker = Path::line(Point::new(x, 0.), Point::new(x, height));
+        frame.stroke(&marker, Stroke::default().with_width(2.).with_color(Color::from_rgb8(0xe5, 0x39, 0x35)));
+
+        return vec![frame.into_geometry()];
+    }
+}
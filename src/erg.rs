@@ -0,0 +1,312 @@
+// ERG (absolute watts) and MRC (percent of FTP) course files
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::workout::{Block, Cue, Power, Step, Target, Workout};
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ErgError {
+    #[error("Failed to read the workout file: {0}")]
+    ReadFailed(String),
+    #[error("The file has no [COURSE DATA] section")]
+    MissingCourseData,
+    #[error("Invalid line {0}: {1}")]
+    InvalidLine(usize, String)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Erg,
+    Mrc
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    None,
+    Header,
+    Data,
+    Text
+}
+
+// how long a cue stays on screen when exported
+const CUE_DURATION: u64 = 10;
+
+impl Format {
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        match extension.to_lowercase().as_str() {
+            "erg" => Some(Format::Erg),
+            "mrc" => Some(Format::Mrc),
+            _ => None
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Erg => "erg",
+            Format::Mrc => "mrc"
+        }
+    }
+}
+
+pub fn load(path: &Path, format: Format) -> Result<Workout, ErgError> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| ErgError::ReadFailed(err.to_string()))?;
+
+    let mut workout = parse(&content, format)?;
+    if workout.name.is_empty() {
+        workout.name = path.file_stem()
+            .map_or(String::from("Unnamed workout"), |x| x.to_string_lossy().to_string());
+    }
+    return Ok(workout);
+}
+
+pub fn parse(content: &str, format: Format) -> Result<Workout, ErgError> {
+    let mut section = Section::None;
+    let mut name = String::new();
+    let mut description = String::new();
+    let mut points: Vec<(f32, f32)> = Vec::new(); // (minutes, watts or percent)
+    let mut cues: Vec<(Duration, String)> = Vec::new();
+    let mut has_data = false;
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+
+        match line.to_uppercase().as_str() {
+            "[COURSE HEADER]" => { section = Section::Header; continue; }
+            "[COURSE DATA]" => { section = Section::Data; has_data = true; continue; }
+            "[COURSE TEXT]" => { section = Section::Text; continue; }
+            x if x.starts_with("[END") => { section = Section::None; continue; }
+            _ => {}
+        }
+
+        match section {
+            Section::Header => {
+                // "MINUTES WATTS" style column headers have no '='
+                let Some((key, value)) = line.split_once('=') else {
+                    continue;
+                };
+                match key.trim().to_uppercase().as_str() {
+                    "FILE NAME" => name = value.trim().to_string(),
+                    "DESCRIPTION" => description = value.trim().to_string(),
+                    _ => {}
+                }
+            }
+            Section::Data => {
+                let mut columns = line.split_whitespace().map(|x| x.parse::<f32>());
+                match (columns.next(), columns.next()) {
+                    (Some(Ok(minutes)), Some(Ok(value))) => points.push((minutes, value)),
+                    _ => return Err(ErgError::InvalidLine(index + 1, line.to_string()))
+                }
+            }
+            Section::Text => {
+                let columns: Vec<&str> = line.split('\t').map(|x| x.trim()).collect();
+                let seconds = columns.first()
+                    .and_then(|x| x.parse::<f32>().ok())
+                    .ok_or_else(|| ErgError::InvalidLine(index + 1, line.to_string()))?;
+                let message = columns.get(1).unwrap_or(&"").to_string();
+                cues.push((Duration::from_secs_f32(seconds.max(0.)), message));
+            }
+            Section::None => {}
+        }
+    }
+
+    if !has_data {
+        return Err(ErgError::MissingCourseData);
+    }
+
+    let power = |value: f32| match format {
+        Format::Erg => Power::Watts(value.round().max(0.) as u16),
+        Format::Mrc => Power::Ftp(value / 100.)
+    };
+
+    // consecutive points describe a segment, equal times are a jump to a new target
+    let mut steps: Vec<Step> = Vec::new();
+    for pair in points.windows(2) {
+        let (start, from) = pair[0];
+        let (end, to) = pair[1];
+        if end <= start {
+            continue;
+        }
+
+        // minutes are written with a few decimals, steps snap back to whole seconds
+        let duration = Duration::from_secs(((end * 60.).round() - (start * 60.).round()) as u64);
+        let step = match from == to {
+            true => Step::steady(duration, power(from)),
+            false => Step::ramp(duration, power(from), power(to))
+        };
+        steps.push(step);
+    }
+
+    for (at, message) in cues {
+        let mut offset = at;
+        for step in steps.iter_mut() {
+            if offset < step.duration {
+                step.cues.push(Cue { offset, message });
+                break;
+            }
+            offset -= step.duration;
+        }
+    }
+
+    return Ok(Workout {
+        name,
+        description,
+        blocks: steps.into_iter().map(Block::Step).collect()
+    });
+}
+
+pub fn export(workout: &Workout, format: Format, ftp: u16) -> String {
+    // free ride and max effort have no target in these formats, they are written as 0
+    let value = |power: &Power| match format {
+        Format::Erg => power.watts(ftp),
+        Format::Mrc => power.watts(ftp) / ftp.max(1) as f32 * 100.
+    };
+
+    let mut lines: Vec<String> = vec![
+        String::from("[COURSE HEADER]"),
+        String::from("VERSION = 2"),
+        String::from("UNITS = ENGLISH"),
+        format!("DESCRIPTION = {}", workout.description),
+        format!("FILE NAME = {}", workout.name)
+    ];
+    match format {
+        Format::Erg => {
+            lines.push(format!("FTP = {}", ftp));
+            lines.push(String::from("MINUTES WATTS"));
+        }
+        Format::Mrc => lines.push(String::from("MINUTES PERCENT"))
+    }
+    lines.push(String::from("[END COURSE HEADER]"));
+    lines.push(String::from("[COURSE DATA]"));
+
+    let precision = match format {
+        Format::Erg => 0,
+        Format::Mrc => 1
+    };
+
+    let mut cues: Vec<String> = Vec::new();
+    let mut start = Duration::default();
+    for step in workout.steps() {
+        let (from, to) = match &step.target {
+            Target::Steady(power) => (value(power), value(power)),
+            Target::Ramp { from, to } => (value(from), value(to)),
+            Target::FreeRide | Target::MaxEffort => (0., 0.)
+        };
+        let end = start + step.duration;
+
+        lines.push(format!("{:.4}\t{:.*}", start.as_secs_f32() / 60., precision, from));
+        lines.push(format!("{:.4}\t{:.*}", end.as_secs_f32() / 60., precision, to));
+
+        for cue in &step.cues {
+            cues.push(format!("{}\t{}\t{}", (start + cue.offset).as_secs(), cue.message, CUE_DURATION));
+        }
+        start = end;
+    }
+    lines.push(String::from("[END COURSE DATA]"));
+
+    if !cues.is_empty() {
+        lines.push(String::from("[COURSE TEXT]"));
+        lines.extend(cues);
+        lines.push(String::from("[END COURSE TEXT]"));
+    }
+
+    return lines.join("\n") + "\n";
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::erg::{export, parse, ErgError, Format};
+    use crate::workout::{Block, Power, Step, Target, Workout};
+
+    const ERG: &str = "[COURSE HEADER]
+VERSION = 2
+UNITS = ENGLISH
+DESCRIPTION = 2 x 10 min at threshold
+FILE NAME = Threshold 2x10
+FTP = 250
+MINUTES WATTS
+[END COURSE HEADER]
+[COURSE DATA]
+0.00\t100
+10.00\t200
+10.00\t250
+20.00\t250
+20.00\t125
+25.00\t125
+[END COURSE DATA]
+[COURSE TEXT]
+600\tFirst interval\t10
+1230\tHalfway\t10
+[END COURSE TEXT]
+";
+
+    #[test]
+    fn parses_erg_segments_and_text() {
+        let workout = parse(ERG, Format::Erg).unwrap();
+        let steps = workout.steps();
+
+        assert_eq!(workout.name, "Threshold 2x10");
+        assert_eq!(workout.description, "2 x 10 min at threshold");
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].target, Target::Ramp { from: Power::Watts(100), to: Power::Watts(200) });
+        assert_eq!(steps[1].target, Target::Steady(Power::Watts(250)));
+        assert_eq!(steps[1].cues[0].message, "First interval");
+        assert_eq!(steps[2].cues[0].offset, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn parses_mrc_as_percent_of_ftp() {
+        let content = "[COURSE HEADER]\nMINUTES PERCENT\n[END COURSE HEADER]\n[COURSE DATA]\n0\t50\n5\t50\n[END COURSE DATA]\n";
+        let workout = parse(content, Format::Mrc).unwrap();
+
+        assert_eq!(workout.steps()[0].target, Target::Steady(Power::Ftp(0.5)));
+        assert_eq!(workout.duration(), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn export_round_trips() {
+        let workout = parse(ERG, Format::Erg).unwrap();
+
+        let mrc = parse(&export(&workout, Format::Mrc, 250), Format::Mrc).unwrap();
+        let erg = parse(&export(&mrc, Format::Erg, 250), Format::Erg).unwrap();
+
+        assert_eq!(mrc.steps()[1].target, Target::Steady(Power::Ftp(1.)));
+        assert_eq!(erg, workout);
+    }
+
+    #[test]
+    fn short_steps_round_trip() {
+        let workout = Workout {
+            name: String::from("Sprints"),
+            description: String::new(),
+            blocks: vec![
+                Block::Step(Step::steady(Duration::from_secs(10), Power::Watts(500))),
+                Block::Step(Step::steady(Duration::from_secs(50), Power::Watts(100))),
+                Block::Step(Step::steady(Duration::from_secs(10), Power::Watts(500)))
+            ]
+        };
+
+        for format in [Format::Erg, Format::Mrc] {
+            let parsed = parse(&export(&workout, format, 250), format).unwrap();
+            let durations: Vec<Duration> = parsed.steps().iter().map(|x| x.duration).collect();
+            assert_eq!(durations, vec![Duration::from_secs(10), Duration::from_secs(50), Duration::from_secs(10)]);
+        }
+    }
+
+    #[test]
+    fn reports_invalid_data() {
+        let content = "[COURSE DATA]\n0\t100\nten\t200\n[END COURSE DATA]\n";
+
+        assert_eq!(parse(content, Format::Erg), Err(ErgError::InvalidLine(3, String::from("ten\t200"))));
+        assert_eq!(parse("[COURSE HEADER]\n", Format::Erg), Err(ErgError::MissingCourseData));
+    }
+}
@@ -0,0 +1,518 @@
+// Garmin FIT activities, only the messages needed for a ride are decoded
+// REF: https://developer.garmin.com/fit/protocol/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use thiserror::Error;
+
+use crate::recording::{EventKind, Recording, Sample};
+
+// seconds between the unix epoch and the FIT epoch, 1989-12-31 UTC
+const FIT_EPOCH: u64 = 631_065_600;
+// longer gaps between records are treated as pauses, s
+const MAX_GAP: u32 = 10;
+
+// global message numbers
+const FILE_ID: u16 = 0;
+const SESSION: u16 = 18;
+const LAP: u16 = 19;
+const RECORD: u16 = 20;
+const EVENT: u16 = 21;
+const DEVICE_INFO: u16 = 23;
+
+// field number shared by all messages
+const TIMESTAMP: u8 = 253;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum FitError {
+    #[error("Failed to read the FIT file: {0}")]
+    ReadFailed(String),
+    #[error("Not a FIT file")]
+    InvalidHeader,
+    #[error("The FIT file is cut off")]
+    Truncated,
+    #[error("The FIT file is corrupted")]
+    InvalidChecksum,
+    #[error("Data message without a definition for local type {0}")]
+    MissingDefinition(u8),
+    #[error("The FIT file has no records")]
+    NoRecords
+}
+
+#[derive(Debug, Clone)]
+struct FieldDefinition {
+    number: u8,
+    size: u8,
+    base_type: u8
+}
+
+#[derive(Debug, Clone)]
+struct Definition {
+    global: u16,
+    big_endian: bool,
+    fields: Vec<FieldDefinition>,
+    // developer fields are skipped
+    developer_size: usize
+}
+
+// valid values of a data message by field number
+#[derive(Debug, Clone)]
+struct Message {
+    global: u16,
+    fields: Vec<(u8, u32)>
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize
+}
+
+impl Message {
+    fn get(&self, number: u8) -> Option<u32> {
+        return self.fields.iter().find(|x| x.0 == number).map(|x| x.1);
+    }
+
+    fn is(&self, global: u16) -> bool {
+        return self.global == global;
+    }
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], FitError> {
+        let bytes = self.data.get(self.position..self.position + count).ok_or(FitError::Truncated)?;
+        self.position += count;
+        return Ok(bytes);
+    }
+
+    fn byte(&mut self) -> Result<u8, FitError> {
+        return Ok(self.take(1)?[0]);
+    }
+
+    fn definition(&mut self, developer: bool) -> Result<Definition, FitError> {
+        let header = self.take(5)?;
+        let big_endian = header[1] == 1;
+        let global = match big_endian {
+            true => u16::from_be_bytes([header[2], header[3]]),
+            false => u16::from_le_bytes([header[2], header[3]])
+        };
+
+        let mut fields = Vec::new();
+        for _ in 0..header[4] {
+            let field = self.take(3)?;
+            fields.push(FieldDefinition { number: field[0], size: field[1], base_type: field[2] });
+        }
+
+        let mut developer_size = 0;
+        if developer {
+            for _ in 0..self.byte()? {
+                developer_size += self.take(3)?[1] as usize;
+            }
+        }
+
+        return Ok(Definition { global, big_endian, fields, developer_size });
+    }
+
+    fn message(&mut self, definition: &Definition) -> Result<Message, FitError> {
+        let mut fields = Vec::new();
+        for field in &definition.fields {
+            let bytes = self.take(field.size as usize)?;
+            if let Some(value) = value(bytes, field.base_type, definition.big_endian) {
+                fields.push((field.number, value));
+            }
+        }
+        self.take(definition.developer_size)?;
+        return Ok(Message { global: definition.global, fields });
+    }
+}
+
+pub fn load(path: &Path) -> Result<Recording, FitError> {
+    let data = fs::read(path).map_err(|err| FitError::ReadFailed(err.to_string()))?;
+    return decode(&data);
+}
+
+// a single file or every .fit file in a folder, with the files that could not be read
+pub fn import(path: &Path) -> (Vec<Recording>, Vec<String>) {
+    let mut rides = Vec::new();
+    let mut errors = Vec::new();
+
+    let paths = match path.is_dir() {
+        true => match fs::read_dir(path) {
+            Ok(entries) => {
+                let mut paths: Vec<PathBuf> = entries.filter_map(|x| x.ok())
+                    .map(|x| x.path())
+                    .filter(|x| x.extension().and_then(|x| x.to_str()).is_some_and(|x| x.eq_ignore_ascii_case("fit")))
+                    .collect();
+                paths.sort();
+                paths
+            }
+            Err(err) => {
+                errors.push(format!("{:?}: {}", path, err));
+                return (rides, errors);
+            }
+        },
+        false => vec![path.to_path_buf()]
+    };
+
+    for path in paths {
+        match load(&path) {
+            Ok(ride) => rides.push(ride),
+            Err(err) => errors.push(format!("{:?}: {}", path, err))
+        }
+    }
+
+    return (rides, errors);
+}
+
+// the same ride recorded by the same device, also within the imported files,
+// returns the new rides and how many were skipped
+pub fn deduplicate(imported: Vec<Recording>, existing: &[&Recording]) -> (Vec<Recording>, usize) {
+    let mut rides: Vec<Recording> = Vec::new();
+    let mut duplicates = 0;
+    for ride in imported {
+        let same = |x: &Recording| x.started_at == ride.started_at && x.device_serial == ride.device_serial;
+        match existing.iter().any(|x| same(x)) || rides.iter().any(same) {
+            true => duplicates += 1,
+            false => rides.push(ride)
+        }
+    }
+    return (rides, duplicates);
+}
+
+// records become one sample per second of moving time, like a ride recorded live
+pub fn decode(data: &[u8]) -> Result<Recording, FitError> {
+    let messages = messages(data)?;
+    let records: Vec<&Message> = messages.iter()
+        .filter(|x| x.is(RECORD) && x.get(TIMESTAMP).is_some())
+        .collect();
+    let Some(first) = records.first().and_then(|x| x.get(TIMESTAMP)) else {
+        return Err(FitError::NoRecords);
+    };
+    let start = messages.iter()
+        .find(|x| x.is(SESSION))
+        .and_then(|x| x.get(2))
+        .map_or(first, |x| x.min(first));
+    // timer stop and stop all events
+    let stops: Vec<u32> = messages.iter()
+        .filter(|x| x.is(EVENT) && x.get(0) == Some(0) && matches!(x.get(1), Some(1) | Some(4)))
+        .filter_map(|x| x.get(TIMESTAMP))
+        .collect();
+
+    let mut recording = Recording::new();
+    recording.started_at = UNIX_EPOCH + Duration::from_secs(FIT_EPOCH + start as u64);
+    recording.device_serial = serial(&messages);
+    recording.add_event(Duration::default(), EventKind::Start);
+
+    // wall clock and moving time of every record, to place the laps
+    let mut timeline: Vec<(u32, Duration)> = Vec::new();
+    let mut elapsed = Duration::default();
+    let mut previous = start;
+    for record in records {
+        let timestamp = record.get(TIMESTAMP).unwrap_or(previous);
+        if !timeline.is_empty() && timestamp <= previous {
+            continue;
+        }
+
+        let gap = timestamp.saturating_sub(previous);
+        let paused = gap > MAX_GAP || stops.iter().any(|x| *x >= previous && *x < timestamp);
+        if paused && !timeline.is_empty() {
+            recording.add_event(elapsed, EventKind::Pause);
+            recording.add_event(elapsed, EventKind::Resume);
+            // one second of the gap is ridden, the rest is the pause
+            recording.add_pause(elapsed, Duration::from_secs(gap.saturating_sub(1) as u64));
+        }
+
+        let sample = Sample {
+            elapsed,
+            heart_rate: record.get(3).unwrap_or_default() as u8,
+            power: record.get(7).unwrap_or_default() as u16,
+            cadence: record.get(4).unwrap_or_default() as u8,
+            // mm/s, enhanced speed first
+            speed: record.get(73).or(record.get(6)).map_or(0., |x| x as f32 / 1000. * 3.6)
+        };
+        // smart recording leaves gaps, the record holds for all of them
+        let seconds = match paused {
+            true => 1,
+            false => gap.max(1)
+        };
+        for _ in 0..seconds {
+            elapsed += Duration::from_secs(1);
+            recording.add_sample(Sample { elapsed, ..sample.clone() });
+        }
+
+        timeline.push((timestamp, elapsed));
+        previous = timestamp;
+    }
+
+    let mut lap_ends: Vec<u32> = messages.iter()
+        .filter(|x| x.is(LAP))
+        .filter_map(|x| x.get(TIMESTAMP))
+        .collect();
+    lap_ends.sort();
+    for end in lap_ends {
+        let lap_end = timeline.iter()
+            .take_while(|x| x.0 <= end)
+            .last()
+            .map_or(Duration::default(), |x| x.1);
+        if lap_end > recording.lap_start && lap_end < elapsed {
+            recording.lap(lap_end);
+            recording.add_event(lap_end, EventKind::Lap);
+        }
+    }
+
+    recording.finish(elapsed);
+    return Ok(recording);
+}
+
+// the file id names the device that created the file, older files only list it as device 0
+fn serial(messages: &[Message]) -> Option<u32> {
+    return messages.iter()
+        .find(|x| x.is(FILE_ID))
+        .and_then(|x| x.get(3))
+        .or_else(|| {
+            messages.iter()
+                .find(|x| x.is(DEVICE_INFO) && x.get(0) == Some(0))
+                .and_then(|x| x.get(3))
+        });
+}
+
+fn messages(data: &[u8]) -> Result<Vec<Message>, FitError> {
+    let mut messages = Vec::new();
+    let mut offset = 0;
+    // files can be chained one after another
+    while offset < data.len() {
+        let header_size = data[offset] as usize;
+        let header = data.get(offset..offset + header_size).ok_or(FitError::Truncated)?;
+        if header_size < 12 || &header[8..12] != b".FIT" {
+            return Err(FitError::InvalidHeader);
+        }
+
+        let data_size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let start = offset + header_size;
+        let end = start + data_size;
+        let checksum = data.get(end..end + 2).ok_or(FitError::Truncated)?;
+        if crc(&data[offset..end]) != u16::from_le_bytes([checksum[0], checksum[1]]) {
+            return Err(FitError::InvalidChecksum);
+        }
+
+        decode_records(&data[start..end], &mut messages)?;
+        offset = end + 2;
+    }
+    return Ok(messages);
+}
+
+fn decode_records(data: &[u8], messages: &mut Vec<Message>) -> Result<(), FitError> {
+    let mut definitions: Vec<Option<Definition>> = vec![None; 16];
+    let mut reader = Reader { data, position: 0 };
+    let mut timestamp = 0;
+
+    while reader.position < data.len() {
+        let header = reader.byte()?;
+        if header & 0x80 != 0 {
+            // compressed timestamp, the offset replaces the low 5 bits and rolls over
+            let local = (header >> 5) & 0x03;
+            let offset = (header & 0x1f) as u32;
+            timestamp = match offset >= timestamp & 0x1f {
+                true => (timestamp & !0x1f) + offset,
+                false => (timestamp & !0x1f) + 0x20 + offset
+            };
+            let definition = definitions[local as usize].as_ref().ok_or(FitError::MissingDefinition(local))?;
+            let mut message = reader.message(definition)?;
+            if message.get(TIMESTAMP).is_none() {
+                message.fields.push((TIMESTAMP, timestamp));
+            }
+            messages.push(message);
+        } else if header & 0x40 != 0 {
+            let local = header & 0x0f;
+            definitions[local as usize] = Some(reader.definition(header & 0x20 != 0)?);
+        } else {
+            let local = header & 0x0f;
+            let definition = definitions[local as usize].as_ref().ok_or(FitError::MissingDefinition(local))?;
+            let message = reader.message(definition)?;
+            if let Some(value) = message.get(TIMESTAMP) {
+                timestamp = value;
+            }
+            messages.push(message);
+        }
+    }
+
+    return Ok(());
+}
+
+// unsigned integers only, invalid values are left out
+fn value(bytes: &[u8], base_type: u8, big_endian: bool) -> Option<u32> {
+    let (size, zero_invalid) = match base_type & 0x1f {
+        // enum, uint8, byte
+        0x00 | 0x02 | 0x0d => (1, false),
+        0x04 => (2, false),
+        0x06 => (4, false),
+        0x0a => (1, true),
+        0x0b => (2, true),
+        0x0c => (4, true),
+        _ => return None
+    };
+    // arrays are not needed
+    if bytes.len() != size {
+        return None;
+    }
+
+    let mut value: u32 = 0;
+    for index in 0..size {
+        let byte = match big_endian {
+            true => bytes[index],
+            false => bytes[size - 1 - index]
+        };
+        value = value << 8 | byte as u32;
+    }
+
+    let invalid = match zero_invalid {
+        true => 0,
+        false => u32::MAX >> (32 - 8 * size)
+    };
+    return match value == invalid {
+        true => None,
+        false => Some(value)
+    };
+}
+
+// REF: FitCRC_Get16 in the FIT SDK
+fn crc(data: &[u8]) -> u16 {
+    const TABLE: [u16; 16] = [
+        0x0000, 0xcc01, 0xd801, 0x1400, 0xf001, 0x3c00, 0x2800, 0xe401,
+        0xa001, 0x6c00, 0x7800, 0xb401, 0x5000, 0x9c01, 0x8801, 0x4400
+    ];
+
+    let mut crc: u16 = 0;
+    for byte in data {
+        for nibble in [byte & 0x0f, byte >> 4] {
+            let low = TABLE[(crc & 0x0f) as usize];
+            crc = (crc >> 4) & 0x0fff;
+            crc = crc ^ low ^ TABLE[nibble as usize];
+        }
+    }
+    return crc;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::fit::{crc, decode, deduplicate, FitError, FIT_EPOCH};
+    use crate::recording::{EventKind, Recording};
+
+    const START: u32 = 1_000_000_000;
+
+    fn definition(local: u8, global: u16, fields: &[(u8, u8, u8)]) -> Vec<u8> {
+        let mut bytes = vec![0x40 | local, 0, 0];
+        bytes.extend(global.to_le_bytes());
+        bytes.push(fields.len() as u8);
+        for (number, size, base_type) in fields {
+            bytes.extend([*number, *size, *base_type]);
+        }
+        return bytes;
+    }
+
+    fn record(timestamp: u32, heart_rate: u8, power: u16) -> Vec<u8> {
+        let mut bytes = vec![0];
+        bytes.extend(timestamp.to_le_bytes());
+        bytes.push(heart_rate);
+        bytes.extend(power.to_le_bytes());
+        bytes.push(90);
+        bytes.extend(8_333u16.to_le_bytes());
+        return bytes;
+    }
+
+    fn file(records: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![14, 0x20, 0x08, 0x08];
+        bytes.extend((records.len() as u32).to_le_bytes());
+        bytes.extend(b".FIT");
+        let header_crc = crc(&bytes);
+        bytes.extend(header_crc.to_le_bytes());
+        bytes.extend(records);
+        let file_crc = crc(&bytes);
+        bytes.extend(file_crc.to_le_bytes());
+        return bytes;
+    }
+
+    // file id, a 1 s recording with a smart recording gap, a lap, a timer pause
+    // and a record with a compressed timestamp
+    fn activity() -> Vec<u8> {
+        let mut records = definition(2, 0, &[(3, 4, 0x8c), (4, 4, 0x86)]);
+        records.push(2);
+        records.extend(1_234u32.to_le_bytes());
+        records.extend(START.to_le_bytes());
+
+        records.extend(definition(0, 20, &[(253, 4, 0x86), (3, 1, 0x02), (7, 2, 0x84), (4, 1, 0x02), (6, 2, 0x84)]));
+        for offset in 0..5 {
+            records.extend(record(START + offset, 120 + offset as u8, 200));
+        }
+        records.extend(record(START + 7, 130, 250));
+
+        records.extend(definition(3, 19, &[(253, 4, 0x86), (2, 4, 0x86)]));
+        records.push(3);
+        records.extend((START + 4).to_le_bytes());
+        records.extend(START.to_le_bytes());
+
+        // timer stop, then resume a minute later
+        records.extend(definition(4, 21, &[(253, 4, 0x86), (0, 1, 0x00), (1, 1, 0x00)]));
+        records.push(4);
+        records.extend((START + 8).to_le_bytes());
+        records.extend([0, 4]);
+        records.push(4);
+        records.extend((START + 67).to_le_bytes());
+        records.extend([0, 0]);
+
+        records.extend(record(START + 68, 110, 150));
+        // heart rate and power without a timestamp field
+        records.extend(definition(1, 20, &[(3, 1, 0x02), (7, 2, 0x84)]));
+        records.push(0x80 | (1 << 5) | ((START + 69) & 0x1f) as u8);
+        records.push(111);
+        records.extend(160u16.to_le_bytes());
+        return file(&records);
+    }
+
+    #[test]
+    fn decodes_records_laps_and_pauses() {
+        let ride = decode(&activity()).unwrap();
+
+        assert_eq!(ride.started_at, UNIX_EPOCH + Duration::from_secs(FIT_EPOCH + START as u64));
+        assert_eq!(ride.device_serial, Some(1_234));
+        // 5 records, 3 s held over the gap, 1 s after the pause and the compressed one
+        assert_eq!(ride.samples.len(), 10);
+        assert!(ride.samples.iter().enumerate().all(|(index, x)| x.elapsed == Duration::from_secs(index as u64 + 1)));
+        assert_eq!(ride.samples[4].heart_rate, 124);
+        assert_eq!(ride.samples[5].power, 250);
+        assert!((ride.samples[0].speed - 30.).abs() < 0.01);
+        assert_eq!(ride.samples[9].power, 160);
+        assert_eq!(ride.samples[9].speed, 0.);
+
+        let laps: Vec<(u64, u64)> = ride.laps.iter().map(|x| (x.start.as_secs(), x.end.as_secs())).collect();
+        assert_eq!(laps, vec![(0, 5), (5, 10)]);
+        assert!(ride.events.iter().any(|x| x.kind == EventKind::Pause && x.elapsed == Duration::from_secs(8)));
+    }
+
+    #[test]
+    fn rejects_corrupted_files() {
+        let mut data = activity();
+        data[40] ^= 0xff;
+
+        assert_eq!(decode(&data).unwrap_err(), FitError::InvalidChecksum);
+        assert_eq!(decode(b"\x0enot a fit file").unwrap_err(), FitError::InvalidHeader);
+        assert_eq!(decode(&file(&[])).unwrap_err(), FitError::NoRecords);
+    }
+
+    #[test]
+    fn skips_rides_already_imported() {
+        let ride = decode(&activity()).unwrap();
+        let mut other_device = ride.clone();
+        other_device.device_serial = Some(5_678);
+        let existing = ride.clone();
+
+        let (rides, duplicates) = deduplicate(vec![ride.clone(), ride, other_device], &[&existing, &Recording::new()]);
+
+        assert_eq!(duplicates, 2);
+        assert_eq!(rides.len(), 1);
+        assert_eq!(rides[0].device_serial, Some(5_678));
+    }
+}
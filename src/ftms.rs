@@ -0,0 +1,130 @@
+// Fitness Machine Service (FTMS) helpers for smart trainers
+// REF: https://www.bluetooth.com/specifications/specs/fitness-machine-service-1-0/
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndoorBikeData {
+    pub speed: Option<f32>, // km/h
+    pub cadence: Option<f32>, // rpm
+    pub power: Option<i16>
+}
+
+// what the trainer is asked to do, sent over the control point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrainerCommand {
+    // ERG, the trainer holds the wattage
+    TargetPower(u16),
+    // grade as a fraction
+    Simulation(f32)
+}
+
+// a flat road leaves the effort to the rider's gears
+pub const RELEASE: TrainerCommand = TrainerCommand::Simulation(0.);
+
+// control point op codes
+const REQUEST_CONTROL: u8 = 0x00;
+const SET_TARGET_POWER: u8 = 0x05;
+const START_OR_RESUME: u8 = 0x07;
+const SET_INDOOR_BIKE_SIMULATION: u8 = 0x11;
+
+pub fn request_control() -> Vec<u8> {
+    return vec![REQUEST_CONTROL];
+}
+
+pub fn start_or_resume() -> Vec<u8> {
+    return vec![START_OR_RESUME];
+}
+
+pub fn set_target_power(watts: i16) -> Vec<u8> {
+    let mut data = vec![SET_TARGET_POWER];
+    data.extend_from_slice(&watts.to_le_bytes());
+    return data;
+}
+
+// no wind, grade as a fraction, wind resistance coefficient in kg/m
+pub fn set_indoor_bike_simulation(grade: f32, rolling_resistance: f32, wind_resistance: f32) -> Vec<u8> {
+    let mut data = vec![SET_INDOOR_BIKE_SIMULATION];
+    // wind speed, 0.001 m/s
+    data.extend_from_slice(&0_i16.to_le_bytes());
+    // grade, 0.01 %
+    data.extend_from_slice(&((grade * 10_000.).round() as i16).to_le_bytes());
+    // Crr, 0.0001
+    data.push((rolling_resistance * 10_000.).round().clamp(0., 255.) as u8);
+    // Cw, 0.01 kg/m
+    data.push((wind_resistance * 100.).round().clamp(0., 255.) as u8);
+    return data;
+}
+
+// Indoor Bike Data (0x2AD2), the flags say which fields follow
+pub fn parse_indoor_bike_data(data: &[u8]) -> Option<IndoorBikeData> {
+    if data.len() < 2 {
+        return None;
+    }
+
+    let flags = u16::from_le_bytes([data[0], data[1]]);
+    let mut offset = 2;
+    let mut result = IndoorBikeData::default();
+
+    let read_u16 = |offset: &mut usize| -> Option<u16> {
+        let bytes = data.get(*offset..*offset + 2)?;
+        *offset += 2;
+        return Some(u16::from_le_bytes([bytes[0], bytes[1]]));
+    };
+
+    // bit 0 "more data" is inverted: instantaneous speed is present when it is 0
+    if flags & 0x0001 == 0 {
+        result.speed = Some(read_u16(&mut offset)? as f32 * 0.01);
+    }
+    // average speed
+    if flags & 0x0002 != 0 {
+        read_u16(&mut offset)?;
+    }
+    if flags & 0x0004 != 0 {
+        result.cadence = Some(read_u16(&mut offset)? as f32 * 0.5);
+    }
+    // average cadence
+    if flags & 0x0008 != 0 {
+        read_u16(&mut offset)?;
+    }
+    // total distance is 24 bits
+    if flags & 0x0010 != 0 {
+        offset += 3;
+    }
+    // resistance level
+    if flags & 0x0020 != 0 {
+        read_u16(&mut offset)?;
+    }
+    if flags & 0x0040 != 0 {
+        result.power = Some(read_u16(&mut offset)? as i16);
+    }
+
+    return Some(result);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ftms::{parse_indoor_bike_data, set_indoor_bike_simulation, set_target_power};
+
+    #[test]
+    fn parses_speed_cadence_and_power() {
+        // flags: cadence + power, speed present (bit 0 not set)
+        let data = [0x44, 0x00, 0xc4, 0x09, 0xb4, 0x00, 0xfa, 0x00];
+
+        let result = parse_indoor_bike_data(&data).unwrap();
+
+        assert_eq!(result.speed, Some(25.));
+        assert_eq!(result.cadence, Some(90.));
+        assert_eq!(result.power, Some(250));
+    }
+
+    #[test]
+    fn target_power_is_little_endian() {
+        assert_eq!(set_target_power(300), vec![0x05, 0x2c, 0x01]);
+    }
+
+    #[test]
+    fn simulation_scales_parameters() {
+        assert_eq!(set_indoor_bike_simulation(0.05, 0.004, 0.51), vec![0x11, 0x00, 0x00, 0xf4, 0x01, 0x28, 0x33]);
+        // -2% is 0xff38
+        assert_eq!(set_indoor_bike_simulation(-0.02, 0.004, 0.51)[3..5], [0x38, 0xff]);
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::recording::Sample;
+use crate::workout::{Block, Power, Step, Target, Workout, WorkoutEngine};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FtpTest {
+    Ramp,
+    TwentyMinute,
+    EightMinute
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FtpTestResult {
+    pub test: FtpTest,
+    pub ftp: u16
+}
+
+// ramp steps start at 50% FTP and go up 6% every minute
+const RAMP_START: f32 = 0.5;
+const RAMP_INCREMENT: f32 = 0.06;
+const RAMP_STEPS: usize = 30;
+const RAMP_FIRST_STEP: usize = 1;
+// the ramp is over when power stays below this share of the target
+const RAMP_FAILURE_SHARE: f32 = 0.6;
+const RAMP_FAILURE_SECONDS: usize = 5;
+
+impl FtpTest {
+    pub const ALL: [FtpTest; 3] = [FtpTest::Ramp, FtpTest::TwentyMinute, FtpTest::EightMinute];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            FtpTest::Ramp => "ramp",
+            FtpTest::TwentyMinute => "20min",
+            FtpTest::EightMinute => "8min"
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<FtpTest> {
+        return FtpTest::ALL.into_iter().find(|x| x.key() == key);
+    }
+
+    pub fn workout(&self) -> Workout {
+        let minutes = |x: u64| Duration::from_secs(x * 60);
+        let warmup = Step::ramp(minutes(10), Power::Ftp(0.45), Power::Ftp(0.7));
+        let cooldown = Step::ramp(minutes(10), Power::Ftp(0.6), Power::Ftp(0.4));
+        let max_effort = |duration: Duration| Step {
+            duration,
+            target: Target::MaxEffort,
+            cadence: None,
+            cues: Vec::new()
+        };
+
+        let blocks = match self {
+            FtpTest::Ramp => {
+                let mut blocks = vec![Block::Step(Step::ramp(minutes(5), Power::Ftp(0.4), Power::Ftp(0.5)))];
+                blocks.extend((0..RAMP_STEPS).map(|x| {
+                    let power = RAMP_START + RAMP_INCREMENT * x as f32;
+                    Block::Step(Step::steady(minutes(1), Power::Ftp(power)))
+                }));
+                blocks.push(Block::Step(cooldown));
+                blocks
+            }
+            FtpTest::TwentyMinute => vec![
+                Block::Step(warmup),
+                Block::Repeat {
+                    count: 3,
+                    steps: vec![
+                        Step::steady(minutes(1), Power::Ftp(1.)),
+                        Step::steady(minutes(1), Power::Ftp(0.5))
+                    ]
+                },
+                Block::Step(max_effort(minutes(5))),
+                Block::Step(Step::steady(minutes(10), Power::Ftp(0.5))),
+                Block::Step(max_effort(minutes(20))),
+                Block::Step(cooldown)
+            ],
+            FtpTest::EightMinute => vec![
+                Block::Step(warmup),
+                Block::Step(max_effort(minutes(8))),
+                Block::Step(Step::steady(minutes(10), Power::Ftp(0.5))),
+                Block::Step(max_effort(minutes(8))),
+                Block::Step(cooldown)
+            ]
+        };
+
+        return Workout {
+            name: self.to_string(),
+            description: String::from("FTP test"),
+            blocks
+        };
+    }
+
+    // the effort is over when the rider can no longer hold the ramp or
+    // when the last max effort of the protocol has been ridden
+    pub fn effort_ended(&self, engine: &WorkoutEngine, samples: &[Sample], elapsed: Duration) -> bool {
+        let Some(current) = engine.current(elapsed) else {
+            return true;
+        };
+
+        match self {
+            FtpTest::Ramp => {
+                if current.index < RAMP_FIRST_STEP || samples.len() < RAMP_FAILURE_SECONDS {
+                    return false;
+                }
+                if current.index > RAMP_FIRST_STEP + RAMP_STEPS - 1 {
+                    return true;
+                }
+
+                let Some(target) = engine.target_power(elapsed) else {
+                    return false;
+                };
+                return samples[samples.len() - RAMP_FAILURE_SECONDS..]
+                    .iter()
+                    .all(|x| (x.power as f32) < target as f32 * RAMP_FAILURE_SHARE);
+            }
+            FtpTest::TwentyMinute | FtpTest::EightMinute => {
+                let last_effort = engine.steps
+                    .iter()
+                    .rposition(|x| x.target == Target::MaxEffort)
+                    .unwrap_or(0);
+                return current.index > last_effort;
+            }
+        }
+    }
+
+    // every protocol ends with a cool down
+    pub fn cool_down(&self, engine: &mut WorkoutEngine, elapsed: Duration) {
+        let last = engine.steps.len().saturating_sub(1);
+        engine.skip_to(elapsed, last);
+    }
+
+    pub fn estimate(&self, samples: &[Sample]) -> Option<u16> {
+        let power: Vec<f32> = samples.iter().map(|x| x.power as f32).collect();
+        let (window, share) = match self {
+            FtpTest::Ramp => (60, 0.75),
+            FtpTest::TwentyMinute => (20 * 60, 0.95),
+            FtpTest::EightMinute => (8 * 60, 0.9)
+        };
+
+        let average = match self {
+            FtpTest::Ramp | FtpTest::TwentyMinute => best_window(&power, window, None).map(|(_, x)| x),
+            // both efforts count, the second one is the best window clear of the first
+            FtpTest::EightMinute => best_window(&power, window, None).and_then(|(start, first)| {
+                return best_window(&power, window, Some(start)).map(|(_, second)| (first + second) / 2.);
+            })
+        };
+
+        return average.map(|x| (x * share).round() as u16);
+    }
+}
+
+impl fmt::Display for FtpTest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            FtpTest::Ramp => "Ramp test",
+            FtpTest::TwentyMinute => "20 minute test",
+            FtpTest::EightMinute => "8 minute test"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+// start and average of the best `window` consecutive values,
+// skipping windows that overlap the one starting at `taken`
+fn best_window(values: &[f32], window: usize, taken: Option<usize>) -> Option<(usize, f32)> {
+    if window == 0 || values.len() < window {
+        return None;
+    }
+
+    let mut sum: f32 = values[..window].iter().sum();
+    let mut best: Option<(usize, f32)> = None;
+    for start in 0..=values.len() - window {
+        if start > 0 {
+            sum += values[start + window - 1] - values[start - 1];
+        }
+
+        let overlaps = match taken {
+            Some(taken) => start < taken + window && taken < start + window,
+            None => false
+        };
+        let better = match best {
+            Some((_, value)) => sum > value,
+            None => true
+        };
+        if !overlaps && better {
+            best = Some((start, sum));
+        }
+    }
+
+    return best.map(|(start, sum)| (start, sum / window as f32));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::ftms::{RELEASE, TrainerCommand};
+    use crate::ftp_test::FtpTest;
+    use crate::recording::Sample;
+    use crate::workout::WorkoutEngine;
+
+    fn samples(power: &[u16]) -> Vec<Sample> {
+        return power.iter()
+            .enumerate()
+            .map(|(index, power)| Sample {
+                elapsed: Duration::from_secs(index as u64 + 1),
+                heart_rate: 0,
+                power: *power,
+                cadence: 0,
+                speed: 0.
+            })
+            .collect();
+    }
+
+    #[test]
+    fn ramp_estimate_is_75_percent_of_best_minute() {
+        let mut power = vec![150; 300];
+        power.extend(vec![400; 60]);
+        power.extend(vec![50; 30]);
+
+        assert_eq!(FtpTest::Ramp.estimate(&samples(&power)), Some(300));
+        assert_eq!(FtpTest::TwentyMinute.estimate(&samples(&power)), None);
+    }
+
+    #[test]
+    fn ramp_ends_when_power_drops_below_target() {
+        let test = FtpTest::Ramp;
+        let engine = WorkoutEngine::new(test.workout(), 200, Duration::default());
+        // 10 minutes in, the target is 50% + 5 * 6% of FTP = 160 W
+        let elapsed = Duration::from_secs(10 * 60 + 30);
+
+        assert!(!test.effort_ended(&engine, &samples(&[150; 10]), elapsed));
+        assert!(test.effort_ended(&engine, &samples(&[50; 10]), elapsed));
+        assert!(!test.effort_ended(&engine, &samples(&[50; 10]), Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn eight_minute_estimate_averages_both_efforts() {
+        let mut power = vec![300; 8 * 60];
+        power.extend(vec![120; 10 * 60]);
+        power.extend(vec![280; 8 * 60]);
+
+        assert_eq!(FtpTest::EightMinute.estimate(&samples(&power)), Some(261));
+        assert_eq!(FtpTest::EightMinute.estimate(&samples(&power[..9 * 60])), None);
+    }
+
+    #[test]
+    fn max_efforts_release_erg() {
+        let engine = WorkoutEngine::new(FtpTest::TwentyMinute.workout(), 200, Duration::default());
+        // 10 minute warm up and three 1 minute pairs, the last recovery is at 50% FTP
+        let recovery = Duration::from_secs(15 * 60 + 30);
+        let effort = Duration::from_secs(16 * 60 + 30);
+
+        assert_eq!(engine.trainer_command(recovery), Some(TrainerCommand::TargetPower(100)));
+        assert_eq!(engine.trainer_command(effort), Some(RELEASE));
+    }
+}
@@ -0,0 +1,73 @@
+// Heart Rate Service helpers
+// REF: https://www.bluetooth.com/specifications/specs/heart-rate-service-1-0/
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeartRateMeasurement {
+    pub heart_rate: u16,
+    // beat to beat intervals in ms
+    pub rr_intervals: Vec<f32>
+}
+
+// Heart Rate Measurement (0x2A37), the flags say which fields follow
+pub fn parse_heart_rate_measurement(data: &[u8]) -> Option<HeartRateMeasurement> {
+    let flags = *data.first()?;
+    let mut offset = 1;
+
+    let read_u16 = |offset: &mut usize| -> Option<u16> {
+        let bytes = data.get(*offset..*offset + 2)?;
+        *offset += 2;
+        return Some(u16::from_le_bytes([bytes[0], bytes[1]]));
+    };
+
+    let heart_rate = match flags & 0x01 {
+        0 => {
+            let value = *data.get(offset)?;
+            offset += 1;
+            value as u16
+        }
+        _ => read_u16(&mut offset)?
+    };
+
+    // energy expended
+    if flags & 0x08 != 0 {
+        read_u16(&mut offset)?;
+    }
+
+    // any number of rr intervals in 1/1024 s fill the rest of the packet
+    let mut rr_intervals = Vec::new();
+    if flags & 0x10 != 0 {
+        while let Some(value) = read_u16(&mut offset) {
+            rr_intervals.push(value as f32 * 1000. / 1024.);
+        }
+    }
+
+    return Some(HeartRateMeasurement { heart_rate, rr_intervals });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heart_rate::parse_heart_rate_measurement;
+
+    #[test]
+    fn parses_heart_rate_and_rr_intervals() {
+        // flags: rr intervals, 8 bit heart rate
+        let data = [0x10, 0x48, 0x00, 0x04, 0x00, 0x02];
+
+        let result = parse_heart_rate_measurement(&data).unwrap();
+
+        assert_eq!(result.heart_rate, 72);
+        assert_eq!(result.rr_intervals, vec![1000., 500.]);
+    }
+
+    #[test]
+    fn skips_energy_expended() {
+        // flags: rr intervals, energy expended, 16 bit heart rate
+        let data = [0x19, 0x2c, 0x01, 0x10, 0x00, 0x00, 0x04];
+
+        let result = parse_heart_rate_measurement(&data).unwrap();
+
+        assert_eq!(result.heart_rate, 300);
+        assert_eq!(result.rr_intervals, vec![1000.]);
+        assert_eq!(parse_heart_rate_measurement(&[0x01, 0x48]), None);
+    }
+}
@@ -0,0 +1,417 @@
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use iced::widget::{button, column, pick_list, row, scrollable, text, text_input};
+use iced::{Alignment, Element, Length};
+
+use crate::aerobic::AerobicAnalysis;
+use crate::analytics::RideSummary;
+use crate::chart::{Chart, ChartWindow, Metric, Smoothing, zone_bands, zone_color};
+use crate::fit;
+use crate::power_curve::DateRange;
+use crate::profile::{Date, Profile};
+use crate::recording::{Recording, RecordingError, RideKind};
+use crate::stopwatch::format_duration;
+use crate::tcx;
+use crate::trimp::TrainingImpulse;
+use crate::zones::{Zone, lap_samples, time_in_zones};
+
+#[derive(Debug, Clone)]
+pub enum HistoryMessage {
+    RangeSelected(DateRange),
+    KindSelected(KindFilter),
+    Open(usize),
+    Back,
+    NameChanged(String),
+    Rename,
+    Delete,
+    ConfirmDelete,
+    CancelDelete,
+    Export,
+    ImportPathChanged(String),
+    Import,
+    Close
+}
+
+#[derive(Debug, Clone)]
+pub enum HistoryEvent {
+    // files that could not be imported
+    ImportFailed(Vec<String>),
+    Closed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KindFilter {
+    All,
+    Only(RideKind)
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    ride: Recording,
+    summary: RideSummary
+}
+
+// saved rides, newest first
+#[derive(Debug, Clone)]
+pub struct History {
+    profile: Profile,
+    entries: Vec<Entry>,
+    range: DateRange,
+    kind: KindFilter,
+    selected: Option<usize>,
+    name: String,
+    // asked before the selected ride is deleted
+    confirm_delete: bool,
+    // FIT file or folder
+    import_path: String,
+    // outcome of the last action
+    status: Option<String>
+}
+
+impl History {
+    pub fn new(rides: Vec<Recording>, profile: &Profile) -> History {
+        let mut entries: Vec<Entry> = rides.into_iter()
+            .map(|ride| {
+                let summary = RideSummary::new(&ride.samples, ride.ftp.unwrap_or(profile.ftp));
+                Entry { ride, summary }
+            })
+            .collect();
+        entries.reverse();
+
+        return History {
+            profile: profile.clone(),
+            entries,
+            range: DateRange::AllTime,
+            kind: KindFilter::All,
+            selected: None,
+            name: String::new(),
+            confirm_delete: false,
+            import_path: String::new(),
+            status: None
+        };
+    }
+
+    pub fn update(&mut self, message: HistoryMessage) -> Result<Option<HistoryEvent>, RecordingError> {
+        match message {
+            HistoryMessage::RangeSelected(range) => {
+                self.range = range;
+            }
+            HistoryMessage::KindSelected(kind) => {
+                self.kind = kind;
+            }
+            HistoryMessage::Open(index) => {
+                if let Some(entry) = self.entries.get(index) {
+                    self.name = entry.ride.name.clone().unwrap_or_default();
+                    self.selected = Some(index);
+                    self.confirm_delete = false;
+                    self.status = None;
+                }
+            }
+            HistoryMessage::Back => {
+                self.selected = None;
+                self.confirm_delete = false;
+            }
+            HistoryMessage::NameChanged(value) => {
+                self.name = value;
+            }
+            HistoryMessage::Rename => {
+                if let Some(entry) = self.selected.and_then(|x| self.entries.get_mut(x)) {
+                    let name = self.name.trim();
+                    entry.ride.name = match name.is_empty() {
+                        true => None,
+                        false => Some(name.to_string())
+                    };
+                    entry.ride.save()?;
+                    self.status = Some(String::from("Renamed"));
+                }
+            }
+            HistoryMessage::Delete => {
+                self.confirm_delete = self.selected.is_some();
+            }
+            HistoryMessage::ConfirmDelete => {
+                self.confirm_delete = false;
+                if let Some(index) = self.selected {
+                    self.entries[index].ride.delete()?;
+                    self.entries.remove(index);
+                    self.selected = None;
+                }
+            }
+            HistoryMessage::CancelDelete => {
+                self.confirm_delete = false;
+            }
+            HistoryMessage::Export => {
+                if let Some(entry) = self.selected.and_then(|x| self.entries.get(x)) {
+                    let path = tcx::export(&entry.ride, &self.profile)?;
+                    self.status = Some(format!("Exported to {}", path.display()));
+                }
+            }
+            HistoryMessage::ImportPathChanged(value) => {
+                self.import_path = value;
+            }
+            HistoryMessage::Import => {
+                let (rides, mut errors) = fit::import(Path::new(self.import_path.trim()));
+                let existing: Vec<&Recording> = self.entries.iter().map(|x| &x.ride).collect();
+                let (rides, duplicates) = fit::deduplicate(rides, &existing);
+
+                let mut imported = 0;
+                for ride in rides {
+                    if let Err(err) = ride.save() {
+                        errors.push(err.to_string());
+                        continue;
+                    }
+                    let summary = RideSummary::new(&ride.samples, ride.ftp.unwrap_or(self.profile.ftp));
+                    self.entries.push(Entry { ride, summary });
+                    imported += 1;
+                }
+                self.entries.sort_by(|a, b| b.ride.started_at.cmp(&a.ride.started_at));
+                self.import_path.clear();
+                self.status = Some(format!(
+                    "Imported {} rides, skipped {} duplicates, {} failed",
+                    imported,
+                    duplicates,
+                    errors.len()
+                ));
+
+                if !errors.is_empty() {
+                    return Ok(Some(HistoryEvent::ImportFailed(errors)));
+                }
+            }
+            HistoryMessage::Close => return Ok(Some(HistoryEvent::Closed))
+        }
+
+        return Ok(None);
+    }
+
+    pub fn view(&self) -> Element<HistoryMessage> {
+        return match self.selected.and_then(|x| self.entries.get(x)) {
+            Some(entry) => self.detail_view(entry),
+            None => self.list_view()
+        };
+    }
+
+    fn list_view(&self) -> Element<HistoryMessage> {
+        let now = SystemTime::now();
+        let units = self.profile.units;
+        let rides = column(
+            self.entries
+                .iter()
+                .enumerate()
+                .filter(|(_, x)| self.range.contains(x.ride.started_at, now) && self.kind.matches(x.ride.kind))
+                .map(|(index, entry)| {
+                    let summary = &entry.summary;
+                    row![
+                        text(format!("{}  {}", date(entry.ride.started_at), entry.ride.title())).width(Length::Fixed(280.)),
+                        text(format_duration(summary.duration)).width(Length::Fixed(80.)),
+                        text(units.format_distance(summary.distance)).width(Length::Fixed(90.)),
+                        text(format!("{} bpm", optional(summary.average_heart_rate, 0))).width(Length::Fixed(70.)),
+                        text(format!("{:.0} W", summary.average_power)).width(Length::Fixed(60.)),
+                        text(format!("TSS {}", optional(summary.training_stress_score, 0))).width(Length::Fixed(70.)),
+                        button("Open").on_press(HistoryMessage::Open(index)).padding(5.)
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .into()
+                })
+                .collect()
+        )
+        .spacing(5);
+
+        let content = match self.entries.is_empty() {
+            true => column![text("No saved rides yet")],
+            false => column![scrollable(rides).height(Length::Fill)]
+        };
+
+        return column![
+            row![
+                text("Ride history").size(30),
+                pick_list(&DateRange::ALL[..], Some(self.range), HistoryMessage::RangeSelected),
+                pick_list(&KindFilter::ALL[..], Some(self.kind), HistoryMessage::KindSelected),
+                button("Close").on_press(HistoryMessage::Close).padding(5.)
+            ]
+            .spacing(20)
+            .align_items(Alignment::Center),
+            row![
+                text_input("FIT file or folder", &self.import_path, HistoryMessage::ImportPathChanged)
+                    .on_submit(HistoryMessage::Import)
+                    .padding(5.)
+                    .width(Length::Fixed(300.)),
+                button("Import").on_press(HistoryMessage::Import).padding(5.)
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            text(self.status.clone().unwrap_or_default()),
+            content
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .into();
+    }
+
+    fn detail_view<'a>(&'a self, entry: &'a Entry) -> Element<'a, HistoryMessage> {
+        let ride = &entry.ride;
+        let summary = &entry.summary;
+        let units = self.profile.units;
+        let impulse = TrainingImpulse::new(&ride.samples, &self.profile);
+        let heart_rate_zones = self.profile.heart_rate_zones();
+        let power_zones = self.profile.power_zones();
+
+        let header = match self.confirm_delete {
+            true => row![
+                text("Delete this ride?"),
+                button("Delete").on_press(HistoryMessage::ConfirmDelete).padding(5.),
+                button("Cancel").on_press(HistoryMessage::CancelDelete).padding(5.)
+            ],
+            false => row![
+                text_input(&ride.kind.to_string(), &self.name, HistoryMessage::NameChanged)
+                    .on_submit(HistoryMessage::Rename)
+                    .padding(5.)
+                    .width(Length::Fixed(250.)),
+                button("Rename").on_press(HistoryMessage::Rename).padding(5.),
+                button("Export .tcx").on_press(HistoryMessage::Export).padding(5.),
+                button("Delete").on_press(HistoryMessage::Delete).padding(5.),
+                button("Back").on_press(HistoryMessage::Back).padding(5.)
+            ]
+        }
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let metrics = column![
+            text(format!("{}  {}", date(ride.started_at), ride.kind)),
+            row![
+                text(format!("Time {}", format_duration(summary.duration))),
+                text(units.format_distance(summary.distance)),
+                text(format!("Avg {:.0} W", summary.average_power)),
+                text(format!("NP {} W", optional(summary.normalized_power, 0))),
+                text(format!("{:.0} kJ", summary.work))
+            ]
+            .spacing(15),
+            row![
+                text(format!("IF {}", optional(summary.intensity_factor, 2))),
+                text(format!("TSS {}", optional(summary.training_stress_score, 0))),
+                text(format!("VI {}", optional(summary.variability_index, 2))),
+                text(format!("HR {} / {} bpm", optional(summary.average_heart_rate, 0), summary.max_heart_rate)),
+                text(format!("TRIMP {:.0}", impulse.banister)),
+                text(format!("{} kcal", optional(impulse.calories, 0)))
+            ]
+            .spacing(15),
+            text(AerobicAnalysis::new(&ride.samples).map_or(String::new(), |x| format!(
+                "EF {:.2}, {} decoupling {:.1}%",
+                x.efficiency_factor,
+                x.output,
+                x.decoupling
+            )))
+        ]
+        .spacing(5)
+        .align_items(Alignment::Center);
+
+        let charts = column![
+            Chart::new(Metric::HeartRate, ChartWindow::WholeRide, Smoothing::TenSeconds, &ride.samples)
+                .with_bands(zone_bands(&heart_rate_zones))
+                .view(),
+            Chart::new(Metric::Power, ChartWindow::WholeRide, Smoothing::TenSeconds, &ride.samples)
+                .with_bands(zone_bands(&power_zones))
+                .view(),
+            Chart::new(Metric::Cadence, ChartWindow::WholeRide, Smoothing::TenSeconds, &ride.samples).view()
+        ]
+        .spacing(10);
+
+        let laps = column(
+            ride.laps
+                .iter()
+                .enumerate()
+                .map(|(index, lap)| {
+                    let samples = lap_samples(&ride.samples, lap);
+                    let lap_summary = RideSummary::new(samples, ride.ftp.unwrap_or(self.profile.ftp));
+                    row![
+                        text(format!("Lap {}", index + 1)).width(Length::Fixed(60.)),
+                        text(format_duration(lap.end - lap.start)).width(Length::Fixed(80.)),
+                        text(units.format_distance(lap_summary.distance)).width(Length::Fixed(90.)),
+                        text(format!("{:.0} W", lap_summary.average_power)).width(Length::Fixed(60.)),
+                        text(format!("NP {} W", optional(lap_summary.normalized_power, 0))).width(Length::Fixed(90.)),
+                        text(format!("{} bpm", optional(lap_summary.average_heart_rate, 0))).width(Length::Fixed(70.))
+                    ]
+                    .spacing(10)
+                    .into()
+                })
+                .collect()
+        )
+        .spacing(5);
+
+        let zones = column![
+            row![text("HR"), zones_row(&heart_rate_zones, &time_in_zones(&ride.samples, &heart_rate_zones, |x| x.heart_rate as f32))]
+                .spacing(10),
+            row![text("Power"), zones_row(&power_zones, &time_in_zones(&ride.samples, &power_zones, |x| x.power as f32))]
+                .spacing(10)
+        ]
+        .spacing(5);
+
+        return scrollable(
+            column![
+                header,
+                text(self.status.clone().unwrap_or_default()),
+                metrics,
+                charts,
+                text("Laps").size(20),
+                laps,
+                text("Time in zones").size(20),
+                zones
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center)
+        )
+        .into();
+    }
+}
+
+impl KindFilter {
+    pub const ALL: [KindFilter; 5] = [
+        KindFilter::All,
+        KindFilter::Only(RideKind::FreeRide),
+        KindFilter::Only(RideKind::Workout),
+        KindFilter::Only(RideKind::FtpTest),
+        KindFilter::Only(RideKind::Route)
+    ];
+
+    pub fn matches(&self, kind: RideKind) -> bool {
+        return match self {
+            KindFilter::All => true,
+            KindFilter::Only(only) => *only == kind
+        };
+    }
+}
+
+impl fmt::Display for KindFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            KindFilter::All => write!(f, "All rides"),
+            KindFilter::Only(kind) => write!(f, "{}", kind)
+        };
+    }
+}
+
+fn date(time: SystemTime) -> Date {
+    let days = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86_400;
+    return Date::from_unix_days(days as i64);
+}
+
+fn optional(value: Option<f32>, precision: usize) -> String {
+    return value.map_or(String::from("-"), |x| format!("{:.*}", precision, x));
+}
+
+fn zones_row<'a>(zones: &[Zone], durations: &[Duration]) -> Element<'a, HistoryMessage> {
+    return row(
+        durations.iter()
+            .zip(zones)
+            .enumerate()
+            .map(|(index, (duration, _))| {
+                text(format!("Z{} {}", index + 1, format_duration(*duration)))
+                    .style(zone_color(index))
+                    .into()
+            })
+            .collect()
+    )
+    .spacing(10)
+    .into();
+}
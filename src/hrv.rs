@@ -0,0 +1,283 @@
+// heart rate variability from beat to beat (RR) intervals in ms
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+pub enum HrvError {
+    #[error("Data directory not found")]
+    DataDirNotFound,
+    #[error("Failed to read the HRV history: {0}")]
+    ReadFailed(String),
+    #[error("Failed to write the HRV history: {0}")]
+    WriteFailed(String)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HrvMetrics {
+    pub mean_rr: f32,
+    pub rmssd: f32,
+    pub sdnn: f32,
+    // percent of successive differences above 50 ms
+    pub pnn50: f32,
+    pub count: usize,
+    pub artifacts: usize
+}
+
+// morning readiness, the rider lies still while rr intervals are collected
+#[derive(Debug, Clone)]
+pub struct Readiness {
+    pub started_at: Instant,
+    // index of the first interval in the rr history
+    pub start: usize,
+    pub duration: Duration
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HrvRecord {
+    pub recorded_at: SystemTime,
+    pub metrics: HrvMetrics
+}
+
+// physiologically possible intervals, 30 to 200 bpm
+const MIN_RR: f32 = 300.;
+const MAX_RR: f32 = 2000.;
+// an interval further than this from the local median is an artifact
+const MAX_DEVIATION: f32 = 0.2;
+const NEIGHBOURS: usize = 2;
+const MIN_INTERVALS: usize = 3;
+
+pub const READINESS_DURATION: Duration = Duration::from_secs(3 * 60);
+
+impl HrvMetrics {
+    pub fn new(rr: &[f32]) -> Option<HrvMetrics> {
+        if rr.len() < MIN_INTERVALS {
+            return None;
+        }
+
+        let (rr, artifacts) = correct_artifacts(rr);
+        let count = rr.len() as f32;
+        let mean_rr = rr.iter().sum::<f32>() / count;
+        let sdnn = (rr.iter().map(|x| (x - mean_rr).powi(2)).sum::<f32>() / (count - 1.)).sqrt();
+
+        let differences: Vec<f32> = rr.windows(2).map(|x| x[1] - x[0]).collect();
+        let rmssd = (differences.iter().map(|x| x.powi(2)).sum::<f32>() / differences.len() as f32).sqrt();
+        let pnn50 = differences.iter().filter(|x| x.abs() > 50.).count() as f32 / differences.len() as f32 * 100.;
+
+        return Some(HrvMetrics { mean_rr, rmssd, sdnn, pnn50, count: rr.len(), artifacts });
+    }
+
+    pub fn heart_rate(&self) -> f32 {
+        return 60_000. / self.mean_rr;
+    }
+}
+
+impl Readiness {
+    pub fn new(now: Instant, start: usize) -> Readiness {
+        return Readiness { started_at: now, start, duration: READINESS_DURATION };
+    }
+
+    pub fn remaining(&self, now: Instant) -> Duration {
+        return self.duration.saturating_sub(now.duration_since(self.started_at));
+    }
+
+    pub fn is_done(&self, now: Instant) -> bool {
+        return self.remaining(now).is_zero();
+    }
+}
+
+impl HrvRecord {
+    pub fn new(metrics: HrvMetrics) -> HrvRecord {
+        return HrvRecord { recorded_at: SystemTime::now(), metrics };
+    }
+
+    fn to_line(&self) -> String {
+        let metrics = &self.metrics;
+        return format!(
+            "{},{},{},{:.1},{:.1},{:.1},{:.1}\n",
+            self.recorded_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            metrics.count,
+            metrics.artifacts,
+            metrics.mean_rr,
+            metrics.rmssd,
+            metrics.sdnn,
+            metrics.pnn50
+        );
+    }
+
+    fn from_line(line: &str) -> Option<HrvRecord> {
+        let columns: Vec<&str> = line.trim().split(',').collect();
+        if columns.len() != 7 {
+            return None;
+        }
+
+        let float = |index: usize| columns[index].parse::<f32>().ok();
+        return Some(HrvRecord {
+            recorded_at: UNIX_EPOCH + Duration::from_secs(columns[0].parse().ok()?),
+            metrics: HrvMetrics {
+                count: columns[1].parse().ok()?,
+                artifacts: columns[2].parse().ok()?,
+                mean_rr: float(3)?,
+                rmssd: float(4)?,
+                sdnn: float(5)?,
+                pnn50: float(6)?
+            }
+        });
+    }
+}
+
+// replaces intervals that are out of range or too far from the local median
+// by interpolating between the closest valid ones
+pub fn correct_artifacts(rr: &[f32]) -> (Vec<f32>, usize) {
+    let is_artifact: Vec<bool> = (0..rr.len())
+        .map(|index| {
+            let value = rr[index];
+            if !(MIN_RR..=MAX_RR).contains(&value) {
+                return true;
+            }
+
+            let from = index.saturating_sub(NEIGHBOURS);
+            let to = (index + NEIGHBOURS + 1).min(rr.len());
+            let mut local: Vec<f32> = rr[from..to].to_vec();
+            match median(&mut local) {
+                Some(median) => (value - median).abs() > median * MAX_DEVIATION,
+                None => false
+            }
+        })
+        .collect();
+
+    let valid = |index: &usize| !is_artifact[*index];
+    let corrected = (0..rr.len())
+        .map(|index| {
+            if !is_artifact[index] {
+                return rr[index];
+            }
+
+            let before = (0..index).rev().find(valid);
+            let after = (index + 1..rr.len()).find(valid);
+            match (before, after) {
+                (Some(before), Some(after)) => {
+                    let share = (index - before) as f32 / (after - before) as f32;
+                    rr[before] + (rr[after] - rr[before]) * share
+                }
+                (Some(x), None) | (None, Some(x)) => rr[x],
+                (None, None) => rr[index]
+            }
+        })
+        .collect();
+
+    return (corrected, is_artifact.iter().filter(|x| **x).count());
+}
+
+// the most recent intervals adding up to the given duration
+pub fn window(rr: &[f32], duration: Duration) -> &[f32] {
+    let limit = duration.as_millis() as f32;
+    let mut total = 0.;
+    let mut start = rr.len();
+    while start > 0 && total < limit {
+        start -= 1;
+        total += rr[start];
+    }
+    return &rr[start..];
+}
+
+pub fn save_record(record: &HrvRecord) -> Result<PathBuf, HrvError> {
+    let path = history_path().ok_or(HrvError::DataDirNotFound)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|err| HrvError::WriteFailed(err.to_string()))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|err| HrvError::WriteFailed(err.to_string()))?;
+    file.write_all(record.to_line().as_bytes())
+        .map_err(|err| HrvError::WriteFailed(err.to_string()))?;
+
+    return Ok(path);
+}
+
+// oldest first
+pub fn load_history() -> Result<Vec<HrvRecord>, HrvError> {
+    let path = history_path().ok_or(HrvError::DataDirNotFound)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|err| HrvError::ReadFailed(err.to_string()))?;
+    return Ok(content.lines().filter_map(HrvRecord::from_line).collect());
+}
+
+fn history_path() -> Option<PathBuf> {
+    return dirs::data_dir().map(|dir| dir.join("cyclo").join("hrv.csv"));
+}
+
+fn median(values: &mut [f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+
+    values.sort_by(|a, b| a.total_cmp(b));
+    let middle = values.len() / 2;
+    return match values.len() % 2 {
+        0 => Some((values[middle - 1] + values[middle]) / 2.),
+        _ => Some(values[middle])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::hrv::{correct_artifacts, window, HrvMetrics, HrvRecord};
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 0.01, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn computes_time_domain_metrics() {
+        let metrics = HrvMetrics::new(&[800., 810., 790., 820., 780., 850.]).unwrap();
+
+        assert_close(metrics.mean_rr, 808.33);
+        assert_close(metrics.rmssd, 39.75);
+        assert_close(metrics.sdnn, 24.83);
+        assert_close(metrics.pnn50, 20.);
+        assert_eq!(metrics.artifacts, 0);
+        assert_eq!(HrvMetrics::new(&[800., 810.]), None);
+    }
+
+    #[test]
+    fn interpolates_artifacts() {
+        // a missed beat shows up as a double interval
+        let (rr, artifacts) = correct_artifacts(&[800., 820., 1600., 780., 800., 200.]);
+
+        assert_eq!(rr, vec![800., 820., 800., 780., 800., 800.]);
+        assert_eq!(artifacts, 2);
+    }
+
+    #[test]
+    fn window_keeps_the_latest_intervals() {
+        let rr = [1000., 900., 800., 700.];
+
+        assert_eq!(window(&rr, Duration::from_millis(1500)), &[800., 700.]);
+        assert_eq!(window(&rr, Duration::from_secs(60)), &rr[..]);
+    }
+
+    #[test]
+    fn history_lines_round_trip() {
+        let record = HrvRecord::new(HrvMetrics::new(&[800., 810., 790., 820.]).unwrap());
+        let parsed = HrvRecord::from_line(&record.to_line()).unwrap();
+
+        assert_close(parsed.metrics.rmssd, record.metrics.rmssd);
+        assert_eq!(parsed.metrics.count, 4);
+        assert_eq!(HrvRecord::from_line("not,a,record"), None);
+    }
+}
@@ -0,0 +1,190 @@
+use std::path::{Path, PathBuf};
+
+use iced::widget::canvas::Canvas;
+use iced::widget::{button, column, pick_list, row, text, text_input};
+use iced::{Alignment, Element, Length};
+
+use crate::elevation::ElevationProfile;
+use crate::erg::Format;
+use crate::ftp_test::FtpTest;
+use crate::profile::Units;
+use crate::route::{self, Route};
+use crate::route_processing::{self, ProcessingConfig};
+use crate::workout::{self, Workout};
+
+#[derive(Debug, Clone)]
+pub enum LibraryMessage {
+    WorkoutSelected(String),
+    StartWorkout,
+    ExportWorkout(Format),
+    ImportPathChanged(String),
+    ImportWorkout,
+    FtpTestSelected(FtpTest),
+    StartFtpTest,
+    RoutePathChanged(String),
+    LoadRoute,
+    StartRoute
+}
+
+// what to ride next, the ride screen takes over from here
+#[derive(Debug, Clone)]
+pub enum LibraryEvent {
+    StartWorkout(Workout),
+    StartFtpTest(FtpTest),
+    StartRoute(Route),
+    Exported(PathBuf)
+}
+
+// workouts, FTP tests and routes to choose from
+#[derive(Debug, Clone)]
+pub struct Library {
+    selected_workout: Option<String>,
+    selected_ftp_test: FtpTest,
+    import_path: String,
+    route_path: String,
+    // smoothed, ready to ride
+    route: Option<Route>
+}
+
+impl Library {
+    pub fn new() -> Library {
+        return Library {
+            selected_workout: None,
+            selected_ftp_test: FtpTest::Ramp,
+            import_path: String::new(),
+            route_path: String::new(),
+            route: None
+        };
+    }
+
+    pub fn update(&mut self, message: LibraryMessage, workouts: &mut Vec<Workout>, ftp: u16) -> Result<Option<LibraryEvent>, String> {
+        match message {
+            LibraryMessage::WorkoutSelected(name) => {
+                self.selected_workout = Some(name);
+            }
+            LibraryMessage::StartWorkout => {
+                let workout = workouts
+                    .iter()
+                    .find(|x| Some(&x.name) == self.selected_workout.as_ref())
+                    .cloned();
+
+                return Ok(workout.map(LibraryEvent::StartWorkout));
+            }
+            LibraryMessage::ExportWorkout(format) => {
+                let workout = workouts
+                    .iter()
+                    .find(|x| Some(&x.name) == self.selected_workout.as_ref());
+
+                if let Some(workout) = workout {
+                    let path = workout::export(workout, format, ftp)?;
+                    return Ok(Some(LibraryEvent::Exported(path)));
+                }
+            }
+            LibraryMessage::ImportPathChanged(value) => {
+                self.import_path = value;
+            }
+            LibraryMessage::ImportWorkout => {
+                let workout = workout::import(Path::new(self.import_path.trim()))?;
+                self.selected_workout = Some(workout.name.clone());
+                workouts.push(workout);
+                self.import_path.clear();
+            }
+            LibraryMessage::FtpTestSelected(test) => {
+                self.selected_ftp_test = test;
+            }
+            LibraryMessage::StartFtpTest => {
+                return Ok(Some(LibraryEvent::StartFtpTest(self.selected_ftp_test)));
+            }
+            LibraryMessage::RoutePathChanged(value) => {
+                self.route_path = value;
+            }
+            LibraryMessage::LoadRoute => {
+                let route = route::load(Path::new(self.route_path.trim())).map_err(|err| err.to_string())?;
+                self.route = Some(route_processing::process(&route, &ProcessingConfig::new()));
+                self.route_path.clear();
+            }
+            LibraryMessage::StartRoute => {
+                return Ok(self.route.clone().map(LibraryEvent::StartRoute));
+            }
+        }
+
+        return Ok(None);
+    }
+
+    pub fn view(&self, workouts: &[Workout], units: Units) -> Element<LibraryMessage> {
+        let names: Vec<String> = workouts.iter().map(|x| x.name.clone()).collect();
+        let mut start_btn = button("Start workout").padding(5.);
+        let mut export_erg_btn = button("Export .erg").padding(5.);
+        let mut export_mrc_btn = button("Export .mrc").padding(5.);
+        if self.selected_workout.is_some() {
+            start_btn = start_btn.on_press(LibraryMessage::StartWorkout);
+            export_erg_btn = export_erg_btn.on_press(LibraryMessage::ExportWorkout(Format::Erg));
+            export_mrc_btn = export_mrc_btn.on_press(LibraryMessage::ExportWorkout(Format::Mrc));
+        }
+
+        let workout_panel = column![
+            text("Workouts").size(24),
+            row![
+                pick_list(names, self.selected_workout.clone(), LibraryMessage::WorkoutSelected),
+                start_btn,
+                export_erg_btn,
+                export_mrc_btn
+            ]
+            .spacing(10),
+            row![
+                text_input("Workout file (.zwo, .erg, .mrc)", &self.import_path, LibraryMessage::ImportPathChanged)
+                    .on_submit(LibraryMessage::ImportWorkout)
+                    .padding(5.)
+                    .width(Length::Fixed(300.)),
+                button("Import").on_press(LibraryMessage::ImportWorkout).padding(5.)
+            ]
+            .spacing(10),
+            text("FTP tests").size(24),
+            row![
+                pick_list(&FtpTest::ALL[..], Some(self.selected_ftp_test), LibraryMessage::FtpTestSelected),
+                button("Start test").on_press(LibraryMessage::StartFtpTest).padding(5.)
+            ]
+            .spacing(10)
+        ]
+        .spacing(10);
+
+        let loaded = match &self.route {
+            Some(route) => column![
+                row![
+                    text(format!(
+                        "{}, {}, {:.0} m climbing",
+                        route.name,
+                        units.format_distance(route.length()),
+                        route_processing::total_ascent(&route.points)
+                    )),
+                    button("Start route").on_press(LibraryMessage::StartRoute).padding(5.)
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                Canvas::new(ElevationProfile { points: route.points.clone(), position: 0. })
+                    .width(Length::Fill)
+                    .height(Length::Fixed(120.))
+            ]
+            .spacing(5),
+            None => column![]
+        };
+
+        let route_panel = column![
+            text("Routes").size(24),
+            loaded,
+            row![
+                text_input("Route file (.gpx, .tcx)", &self.route_path, LibraryMessage::RoutePathChanged)
+                    .on_submit(LibraryMessage::LoadRoute)
+                    .padding(5.)
+                    .width(Length::Fixed(300.)),
+                button("Load route").on_press(LibraryMessage::LoadRoute).padding(5.)
+            ]
+            .spacing(10)
+        ]
+        .spacing(10);
+
+        return column![workout_panel, route_panel]
+            .spacing(30)
+            .into();
+    }
+}
@@ -1,33 +1,89 @@
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use bluetoothctl::{BluetoothError, Btle, listen_events};
+use bluetoothctl::{BluetoothError, Btle, Trainer, listen_events};
 
 pub mod bluetoothctl;
 pub mod state;
 pub mod device;
+pub mod stopwatch;
+pub mod autopause;
+pub mod recording;
+pub mod chart;
+pub mod zones;
+pub mod profile;
+pub mod settings;
+pub mod workout;
+pub mod ftms;
+pub mod zwo;
+pub mod erg;
+pub mod player;
+pub mod ftp_test;
+pub mod analytics;
+pub mod heart_rate;
+pub mod hrv;
+pub mod power_curve;
+pub mod curve;
+pub mod wbal;
+pub mod training_load;
+pub mod training;
+pub mod trimp;
+pub mod aerobic;
+pub mod physics;
+pub mod route;
+pub mod route_processing;
+pub mod elevation;
+pub mod tcx;
+pub mod fit;
+pub mod dashboard;
+pub mod tiles;
+pub mod library;
+pub mod sensors;
+pub mod navigation;
+pub mod history;
+pub mod notifications;
+pub mod notification_panel;
+pub mod csc;
+pub mod ride;
 
 use device::Device;
-use iced::theme::{self, Theme};
-use iced::{executor, subscription, time};
+use iced::theme::Theme;
+use iced::alignment::Horizontal;
+use iced::{clipboard, event, executor, keyboard, subscription, time};
+use iced::widget::canvas::Canvas;
 use iced::widget::{
-    button, checkbox, column, container, pick_list, row, slider, text, vertical_space, scrollable,
+    button, checkbox, column, container, progress_bar, row, text,
 };
 use iced::{
-    Alignment, Application, Command, Element, Length, Settings, Subscription,
+    Alignment, Application, Color, Command, Element, Length, Subscription,
 };
 use state::State;
-
-#[derive(Debug, Clone)]
-struct Stopwatch {
-    duration: Duration,
-    state: StopwatchState
-}
-
-#[derive(Debug, Clone)]
-enum StopwatchState {
-    Idle,
-    Ticking { last_tick: Instant }
-}
+use stopwatch::{Stopwatch, StopwatchState, format_duration};
+use autopause::{AutoPause, AutoPauseAction, AutoPauseConfig};
+use recording::{EventKind, Recording, RecordingError, RideKind, Sample};
+use zones::find_zone;
+use profile::{Profile, Units};
+use settings::{Settings, SettingsEvent, SettingsMessage};
+use workout::{Workout, WorkoutEngine};
+use player::PlayerMessage;
+use ftp_test::{FtpTest, FtpTestResult};
+use analytics::RideSummary;
+use trimp::TrainingImpulse;
+use aerobic::{AerobicAnalysis, DECOUPLING_THRESHOLD};
+use physics::{PhysicsModel, VirtualRide};
+use route::{Route, RouteProgress};
+use elevation::ElevationProfile;
+use hrv::{HrvMetrics, HrvRecord, Readiness};
+use curve::{Curve, CurveEvent, CurveMessage};
+use wbal::WPrimeBalance;
+use training::{Training, TrainingEvent, TrainingMessage};
+use history::{History, HistoryEvent, HistoryMessage};
+use dashboard::{DashboardMessage, Readings};
+use library::{Library, LibraryEvent, LibraryMessage};
+use sensors::SensorsMessage;
+use navigation::Screen;
+use ftms::TrainerCommand;
+use notifications::{Notifications, NotificationsMessage, SensorWatch, Severity};
+use ride::{Details, RideMessage, RideScreen};
 
 #[derive(Clone, Debug)]
 struct App {
@@ -35,13 +91,51 @@ struct App {
     state: Arc<Mutex<State>>,
     tick: Tick,
     display_heart_rate: u8,
-    display_power: u8, // don't know the datatype currently
+    display_power: u16,
+    display_speed: f32, // km/h
+    display_cadence: u8,
     display_scanned_devices: Vec<Device>,
     connected_devices: Vec<Device>,
-    errors: Vec<String>,
-    stopwatch: Stopwatch
+    notifications: Notifications,
+    sensor_watch: SensorWatch,
+    stopwatch: Stopwatch,
+    auto_pause: AutoPause,
+    recording: Recording,
+    profile: Profile,
+    screen: Screen,
+    settings: Option<Settings>,
+    workouts: Vec<Workout>,
+    library: Library,
+    workout: Option<WorkoutEngine>,
+    workout_cue: Option<String>,
+    target_power: Option<u16>,
+    ftp_test: Option<FtpTest>,
+    // estimate waiting for the rider to accept or dismiss
+    ftp_test_result: Option<FtpTestResult>,
+    // summary of the last finished ride
+    summary: Option<(RideSummary, TrainingImpulse)>,
+    // decoupling of the last finished ride, None for short rides or without heart rate
+    aerobic: Option<AerobicAnalysis>,
+    // rolling hrv over HRV_WINDOWS
+    live_hrv: Vec<Option<HrvMetrics>>,
+    readiness: Option<Readiness>,
+    hrv_history: Vec<HrvRecord>,
+    power_curve: Option<Curve>,
+    w_prime_balance: WPrimeBalance,
+    training: Option<Training>,
+    history: Option<History>,
+    ride: RideScreen,
+    // when the running pause started, recorded on resume
+    paused_at: Option<Instant>,
+    // speed and distance for trainers that only report power
+    virtual_ride: VirtualRide,
+    route_progress: Option<RouteProgress>,
+    // last ERG or simulation command the trainer got
+    trainer_command: Option<TrainerCommand>
 }
 
+const HRV_WINDOWS: [Duration; 2] = [Duration::from_secs(60), Duration::from_secs(5 * 60)];
+
 #[derive(Debug, Clone)]
 enum Tick {
     Idle,
@@ -51,22 +145,469 @@ enum Tick {
 #[derive(Debug, Clone)]
 enum Message {
     InitBluetooth(Result<Btle, BluetoothError>),
-    ScanDevices,
+    Navigate(Screen),
+    Sensors(SensorsMessage),
     FoundDevices(Result<Vec<Device>, ()>),
     Connect,
     Disconnect,
-    ListenEvents,
     ReadData(Result<(), BluetoothError>),
-    Tick(Instant)
+    Tick(Instant),
+    ToggleAutoPause(bool),
+    Ride(RideMessage),
+    // ride controls from the keyboard
+    Shortcut(Box<Message>),
+    Dashboard(DashboardMessage),
+    StartRide,
+    PauseRide,
+    ResumeRide,
+    ToggleRide,
+    Lap,
+    FinishRide,
+    DiscardRide,
+    ConfirmDiscard,
+    PowerCurve(CurveMessage),
+    Training(TrainingMessage),
+    History(HistoryMessage),
+    Notifications(NotificationsMessage),
+    Settings(SettingsMessage),
+    Player(PlayerMessage),
+    AcceptFtp,
+    DismissFtp,
+    StopRoute,
+    Library(LibraryMessage),
+    TargetPowerSent(Result<(), BluetoothError>),
+    SimulationSent(Result<(), BluetoothError>)
 }
 
-impl Stopwatch {
-    fn new() -> Stopwatch {
-        return Stopwatch {
-            duration: Duration::default(),
-            state: StopwatchState::Idle
+impl App {
+    fn notify(&mut self, severity: Severity, message: String) {
+        self.notifications.notify(severity, message, Instant::now());
+    }
+
+    fn start_ride(&mut self, now: Instant) {
+        self.summary = None;
+        self.aerobic = None;
+        self.recording = Recording::new();
+        self.recording.ftp = Some(self.profile.ftp);
+        self.recording.weight = Some(self.profile.weight);
+        self.recording.critical_power = Some(self.profile.critical_power);
+        self.recording.w_prime = Some(self.profile.w_prime);
+        self.w_prime_balance = WPrimeBalance::new(self.profile.critical_power, self.profile.w_prime);
+        self.virtual_ride = VirtualRide::new(PhysicsModel::new(&self.profile));
+        self.stopwatch = Stopwatch::new();
+        self.stopwatch.start(now);
+        self.recording.add_event(self.stopwatch.duration, EventKind::Start);
+    }
+
+    fn start_workout(&mut self, workout: Workout) {
+        if self.stopwatch.is_idle() {
+            self.auto_pause.set_manual_override(false);
+            self.start_ride(Instant::now());
+        }
+
+        self.recording.kind = RideKind::Workout;
+        self.recording.name = Some(workout.name.clone());
+        self.ftp_test = None;
+        self.route_progress = None;
+        self.workout = Some(WorkoutEngine::new(workout, self.profile.ftp, self.stopwatch.duration));
+        self.workout_cue = None;
+        self.target_power = None;
+    }
+
+    fn start_ftp_test(&mut self, test: FtpTest) {
+        if self.stopwatch.is_idle() {
+            self.auto_pause.set_manual_override(false);
+            self.start_ride(Instant::now());
+        }
+
+        self.recording.kind = RideKind::FtpTest;
+        self.recording.name = Some(test.to_string());
+        self.ftp_test = Some(test);
+        self.route_progress = None;
+        self.workout = Some(WorkoutEngine::new(test.workout(), self.profile.ftp, self.stopwatch.duration));
+        self.workout_cue = None;
+        self.target_power = None;
+    }
+
+    fn start_route(&mut self, route: Route) {
+        if self.stopwatch.is_idle() {
+            self.auto_pause.set_manual_override(false);
+            self.start_ride(Instant::now());
+        }
+
+        self.recording.kind = RideKind::Route;
+        self.recording.name = Some(route.name.clone());
+        // the trainer leaves ERG mode for the simulated grade
+        self.ftp_test = None;
+        self.workout = None;
+        self.target_power = None;
+        self.workout_cue = None;
+        self.route_progress = Some(RouteProgress::new(route));
+    }
+
+    // only what matters while riding, everything else has its own screen
+    fn ride_view(&self) -> Element<Message> {
+        let heart_rate_zones = self.profile.heart_rate_zones();
+        let power_zones = self.profile.power_zones();
+
+        let recent = &self.recording.samples[self.recording.samples.len().saturating_sub(3)..];
+        let three_second_power = recent.iter().map(|x| x.power as f32).sum::<f32>() / recent.len().max(1) as f32;
+        let live_summary = RideSummary::new(&self.recording.samples, self.profile.ftp);
+        let readings = Readings {
+            heart_rate: self.display_heart_rate,
+            power: self.display_power,
+            three_second_power,
+            cadence: self.display_cadence,
+            speed: self.display_speed,
+            distance: live_summary.distance,
+            heart_rate_zone: find_zone(&heart_rate_zones, self.display_heart_rate as f32),
+            power_zone: find_zone(&power_zones, self.display_power as f32),
+            elapsed: self.stopwatch.duration,
+            lap: self.recording.laps.len() + 1,
+            lap_time: self.recording.current_lap(self.stopwatch.duration),
+            normalized_power: live_summary.normalized_power,
+            work: live_summary.work,
+            w_prime_balance: self.w_prime_balance.balance,
+            target_power: self.target_power
+        };
+
+        let dashboard = match self.ride.editing_dashboard {
+            true => column![
+                tiles::view(&self.profile.dashboard, &readings, self.profile.units),
+                tiles::editor(&self.profile.dashboard).map(Message::Dashboard),
+                button("Done").on_press(Message::Ride(RideMessage::ToggleDashboardEditor)).padding(5.)
+            ],
+            false => column![
+                tiles::view(&self.profile.dashboard, &readings, self.profile.units),
+                button("Customize").on_press(Message::Ride(RideMessage::ToggleDashboardEditor)).padding(5.)
+            ]
+        }
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let toggle_btn = match self.stopwatch.state {
+            StopwatchState::Idle => button("Start").on_press(Message::StartRide),
+            StopwatchState::Ticking { .. } => button("Pause").on_press(Message::PauseRide),
+            StopwatchState::Paused => button("Resume").on_press(Message::ResumeRide)
+        };
+
+        let mut lap_btn = button("Lap");
+        let mut finish_btn = button("Finish");
+        let mut discard_btn = button("Discard");
+        if !self.stopwatch.is_idle() {
+            lap_btn = lap_btn.on_press(Message::Lap);
+            finish_btn = finish_btn.on_press(Message::FinishRide);
+            discard_btn = discard_btn.on_press(Message::DiscardRide);
+        }
+
+        let ride_controls = match self.ride.confirm_discard {
+            true => row![
+                text("Discard this ride?"),
+                button("Discard").on_press(Message::ConfirmDiscard).padding(5.),
+                button("Keep riding").on_press(Message::Ride(RideMessage::CancelDiscard)).padding(5.)
+            ],
+            false => row![
+                toggle_btn.padding(5.),
+                lap_btn.padding(5.),
+                finish_btn.padding(5.),
+                discard_btn.padding(5.)
+            ]
+        }
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let auto_pause = checkbox(
+            "Auto pause",
+            !self.auto_pause.manual_override,
+            Message::ToggleAutoPause
+        );
+
+        let workout_panel = match &self.workout {
+            Some(engine) => {
+                column![player::view(engine, self.stopwatch.duration, three_second_power, &power_zones).map(Message::Player)]
+            }
+            None => column![]
+        };
+        let route_panel = match &self.route_progress {
+            Some(progress) => column![
+                row![
+                    text(&progress.route.name).size(20),
+                    text(format!("Grade {:.1}%", progress.grade() * 100.)).size(20),
+                    text(self.profile.units.format_elevation(progress.elevation())),
+                    text(format!("{} to go", self.profile.units.format_distance(progress.remaining()))),
+                    button("Stop route").on_press(Message::StopRoute).padding(5.)
+                ]
+                .spacing(15)
+                .align_items(Alignment::Center),
+                Canvas::new(ElevationProfile { points: progress.route.points.clone(), position: progress.distance })
+                    .width(Length::Fill)
+                    .height(Length::Fixed(120.))
+            ],
+            None => column![]
+        }
+        .spacing(5)
+        .align_items(Alignment::Center);
+        let workout_cue = text(self.workout_cue.clone().unwrap_or_default()).size(20);
+        let ftp_offer = match &self.ftp_test_result {
+            Some(result) => row![
+                text(format!("New FTP {} W from the {} (currently {} W)", result.ftp, result.test, self.profile.ftp)),
+                button("Update profile").on_press(Message::AcceptFtp).padding(5.),
+                button("Dismiss").on_press(Message::DismissFtp).padding(5.)
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            None => row![]
+        };
+        let cadence_target = self.workout
+            .as_ref()
+            .and_then(|x| x.current(self.stopwatch.duration))
+            .and_then(|x| x.step.cadence)
+            .map(|x| x as f32);
+
+        let w_prime = &self.w_prime_balance;
+        let w_prime_gauge = column![
+            row![
+                text("W'"),
+                progress_bar(0.0..=1.0, w_prime.fraction())
+                    .width(Length::Fixed(300.))
+                    .height(Length::Fixed(20.)),
+                text(format!("{:.1} / {:.1} kJ", w_prime.balance.max(0.) / 1000., w_prime.w_prime / 1000.))
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            match w_prime.is_low() && self.stopwatch.is_ticking() {
+                true => text("W' nearly depleted, ease off").size(20).style(Color::from_rgb8(0xe5, 0x39, 0x35)),
+                false => text("")
+            }
+        ]
+        .align_items(Alignment::Center);
+
+        let ride_summary = match (&self.summary, self.recording.is_empty()) {
+            (_, false) => summary_view(
+                &live_summary,
+                &TrainingImpulse::new(&self.recording.samples, &self.profile),
+                self.profile.units
+            ),
+            (Some((summary, impulse)), true) => column![
+                text("Last ride").size(20),
+                summary_view(summary, impulse, self.profile.units),
+                match &self.aerobic {
+                    Some(aerobic) => aerobic_view(aerobic),
+                    None => text("Ride at least 20 min with heart rate for aerobic decoupling").into()
+                }
+            ]
+            .spacing(5)
+            .align_items(Alignment::Center)
+            .into(),
+            (None, true) => column![].into()
+        };
+
+        let details = match self.ride.show_details {
+            true => {
+                let samples = self.chart_samples();
+                column![
+                    button("Hide details").on_press(Message::Ride(RideMessage::ToggleDetails)).padding(5.),
+                    ride_summary,
+                    self.ride.details(Details {
+                        recording: &self.recording,
+                        samples: &samples,
+                        heart_rate_zones: &heart_rate_zones,
+                        power_zones: &power_zones,
+                        target_power: self.target_power.map(|x| x as f32),
+                        target_cadence: cadence_target,
+                        w_prime_balance: w_prime
+                    })
+                    .map(Message::Ride)
+                ]
+            }
+            false => column![
+                button("Show details").on_press(Message::Ride(RideMessage::ToggleDetails)).padding(5.)
+            ]
+        }
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        return column![
+            dashboard,
+            ride_controls,
+            auto_pause,
+            workout_panel,
+            route_panel,
+            workout_cue,
+            ftp_offer,
+            w_prime_gauge,
+            details
+        ]
+        .width(Length::Fill)
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into();
+    }
+
+    fn pause_ride(&mut self, now: Instant) {
+        self.stopwatch.pause(now);
+        self.recording.add_event(self.stopwatch.duration, EventKind::Pause);
+        self.paused_at = Some(now);
+    }
+
+    fn resume_ride(&mut self, now: Instant) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.recording.add_pause(self.stopwatch.duration, now - paused_at);
+        }
+        self.stopwatch.start(now);
+        self.recording.add_event(self.stopwatch.duration, EventKind::Resume);
+    }
+
+    fn finish_ride(&mut self, now: Instant) -> Result<(), RecordingError> {
+        self.stopwatch.pause(now);
+        self.recording.finish(self.stopwatch.duration);
+
+        let result = match self.recording.is_empty() {
+            true => Ok(()),
+            false => {
+                let ftp = self.recording.ftp.unwrap_or(self.profile.ftp);
+                self.summary = Some((
+                    RideSummary::new(&self.recording.samples, ftp),
+                    TrainingImpulse::new(&self.recording.samples, &self.profile)
+                ));
+                self.aerobic = AerobicAnalysis::new(&self.recording.samples);
+                self.recording.save().map(|path| {
+                    self.notifications.notify(Severity::Info, format!("Saved ride to {}", path.display()), now);
+                })
+            }
+        };
+
+        self.reset_ride();
+        return result;
+    }
+
+    fn chart_samples(&self) -> Vec<Sample> {
+        if !self.recording.is_empty() {
+            return self.recording.samples.clone();
+        }
+
+        // show the heart rate before the ride has started
+        let state = self.state.lock().unwrap();
+        return state.heart_rate_history
+            .iter()
+            .enumerate()
+            .map(|(index, heart_rate)| Sample {
+                elapsed: Duration::from_secs(index as u64),
+                heart_rate: *heart_rate,
+                power: 0,
+                cadence: 0,
+                speed: 0.
+            })
+            .collect();
+    }
+
+    // advances the workout and its target
+    fn update_workout(&mut self, previous: Duration) {
+        let Some(engine) = &self.workout else {
+            return;
+        };
+
+        let elapsed = self.stopwatch.duration;
+        if let Some(cue) = engine.cues(previous, elapsed).last() {
+            self.workout_cue = Some(cue.message.clone());
+        }
+
+        if let Some(test) = self.ftp_test {
+            let samples: Vec<Sample> = self.recording.samples
+                .iter()
+                .filter(|x| x.elapsed > engine.started_at)
+                .cloned()
+                .collect();
+
+            if test.effort_ended(engine, &samples, elapsed) {
+                self.finish_ftp_test(test, &samples, elapsed);
+            }
+        }
+
+        let Some(engine) = &self.workout else {
+            return;
+        };
+        if engine.is_finished(elapsed) {
+            self.workout = None;
+            self.workout_cue = Some(String::from("Workout complete"));
+        }
+
+        self.target_power = self.workout.as_ref().and_then(|x| x.target_power(elapsed));
+    }
+
+    // workout target or route grade, a flat road once neither is ridden,
+    // a command only counts as sent once a trainer got it
+    fn update_trainer(&mut self, trainer: Option<Trainer>) -> Command<Message> {
+        let Some(trainer) = trainer else {
+            self.trainer_command = None;
+            return Command::none();
+        };
+
+        let command = match (&self.workout, &self.route_progress) {
+            (Some(engine), _) => engine.trainer_command(self.stopwatch.duration),
+            (None, Some(progress)) => {
+                // a tenth of a percent is the smallest change worth sending
+                let grade = route::trainer_grade(progress.grade(), self.profile.route_difficulty);
+                Some(TrainerCommand::Simulation((grade * 1000.).round() / 1000.))
+            }
+            (None, None) => self.trainer_command.map(|_| ftms::RELEASE)
+        };
+
+        let Some(command) = command.filter(|x| Some(*x) != self.trainer_command) else {
+            return Command::none();
+        };
+
+        self.trainer_command = Some(command);
+        let model = self.virtual_ride.model;
+        return match command {
+            TrainerCommand::TargetPower(watts) => {
+                Command::perform(trainer.set_target_power(watts), Message::TargetPowerSent)
+            }
+            TrainerCommand::Simulation(grade) => Command::perform(
+                trainer.set_simulation(grade, model.rolling_resistance, model.wind_resistance()),
+                Message::SimulationSent
+            )
+        };
+    }
+
+    fn update_route(&mut self) {
+        if self.route_progress.as_ref().is_some_and(|x| x.is_finished()) {
+            self.route_progress = None;
+            self.workout_cue = Some(String::from("Route complete"));
         }
     }
+
+    fn finish_ftp_test(&mut self, test: FtpTest, samples: &[Sample], elapsed: Duration) {
+        self.ftp_test = None;
+        if let Some(engine) = &mut self.workout {
+            test.cool_down(engine, elapsed);
+        }
+
+        match test.estimate(samples) {
+            Some(ftp) => {
+                let result = FtpTestResult { test, ftp };
+                self.workout_cue = Some(format!("{} done, estimated FTP {} W", test, ftp));
+                self.recording.ftp_test = Some(result.clone());
+                self.ftp_test_result = Some(result);
+            }
+            None => {
+                self.workout_cue = Some(format!("{} ended too early to estimate FTP", test));
+            }
+        }
+    }
+
+    fn reset_ride(&mut self) {
+        self.ftp_test = None;
+        self.workout = None;
+        self.workout_cue = None;
+        self.target_power = None;
+        self.route_progress = None;
+        self.stopwatch = Stopwatch::new();
+        self.recording = Recording::new();
+        self.ride.confirm_discard = false;
+        self.paused_at = None;
+        self.auto_pause.hold_until_stopped();
+    }
 }
 
 impl Application for App {
@@ -76,6 +617,31 @@ impl Application for App {
     type Flags = ();
 
     fn new(flags: Self::Flags) -> (Self, iced::Command<Self::Message>) {
+        let now = Instant::now();
+        let mut notifications = Notifications::new();
+        let profile = match Profile::load() {
+            Ok((profile, errors)) => {
+                for err in errors {
+                    notifications.notify(Severity::Warning, err.to_string(), now);
+                }
+                profile
+            }
+            Err(err) => {
+                notifications.notify(Severity::Error, err.to_string(), now);
+                Profile::new()
+            }
+        };
+        let (workouts, workout_errors) = workout::load_workouts();
+        for err in workout_errors {
+            notifications.notify(Severity::Error, err, now);
+        }
+        let w_prime_balance = WPrimeBalance::new(profile.critical_power, profile.w_prime);
+        let virtual_ride = VirtualRide::new(PhysicsModel::new(&profile));
+        let hrv_history = hrv::load_history().unwrap_or_else(|err| {
+            notifications.notify(Severity::Error, err.to_string(), now);
+            return Vec::new();
+        });
+
         (
             Self {
                 btle: None,
@@ -83,10 +649,39 @@ impl Application for App {
                 tick: Tick::Listen,
                 display_heart_rate: 0,
                 display_power: 0,
+                display_speed: 0.,
+                display_cadence: 0,
                 display_scanned_devices: Vec::new(),
                 connected_devices: Vec::new(),
-                errors: Vec::new(),
-                stopwatch: Stopwatch::new()
+                notifications,
+                sensor_watch: SensorWatch::new(),
+                stopwatch: Stopwatch::new(),
+                auto_pause: AutoPause::new(AutoPauseConfig::new(&profile)),
+                recording: Recording::new(),
+                profile,
+                screen: Screen::Ride,
+                settings: None,
+                workouts,
+                library: Library::new(),
+                workout: None,
+                workout_cue: None,
+                target_power: None,
+                ftp_test: None,
+                ftp_test_result: None,
+                summary: None,
+                aerobic: None,
+                live_hrv: Vec::new(),
+                readiness: None,
+                hrv_history,
+                power_curve: None,
+                w_prime_balance,
+                training: None,
+                history: None,
+                ride: RideScreen::new(),
+                paused_at: None,
+                virtual_ride,
+                route_progress: None,
+                trainer_command: None
             },
             Command::perform(Btle::init(), Message::InitBluetooth)
         )
@@ -104,24 +699,24 @@ impl Application for App {
                         self.btle = Some(value);
                     },
                     Err(err) => {
-                        //todo: make a ui section to display errors
-                        self.errors.push(err.to_string())
+                        self.notify(Severity::Error, err.to_string());
                     }
                 }
             }
-            Message::ScanDevices => {
+            Message::Sensors(SensorsMessage::Scan) => {
                 match self.btle.clone() {
                     Some(value) => {
                         return Command::perform(value.scan(), Message::FoundDevices)
                     },
                     None => {
-                        self.errors.push(String::from("Btle has none value"));
+                        self.notify(Severity::Error, String::from("Bluetooth is not ready yet"));
                     }
-                    
                 }
             }
             Message::FoundDevices(resp) => {
-                println!("scanned {:?}", resp);
+                if resp.is_err() {
+                    self.notify(Severity::Error, String::from("Scanning for devices failed"));
+                }
                 if let Ok(value) = resp {
                     let connected_devices = value.clone()
                         .into_iter()
@@ -135,32 +730,317 @@ impl Application for App {
                     self.connected_devices = connected_devices;
                 }
             }
-            Message::ListenEvents => {
-                let btle = self.btle.clone().unwrap();
-                let state = Arc::clone(&self.state);
-
-                return Command::perform(listen_events(btle.adapter, state), Message::ReadData);
+            Message::Sensors(SensorsMessage::Listen) => {
+                match self.btle.clone() {
+                    Some(btle) => {
+                        let state = Arc::clone(&self.state);
+                        return Command::perform(listen_events(btle.adapter, state), Message::ReadData);
+                    },
+                    None => {
+                        self.notify(Severity::Error, String::from("Bluetooth is not ready yet"));
+                    }
+                }
             }
-            Message::ReadData(_) => {
-                println!("Started listening data");
+            Message::Sensors(SensorsMessage::StartReadiness) => {
+                let start = self.state.lock().unwrap().rr_intervals.len();
+                self.readiness = Some(Readiness::new(Instant::now(), start));
+            }
+            Message::Sensors(SensorsMessage::CancelReadiness) => {
+                self.readiness = None;
+            }
+            Message::ReadData(resp) => {
+                if let Err(err) = resp {
+                    self.notify(Severity::Error, err.to_string());
+                }
             }
             Message::Tick(now) => {
                 let clone = Arc::clone(&self.state);
-                let lock = clone.lock().unwrap();
+                let mut lock = clone.lock().unwrap();
+                self.notifications.expire(now);
+                for err in lock.errors.drain(..) {
+                    self.notify(Severity::Error, err);
+                }
+                for (severity, message) in self.sensor_watch.update(&lock.connected_devices, &lock.battery_levels) {
+                    self.notify(severity, message);
+                }
+
                 self.display_heart_rate = lock.heart_rate;
-                // todo set display power
-                // if power value comes in start the stopwatch
-                // first wait for couple of seconds
-                // also stop the timer if power values stop coming in
-                // once timer is started also start recording the data
-                // maybe use sqlite or just write a csv file
-                // to write a fit file probably use golang
+                self.display_power = lock.power;
+                self.display_cadence = lock.cadence;
+                self.live_hrv = HRV_WINDOWS
+                    .iter()
+                    .map(|x| HrvMetrics::new(hrv::window(&lock.rr_intervals, *x)))
+                    .collect();
+
+                if let Some(readiness) = self.readiness.as_ref().filter(|x| x.is_done(now)) {
+                    match HrvMetrics::new(&lock.rr_intervals[readiness.start..]) {
+                        Some(metrics) => {
+                            let record = HrvRecord::new(metrics);
+                            if let Err(err) = hrv::save_record(&record) {
+                                self.notify(Severity::Error, err.to_string());
+                            }
+                            self.hrv_history.push(record);
+                        }
+                        None => self.notify(Severity::Warning, String::from("No RR intervals were received during the readiness measurement"))
+                    }
+                    self.readiness = None;
+                }
 
-                if let StopwatchState::Ticking { last_tick } = &mut self.stopwatch.state {
-                    self.stopwatch.duration += now - *last_tick;
-                    *last_tick = now;
+                let trigger = self.auto_pause.config.trigger;
+                let value = trigger.value(&lock, now, self.auto_pause.config.stop_delay);
+                match self.auto_pause.update(value, now, &self.stopwatch.state) {
+                    Some(AutoPauseAction::Start) => self.start_ride(now),
+                    Some(AutoPauseAction::Pause) => self.pause_ride(now),
+                    Some(AutoPauseAction::Resume) => self.resume_ride(now),
+                    None => {}
+                }
+
+                let previous = self.stopwatch.duration;
+                // trainers that only count wheel revolutions report no speed of their own
+                self.display_speed = match lock.wheel_rate {
+                    Some(rate) if lock.speed == 0. => csc::speed(rate, self.profile.wheel_circumference),
+                    _ => lock.speed
+                };
+                let has_speed = lock.speed > 0. || lock.wheel_rate.is_some();
+                if self.stopwatch.is_ticking() {
+                    self.stopwatch.tick(now);
+                    let interval = self.stopwatch.duration.saturating_sub(previous);
+                    // flat road unless riding a route
+                    let grade = self.route_progress.as_ref().map_or(0., |x| x.grade());
+                    self.virtual_ride.update(lock.power as f32, grade, interval);
+                    if !has_speed {
+                        self.display_speed = self.virtual_ride.speed_kmh();
+                    }
+
+                    self.recording.add_sample(Sample {
+                        elapsed: self.stopwatch.duration,
+                        heart_rate: lock.heart_rate,
+                        power: lock.power,
+                        cadence: lock.cadence,
+                        speed: self.display_speed
+                    });
+
+                    let balance = self.w_prime_balance.update(lock.power as f32, interval);
+                    self.recording.w_prime_balance.push((self.stopwatch.duration, balance));
+
+                    if let Some(progress) = &mut self.route_progress {
+                        progress.advance(self.display_speed, interval);
+                    }
+                }
+
+                self.update_workout(previous);
+                self.update_route();
+                return self.update_trainer(lock.trainer.clone());
+            }
+            Message::ToggleAutoPause(value) => {
+                // turning auto pause off is a manual override
+                self.auto_pause.set_manual_override(!value);
+            }
+            Message::Ride(message) => {
+                self.ride.update(message);
+            }
+            Message::Shortcut(message) => {
+                // the keys only control the ride while it is on screen
+                if self.screen == Screen::Ride {
+                    return self.update(*message);
+                }
+            }
+            Message::Dashboard(message) => {
+                self.profile.dashboard.update(message);
+                if let Err(err) = self.profile.save() {
+                    self.notify(Severity::Error, err.to_string());
+                }
+            }
+            Message::StartRide => {
+                if self.stopwatch.is_idle() {
+                    self.auto_pause.set_manual_override(false);
+                    self.start_ride(Instant::now());
+                }
+            }
+            Message::PauseRide => {
+                if self.stopwatch.is_ticking() {
+                    // keep auto pause from resuming while the rider takes a break
+                    self.auto_pause.set_manual_override(true);
+                    self.pause_ride(Instant::now());
+                }
+            }
+            Message::ResumeRide => {
+                if self.stopwatch.is_paused() {
+                    self.auto_pause.set_manual_override(false);
+                    self.resume_ride(Instant::now());
+                }
+            }
+            Message::ToggleRide => {
+                let message = match self.stopwatch.state {
+                    StopwatchState::Idle => Message::StartRide,
+                    StopwatchState::Ticking { .. } => Message::PauseRide,
+                    StopwatchState::Paused => Message::ResumeRide
+                };
+                return self.update(message);
+            }
+            Message::Lap => {
+                if !self.stopwatch.is_idle() {
+                    self.stopwatch.tick(Instant::now());
+                    self.recording.lap(self.stopwatch.duration);
+                    self.recording.add_event(self.stopwatch.duration, EventKind::Lap);
+                }
+            }
+            Message::FinishRide => {
+                if !self.stopwatch.is_idle() {
+                    if let Err(err) = self.finish_ride(Instant::now()) {
+                        self.notify(Severity::Error, err.to_string());
+                    }
+                }
+            }
+            Message::DiscardRide => {
+                self.ride.confirm_discard = !self.stopwatch.is_idle();
+            }
+            Message::ConfirmDiscard => {
+                self.reset_ride();
+            }
+            Message::Library(message) => {
+                match self.library.update(message, &mut self.workouts, self.profile.ftp) {
+                    Ok(Some(LibraryEvent::StartWorkout(workout))) => {
+                        self.start_workout(workout);
+                        self.screen = Screen::Ride;
+                    }
+                    Ok(Some(LibraryEvent::StartFtpTest(test))) => {
+                        self.start_ftp_test(test);
+                        self.screen = Screen::Ride;
+                    }
+                    Ok(Some(LibraryEvent::StartRoute(route))) => {
+                        self.start_route(route);
+                        self.screen = Screen::Ride;
+                    }
+                    Ok(Some(LibraryEvent::Exported(path))) => {
+                        self.notify(Severity::Info, format!("Exported workout to {}", path.display()));
+                    }
+                    Ok(None) => {},
+                    Err(err) => self.notify(Severity::Error, err)
+                }
+            }
+            Message::AcceptFtp => {
+                if let Some(result) = self.ftp_test_result.take() {
+                    self.profile.ftp = result.ftp;
+                    if let Err(err) = self.profile.save() {
+                        self.notify(Severity::Error, err.to_string());
+                    }
                 }
             }
+            Message::DismissFtp => {
+                self.ftp_test_result = None;
+            }
+            Message::Player(message) => {
+                if let PlayerMessage::Stop = message {
+                    self.ftp_test = None;
+                    self.workout = None;
+                    self.target_power = None;
+                } else if let Some(engine) = &mut self.workout {
+                    message.apply(engine, self.stopwatch.duration);
+                }
+            }
+            Message::StopRoute => {
+                self.route_progress = None;
+            }
+            Message::TargetPowerSent(resp) | Message::SimulationSent(resp) => {
+                if let Err(err) = resp {
+                    self.notify(Severity::Error, err.to_string());
+                }
+            }
+            Message::Navigate(screen) => {
+                // screens showing saved data are rebuilt so they pick up the latest rides and profile
+                match screen {
+                    Screen::Settings => self.settings = Some(Settings::new(&self.profile)),
+                    Screen::PowerCurve => {
+                        let (rides, errors) = recording::load_rides();
+                        for err in errors {
+                            self.notify(Severity::Error, err);
+                        }
+                        self.power_curve = Some(Curve::new(&rides));
+                    }
+                    Screen::Training => {
+                        let (rides, errors) = recording::load_rides();
+                        for err in errors {
+                            self.notify(Severity::Error, err);
+                        }
+                        let plan = training_load::load_plan().unwrap_or_else(|err| {
+                            self.notify(Severity::Error, err.to_string());
+                            return Vec::new();
+                        });
+                        self.training = Some(Training::new(&rides, plan, &self.workouts, &self.profile));
+                    }
+                    Screen::History => {
+                        let (rides, errors) = recording::load_rides();
+                        for err in errors {
+                            self.notify(Severity::Error, err);
+                        }
+                        self.history = Some(History::new(rides, &self.profile));
+                    }
+                    Screen::Ride | Screen::Devices | Screen::Workouts | Screen::Notifications => {}
+                }
+                self.screen = screen;
+            }
+            Message::Settings(message) => {
+                if let Some(settings) = &mut self.settings {
+                    match settings.update(message) {
+                        Ok(Some(SettingsEvent::Saved(profile))) => {
+                            self.auto_pause.config = AutoPauseConfig::new(&profile);
+                            self.profile = profile;
+                            self.settings = None;
+                            self.screen = Screen::Ride;
+                        },
+                        Ok(Some(SettingsEvent::Closed)) => {
+                            self.settings = None;
+                            self.screen = Screen::Ride;
+                        },
+                        Ok(None) => {},
+                        Err(err) => self.notify(Severity::Error, err.to_string())
+                    }
+                }
+            }
+            Message::PowerCurve(message) => {
+                if let Some(curve) = &mut self.power_curve {
+                    if let Some(CurveEvent::Closed) = curve.update(message) {
+                        self.power_curve = None;
+                        self.screen = Screen::Ride;
+                    }
+                }
+            }
+            Message::Training(message) => {
+                if let Some(training) = &mut self.training {
+                    match training.update(message) {
+                        Ok(Some(TrainingEvent::Closed)) => {
+                            self.training = None;
+                            self.screen = Screen::Ride;
+                        }
+                        Ok(None) => {},
+                        Err(err) => self.notify(Severity::Error, err.to_string())
+                    }
+                }
+            }
+            Message::History(message) => {
+                if let Some(history) = &mut self.history {
+                    match history.update(message) {
+                        Ok(Some(HistoryEvent::Closed)) => {
+                            self.history = None;
+                            self.screen = Screen::Ride;
+                        }
+                        Ok(Some(HistoryEvent::ImportFailed(errors))) => {
+                            for err in errors {
+                                self.notify(Severity::Warning, err);
+                            }
+                        }
+                        Ok(None) => {},
+                        Err(err) => self.notify(Severity::Error, err.to_string())
+                    }
+                }
+            }
+            Message::Notifications(message) => {
+                if let NotificationsMessage::Copy = message {
+                    return clipboard::write(self.notifications.report());
+                }
+                self.notifications.update(message);
+            }
             _ => {
 
             }
@@ -170,72 +1050,151 @@ impl Application for App {
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        match self.tick {
+        let tick = match self.tick {
             Tick::Idle=> Subscription::none(),
             Tick::Listen => {
                 time::every(Duration::from_millis(1000))
                     .map(Message::Tick)
             },
-        }
+        };
+
+        return Subscription::batch(vec![tick, subscription::events_with(handle_shortcut)]);
     }
 
     fn view(&self) -> Element<Message> {
-        const MINUTE: u64 = 60;
-        const HOUR: u64 = 60 * MINUTE;
-
-        let scan_btn = button("Scan")
-            .on_press(Message::ScanDevices)
-            .padding(5.);
-
-        let display_devices = self.display_scanned_devices.clone();
-        let scanned_devices = column(
-            display_devices
-                .into_iter()
-                .map(|device| {
-                    row![text(format!("{} {} {}", device.name, device.address, device.is_connected))]
-                        .spacing(10)
-                        .into()
-                }).collect()
-        );
+        let screen = match self.screen {
+            Screen::Ride => self.ride_view(),
+            Screen::Devices => sensors::view(
+                &self.display_scanned_devices,
+                &HRV_WINDOWS,
+                &self.live_hrv,
+                self.readiness.as_ref(),
+                self.hrv_history.last()
+            )
+            .map(Message::Sensors),
+            Screen::Workouts => self.library.view(&self.workouts, self.profile.units).map(Message::Library),
+            Screen::History => match &self.history {
+                Some(history) => history.view().map(Message::History),
+                None => column![].into()
+            },
+            Screen::PowerCurve => match &self.power_curve {
+                Some(curve) => curve.view().map(Message::PowerCurve),
+                None => column![].into()
+            },
+            Screen::Training => match &self.training {
+                Some(training) => training.view().map(Message::Training),
+                None => column![].into()
+            },
+            Screen::Notifications => notification_panel::view(&self.notifications).map(Message::Notifications),
+            Screen::Settings => match &self.settings {
+                Some(settings) => settings.view().map(Message::Settings),
+                None => column![].into()
+            }
+        };
 
-        //todo: start listening automatically when device is connected
-        let listen_btn = button("Listen")
-            .on_press(Message::ListenEvents)
-            .padding(5.);
-
-        let heart_beat = text(self.display_heart_rate).size(40);
-
-        let seconds = self.stopwatch.duration.as_secs();
-        let stopwatch = text(format!(
-            "{:0>2}:{:0>2}:{:0>2}",
-            seconds / HOUR,
-            (seconds % HOUR) / MINUTE,
-            seconds % MINUTE
-        ))
-        .size(40);
-
-        let content = column![
-            scan_btn,
-            scanned_devices,
-            listen_btn,
-            heart_beat,
-            stopwatch
+        return row![
+            navigation::sidebar(self.screen, Message::Navigate),
+            column![
+                container(notification_panel::toasts(&self.notifications).map(Message::Notifications))
+                    .width(Length::Fill)
+                    .align_x(Horizontal::Right),
+                container(screen)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .padding(20)
+                    .center_x()
+                    .center_y()
+            ]
         ]
-        .width(Length::Fill)
-        .align_items(Alignment::Center)
-        .spacing(10);
+        .into();
+    }
+}
+
+fn summary_view<'a>(summary: &RideSummary, impulse: &TrainingImpulse, units: Units) -> Element<'a, Message> {
+    let optional = |value: Option<f32>, precision: usize| {
+        value.map_or(String::from("-"), |x| format!("{:.*}", precision, x))
+    };
 
-        container(content)
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .center_x()
-            .center_y()
-            .into()
+    return column![
+        row![
+            text(format!("Time {}", format_duration(summary.duration))),
+            text(format!("Avg {:.0} W", summary.average_power)),
+            text(format!("Max {} W", summary.max_power)),
+            text(format!("NP {} W", optional(summary.normalized_power, 0))),
+            text(format!("{:.0} kJ", summary.work)),
+            text(units.format_distance(summary.distance))
+        ]
+        .spacing(15),
+        row![
+            text(format!("IF {}", optional(summary.intensity_factor, 2))),
+            text(format!("TSS {}", optional(summary.training_stress_score, 0))),
+            text(format!("VI {}", optional(summary.variability_index, 2))),
+            text(format!("Cadence {} rpm", optional(summary.average_cadence, 0))),
+            text(format!("HR {} / {} bpm", optional(summary.average_heart_rate, 0), summary.max_heart_rate))
+        ]
+        .spacing(15),
+        row![
+            text(format!("TRIMP {:.0}", impulse.banister)),
+            text(format!("Edwards {:.0}", impulse.edwards)),
+            text(format!("{} kcal", optional(impulse.calories, 0)))
+        ]
+        .spacing(15)
+    ]
+    .spacing(5)
+    .into();
+}
+
+fn aerobic_view<'a>(aerobic: &AerobicAnalysis) -> Element<'a, Message> {
+    let decoupling_color = match aerobic.decoupling < DECOUPLING_THRESHOLD {
+        true => Color::from_rgb8(0x43, 0xa0, 0x47),
+        false => Color::from_rgb8(0xe5, 0x39, 0x35)
+    };
+    let note = match aerobic.steady {
+        true => "",
+        false => "Power too variable for a reliable decoupling"
+    };
+
+    return column![
+        row![
+            text(format!("EF {:.2}", aerobic.efficiency_factor)),
+            text(format!("1st half {:.2} / 2nd half {:.2}", aerobic.first_half, aerobic.second_half)),
+            text(format!("{} decoupling {:.1}%", aerobic.output, aerobic.decoupling)).style(decoupling_color),
+            text(format!(
+                "HR drift {}",
+                aerobic.heart_rate_drift.map_or(String::from("-"), |x| format!("{:+.1}%", x))
+            ))
+        ]
+        .spacing(15),
+        text(note)
+    ]
+    .spacing(5)
+    .align_items(Alignment::Center)
+    .into();
+}
+
+// space - start/pause/resume, l - lap, ctrl+f - finish, ctrl+d - discard
+fn handle_shortcut(event: iced::Event, status: event::Status) -> Option<Message> {
+    if let event::Status::Captured = status {
+        return None;
     }
+
+    let message = match event {
+        iced::Event::Keyboard(keyboard::Event::KeyPressed { key_code, modifiers }) => {
+            match (key_code, modifiers.control()) {
+                (keyboard::KeyCode::Space, false) => Message::ToggleRide,
+                (keyboard::KeyCode::L, false) => Message::Lap,
+                (keyboard::KeyCode::F, true) => Message::FinishRide,
+                (keyboard::KeyCode::D, true) => Message::DiscardRide,
+                _ => return None
+            }
+        }
+        _ => return None
+    };
+    return Some(Message::Shortcut(Box::new(message)));
 }
 
 fn main() -> iced::Result {
-    return App::run(Settings::default());
+    return App::run(iced::Settings::default());
 }
 
 
@@ -0,0 +1,73 @@
+use std::fmt;
+
+use iced::widget::{button, column, container, text};
+use iced::{theme, Element, Length};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    Ride,
+    Devices,
+    Workouts,
+    History,
+    PowerCurve,
+    Training,
+    Notifications,
+    Settings
+}
+
+impl Screen {
+    pub const ALL: [Screen; 8] = [
+        Screen::Ride,
+        Screen::Devices,
+        Screen::Workouts,
+        Screen::History,
+        Screen::PowerCurve,
+        Screen::Training,
+        Screen::Notifications,
+        Screen::Settings
+    ];
+}
+
+impl fmt::Display for Screen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Screen::Ride => "Ride",
+            Screen::Devices => "Devices",
+            Screen::Workouts => "Workouts",
+            Screen::History => "History",
+            Screen::PowerCurve => "Power curve",
+            Screen::Training => "Training load",
+            Screen::Notifications => "Notifications",
+            Screen::Settings => "Settings"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+// one button per screen, the current one is highlighted
+pub fn sidebar<'a, Message: Clone + 'a>(current: Screen, on_select: fn(Screen) -> Message) -> Element<'a, Message> {
+    let buttons = column(
+        Screen::ALL.into_iter()
+            .map(|screen| {
+                let style = match screen == current {
+                    true => theme::Button::Primary,
+                    false => theme::Button::Text
+                };
+                button(text(screen))
+                    .on_press(on_select(screen))
+                    .style(style)
+                    .width(Length::Fill)
+                    .padding(10.)
+                    .into()
+            })
+            .collect()
+    )
+    .spacing(5);
+
+    return container(buttons)
+        .style(theme::Container::Box)
+        .width(Length::Fixed(160.))
+        .height(Length::Fill)
+        .padding(10)
+        .into();
+}
@@ -0,0 +1,92 @@
+use iced::widget::{button, column, container, row, scrollable, text};
+use iced::{theme, Alignment, Color, Element, Length};
+
+use crate::notifications::{Notifications, NotificationsMessage, Severity, timestamp};
+
+fn color(severity: Severity) -> Color {
+    match severity {
+        Severity::Info => Color::from_rgb8(0x3b, 0x82, 0xf6),
+        Severity::Warning => Color::from_rgb8(0xf5, 0x9e, 0x0b),
+        Severity::Error => Color::from_rgb8(0xdc, 0x26, 0x26)
+    }
+}
+
+// latest toasts on top of the current screen
+pub fn toasts(notifications: &Notifications) -> Element<NotificationsMessage> {
+    return column(
+        notifications.toasts
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, toast)| {
+                container(
+                    row![
+                        text(toast.notification.severity).style(color(toast.notification.severity)),
+                        text(&toast.notification.message).width(Length::Fill),
+                        button("x").on_press(NotificationsMessage::Dismiss(index)).style(theme::Button::Text)
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                )
+                .style(theme::Container::Box)
+                .width(Length::Fixed(420.))
+                .padding(5)
+                .into()
+            })
+            .collect()
+    )
+    .spacing(5)
+    .into();
+}
+
+// full log, newest first
+pub fn view(notifications: &Notifications) -> Element<NotificationsMessage> {
+    let entries: Element<NotificationsMessage> = match notifications.log.is_empty() {
+        true => text("Nothing to report").into(),
+        false => scrollable(
+            column(
+                notifications.log
+                    .iter()
+                    .rev()
+                    .map(|x| {
+                        row![
+                            text(timestamp(x.created_at)).size(14),
+                            text(x.severity).style(color(x.severity)).width(Length::Fixed(70.)),
+                            text(&x.message)
+                        ]
+                        .spacing(10)
+                        .into()
+                    })
+                    .collect()
+            )
+            .spacing(5)
+        )
+        .height(Length::Fill)
+        .into()
+    };
+
+    let mut copy_btn = button("Copy to clipboard").padding(5.);
+    let mut clear_btn = button("Clear").padding(5.);
+    if !notifications.log.is_empty() {
+        copy_btn = copy_btn.on_press(NotificationsMessage::Copy);
+        clear_btn = clear_btn.on_press(NotificationsMessage::Clear);
+    }
+
+    return column![
+        text("Notifications").size(24),
+        row![
+            text(format!(
+                "{} errors, {} warnings",
+                notifications.count(Severity::Error),
+                notifications.count(Severity::Warning)
+            )),
+            copy_btn,
+            clear_btn
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center),
+        entries
+    ]
+    .spacing(10)
+    .into();
+}
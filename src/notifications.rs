@@ -0,0 +1,273 @@
+// everything worth telling the rider, shortly as a toast and for good in the log
+
+use std::fmt;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::profile::Date;
+
+// older entries are dropped
+pub const MAX_LOG: usize = 500;
+// percent
+pub const LOW_BATTERY: u8 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub severity: Severity,
+    pub message: String,
+    pub created_at: SystemTime
+}
+
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub notification: Notification,
+    pub expires_at: Instant
+}
+
+#[derive(Debug, Clone)]
+pub struct Notifications {
+    pub log: Vec<Notification>,
+    pub toasts: Vec<Toast>
+}
+
+#[derive(Debug, Clone)]
+pub enum NotificationsMessage {
+    Dismiss(usize),
+    Clear,
+    Copy
+}
+
+// turns changes in the connected sensors into notifications
+#[derive(Debug, Clone)]
+pub struct SensorWatch {
+    connected: Vec<String>,
+    // disconnected at some point, coming back is a reconnect
+    lost: Vec<String>,
+    low_battery: Vec<String>
+}
+
+impl Severity {
+    // how long the toast stays up
+    pub fn duration(&self) -> Duration {
+        match self {
+            Severity::Info => Duration::from_secs(5),
+            Severity::Warning => Duration::from_secs(10),
+            Severity::Error => Duration::from_secs(20)
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Severity::Info => "Info",
+            Severity::Warning => "Warning",
+            Severity::Error => "Error"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+impl Notification {
+    pub fn new(severity: Severity, message: String) -> Notification {
+        return Notification { severity, message, created_at: SystemTime::now() };
+    }
+}
+
+impl fmt::Display for Notification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{} [{}] {}", timestamp(self.created_at), self.severity, self.message);
+    }
+}
+
+impl Notifications {
+    pub fn new() -> Notifications {
+        return Notifications { log: Vec::new(), toasts: Vec::new() };
+    }
+
+    pub fn push(&mut self, notification: Notification, now: Instant) {
+        self.toasts.push(Toast {
+            expires_at: now + notification.severity.duration(),
+            notification: notification.clone()
+        });
+
+        self.log.push(notification);
+        if self.log.len() > MAX_LOG {
+            self.log.drain(..self.log.len() - MAX_LOG);
+        }
+    }
+
+    pub fn notify(&mut self, severity: Severity, message: String, now: Instant) {
+        self.push(Notification::new(severity, message), now);
+    }
+
+    // called every tick
+    pub fn expire(&mut self, now: Instant) {
+        self.toasts.retain(|x| x.expires_at > now);
+    }
+
+    pub fn update(&mut self, message: NotificationsMessage) {
+        match message {
+            NotificationsMessage::Dismiss(index) => {
+                if index < self.toasts.len() {
+                    self.toasts.remove(index);
+                }
+            }
+            NotificationsMessage::Clear => {
+                self.log.clear();
+                self.toasts.clear();
+            }
+            // the clipboard is written by the app
+            NotificationsMessage::Copy => {}
+        }
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        return self.log.iter().filter(|x| x.severity == severity).count();
+    }
+
+    // whole log as text, pasted into bug reports
+    pub fn report(&self) -> String {
+        return self.log.iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>()
+            .join("\n");
+    }
+}
+
+impl SensorWatch {
+    pub fn new() -> SensorWatch {
+        return SensorWatch {
+            connected: Vec::new(),
+            lost: Vec::new(),
+            low_battery: Vec::new()
+        };
+    }
+
+    // batteries are (sensor, percent)
+    pub fn update(&mut self, connected: &[String], batteries: &[(String, u8)]) -> Vec<(Severity, String)> {
+        let mut result = Vec::new();
+
+        for name in connected.iter().filter(|x| !self.connected.contains(x)) {
+            match self.lost.contains(name) {
+                true => result.push((Severity::Info, format!("{} reconnected", name))),
+                false => result.push((Severity::Info, format!("{} connected", name)))
+            }
+        }
+
+        for name in self.connected.iter().filter(|x| !connected.contains(x)) {
+            result.push((Severity::Warning, format!("{} disconnected", name)));
+            if !self.lost.contains(name) {
+                self.lost.push(name.clone());
+            }
+        }
+
+        // warn once per sensor, again only after it was charged
+        for (name, level) in batteries {
+            let is_low = *level <= LOW_BATTERY;
+            let was_low = self.low_battery.contains(name);
+            if is_low && !was_low {
+                result.push((Severity::Warning, format!("{} battery is low ({}%)", name, level)));
+                self.low_battery.push(name.clone());
+            } else if !is_low && was_low {
+                self.low_battery.retain(|x| x != name);
+            }
+        }
+
+        self.connected = connected.to_vec();
+
+        return result;
+    }
+}
+
+// e.g. 2024-03-02 07:45:09 UTC
+pub fn timestamp(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let time_of_day = seconds % 86_400;
+    return format!(
+        "{} {:02}:{:02}:{:02} UTC",
+        Date::from_unix_days((seconds / 86_400) as i64),
+        time_of_day / 3600,
+        time_of_day % 3600 / 60,
+        time_of_day % 60
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant, UNIX_EPOCH};
+
+    use crate::notifications::{MAX_LOG, Notification, Notifications, NotificationsMessage, SensorWatch, Severity, timestamp};
+
+    #[test]
+    fn toasts_expire_but_stay_in_the_log() {
+        let now = Instant::now();
+        let mut notifications = Notifications::new();
+        notifications.notify(Severity::Info, String::from("Heart rate strap connected"), now);
+        notifications.notify(Severity::Error, String::from("Adapter not found"), now);
+
+        notifications.expire(now + Duration::from_secs(6));
+        assert_eq!(notifications.toasts.len(), 1);
+        assert_eq!(notifications.toasts[0].notification.severity, Severity::Error);
+
+        notifications.update(NotificationsMessage::Dismiss(0));
+        assert!(notifications.toasts.is_empty());
+        assert_eq!(notifications.log.len(), 2);
+        assert_eq!(notifications.count(Severity::Error), 1);
+
+        for index in 0..MAX_LOG {
+            notifications.notify(Severity::Info, index.to_string(), now);
+        }
+        assert_eq!(notifications.log.len(), MAX_LOG);
+        assert_eq!(notifications.log[0].message, "0");
+
+        notifications.update(NotificationsMessage::Clear);
+        assert!(notifications.log.is_empty());
+    }
+
+    #[test]
+    fn formats_report() {
+        let mut notifications = Notifications::new();
+        notifications.push(Notification {
+            severity: Severity::Warning,
+            message: String::from("Trainer battery is low (10%)"),
+            created_at: UNIX_EPOCH + Duration::from_secs(1_709_365_509)
+        }, Instant::now());
+
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01 00:00:00 UTC");
+        assert_eq!(notifications.report(), "2024-03-02 07:45:09 UTC [Warning] Trainer battery is low (10%)");
+    }
+
+    #[test]
+    fn watches_sensors() {
+        let both = [String::from("HRM-Pro"), String::from("KICKR")];
+        let (strap, trainer) = (&both[..1], &both[1..]);
+        let mut watch = SensorWatch::new();
+
+        let events = watch.update(&both, &[(both[0].clone(), 80)]);
+        assert_eq!(events, vec![
+            (Severity::Info, String::from("HRM-Pro connected")),
+            (Severity::Info, String::from("KICKR connected"))
+        ]);
+        assert!(watch.update(&both, &[(both[0].clone(), 80)]).is_empty());
+
+        let events = watch.update(strap, &[(both[0].clone(), 12)]);
+        assert_eq!(events, vec![
+            (Severity::Warning, String::from("KICKR disconnected")),
+            (Severity::Warning, String::from("HRM-Pro battery is low (12%)"))
+        ]);
+        assert!(watch.update(strap, &[(both[0].clone(), 11)]).is_empty());
+
+        let events = watch.update(trainer, &[]);
+        assert_eq!(events, vec![
+            (Severity::Info, String::from("KICKR reconnected")),
+            (Severity::Warning, String::from("HRM-Pro disconnected"))
+        ]);
+    }
+}
@@ -0,0 +1,166 @@
+// virtual speed from power for trainers that only report watts
+// REF: Martin et al. 1998, "Validation of a mathematical model for road cycling power"
+
+use std::time::Duration;
+
+use crate::profile::Profile;
+
+const GRAVITY: f32 = 9.81;
+// integration step, s
+const STEP: f32 = 0.1;
+// pedaling force is capped below this speed, m/s
+const MIN_PROPULSION_SPEED: f32 = 1.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsModel {
+    // rider and bike, kg
+    pub mass: f32,
+    // m²
+    pub drag_area: f32,
+    pub rolling_resistance: f32,
+    // kg/m³
+    pub air_density: f32,
+    // share of the power lost between the pedals and the wheel
+    pub drivetrain_loss: f32
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VirtualRide {
+    pub model: PhysicsModel,
+    // m/s
+    pub speed: f32,
+    // m
+    pub distance: f32
+}
+
+impl PhysicsModel {
+    pub fn new(profile: &Profile) -> PhysicsModel {
+        return PhysicsModel {
+            mass: profile.weight + profile.bike_weight,
+            drag_area: profile.drag_area,
+            rolling_resistance: profile.rolling_resistance,
+            air_density: profile.air_density,
+            drivetrain_loss: profile.drivetrain_loss / 100.
+        };
+    }
+
+    // force against the rider at speed in m/s, grade as a fraction
+    pub fn resistance(&self, speed: f32, grade: f32) -> f32 {
+        let angle = grade.atan();
+        let weight = self.mass * GRAVITY;
+        let gravity = weight * angle.sin();
+        let rolling = weight * angle.cos() * self.rolling_resistance;
+        let drag = self.wind_resistance() * speed * speed;
+        return gravity + rolling + drag;
+    }
+
+    // drag force per (m/s)², the Cw a trainer simulates
+    pub fn wind_resistance(&self) -> f32 {
+        return 0.5 * self.air_density * self.drag_area;
+    }
+
+    // power at the pedals needed to hold the speed
+    pub fn power(&self, speed: f32, grade: f32) -> f32 {
+        return self.resistance(speed, grade) * speed / (1. - self.drivetrain_loss);
+    }
+}
+
+impl VirtualRide {
+    pub fn new(model: PhysicsModel) -> VirtualRide {
+        return VirtualRide {
+            model,
+            speed: 0.,
+            distance: 0.
+        };
+    }
+
+    // integrates the net force, so the rider speeds up and coasts down like on the road
+    pub fn update(&mut self, power: f32, grade: f32, duration: Duration) -> f32 {
+        if self.model.mass <= 0. {
+            return self.speed;
+        }
+
+        let mut remaining = duration.as_secs_f32();
+        while remaining > 0. {
+            let step = remaining.min(STEP);
+            let propulsion = power * (1. - self.model.drivetrain_loss) / self.speed.max(MIN_PROPULSION_SPEED);
+            let force = propulsion - self.model.resistance(self.speed, grade);
+            // rolling resistance holds a stopped bike, it never rolls backwards
+            self.speed = (self.speed + force / self.model.mass * step).max(0.);
+            self.distance += self.speed * step;
+            remaining -= step;
+        }
+
+        return self.speed;
+    }
+
+    pub fn speed_kmh(&self) -> f32 {
+        return self.speed * 3.6;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::physics::{PhysicsModel, VirtualRide};
+
+    // upper bound when solving for the steady speed, m/s
+    const MAX_SPEED: f32 = 40.;
+
+    // speed the rider settles at when holding the power
+    fn steady_speed(model: &PhysicsModel, power: f32, grade: f32) -> f32 {
+        let mut low = 0.;
+        let mut high = MAX_SPEED;
+        for _ in 0..50 {
+            let middle = (low + high) / 2.;
+            match model.power(middle, grade) < power {
+                true => low = middle,
+                false => high = middle
+            }
+        }
+        return low;
+    }
+
+    fn model() -> PhysicsModel {
+        return PhysicsModel {
+            mass: 84.,
+            drag_area: 0.32,
+            rolling_resistance: 0.004,
+            air_density: 1.225,
+            drivetrain_loss: 0.03
+        };
+    }
+
+    #[test]
+    fn steady_speed_balances_power() {
+        let model = model();
+        let flat = steady_speed(&model, 200., 0.);
+
+        // 194 W at the wheel = 3.3 v + 0.196 v³
+        assert!((flat - 9.40).abs() < 0.02, "{}", flat);
+        assert!((model.power(flat, 0.) - 200.).abs() < 0.1);
+        assert!(steady_speed(&model, 200., 0.05) < flat / 2.);
+        // coasting downhill
+        assert!(steady_speed(&model, 0., -0.05) > 10.);
+    }
+
+    #[test]
+    fn virtual_speed_builds_up_to_steady_speed() {
+        let model = model();
+        let mut ride = VirtualRide::new(model);
+
+        ride.update(200., 0., Duration::from_secs(1));
+        assert!(ride.speed > 0. && ride.speed < 3.);
+
+        for _ in 0..600 {
+            ride.update(200., 0., Duration::from_secs(1));
+        }
+        assert!((ride.speed - steady_speed(&model, 200., 0.)).abs() < 0.05, "{}", ride.speed);
+        assert!(ride.distance > 5000. && ride.distance < 601. * 9.4, "{}", ride.distance);
+
+        // stopping pedaling slows down, uphill stops the bike
+        ride.update(0., 0.1, Duration::from_secs(60));
+        assert_eq!(ride.speed, 0.);
+    }
+}
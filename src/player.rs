@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use iced::widget::canvas::{self, Canvas, Cursor, Frame, Geometry, Path, Stroke};
+use iced::widget::{button, column, row, text};
+use iced::{Alignment, Color, Element, Length, Point, Rectangle, Renderer, Theme};
+
+use crate::chart::zone_color;
+use crate::stopwatch::format_duration;
+use crate::workout::{Compliance, Target, WorkoutEngine};
+use crate::zones::{Zone, find_zone};
+
+#[derive(Debug, Clone)]
+pub enum PlayerMessage {
+    Skip,
+    Extend,
+    IntensityUp,
+    IntensityDown,
+    Stop
+}
+
+// the whole workout as target blocks with a marker at the current position
+struct WorkoutProfile {
+    // (start, duration, (from, to)) in seconds and watts, None is a free segment
+    segments: Vec<(f32, f32, Option<(f32, f32)>)>,
+    zones: Vec<Zone>,
+    position: f32,
+    duration: f32
+}
+
+const EXTEND_BY: Duration = Duration::from_secs(60);
+const INTENSITY_STEP: f32 = 0.05;
+
+pub fn view<'a>(engine: &WorkoutEngine, elapsed: Duration, power: f32, zones: &[Zone]) -> Element<'a, PlayerMessage> {
+    let target = engine.target_power(elapsed);
+    let current = engine.current(elapsed);
+
+    let step = match &current {
+        Some(x) => text(format!("{}  {}", x.step.describe(engine.ftp, engine.intensity), format_duration(x.remaining))),
+        None => text("Done")
+    };
+
+    let next = engine.next_step(elapsed).map_or(String::from("-"), |x| format!(
+        "{} for {}",
+        x.describe(engine.ftp, engine.intensity),
+        format_duration(x.duration)
+    ));
+
+    let (target_text, compliance) = match target {
+        Some(watts) => {
+            let color = match Compliance::new(power, watts as f32) {
+                Compliance::OnTarget => Color::from_rgb8(0x43, 0xa0, 0x47),
+                Compliance::Close => Color::from_rgb8(0xfb, 0x8c, 0x00),
+                Compliance::Off => Color::from_rgb8(0xe5, 0x39, 0x35)
+            };
+            (format!("{:.0} / {} W", power, watts), color)
+        }
+        None => (format!("{:.0} W", power), Color::BLACK)
+    };
+
+    let controls = row![
+        button("-5%").on_press(PlayerMessage::IntensityDown).padding(5.),
+        text(format!("{:.0}%", engine.intensity * 100.)),
+        button("+5%").on_press(PlayerMessage::IntensityUp).padding(5.),
+        button("+1 min").on_press(PlayerMessage::Extend).padding(5.),
+        button("Skip").on_press(PlayerMessage::Skip).padding(5.),
+        button("Stop workout").on_press(PlayerMessage::Stop).padding(5.)
+    ]
+    .spacing(10)
+    .align_items(Alignment::Center);
+
+    let profile = WorkoutProfile::new(engine, elapsed, zones);
+
+    return column![
+        text(&engine.workout.name).size(20),
+        Canvas::new(profile).width(Length::Fill).height(Length::Fixed(100.)),
+        row![
+            step.size(30),
+            text(target_text).size(30).style(compliance)
+        ]
+        .spacing(20),
+        text(format!("Next: {}", next)),
+        text(format!("Remaining {}", format_duration(engine.remaining(elapsed)))),
+        controls
+    ]
+    .spacing(5)
+    .align_items(Alignment::Center)
+    .into();
+}
+
+impl PlayerMessage {
+    pub fn apply(&self, engine: &mut WorkoutEngine, elapsed: Duration) {
+        match self {
+            PlayerMessage::Skip => engine.skip(elapsed),
+            PlayerMessage::Extend => engine.extend(elapsed, EXTEND_BY),
+            PlayerMessage::IntensityUp => engine.adjust_intensity(INTENSITY_STEP),
+            PlayerMessage::IntensityDown => engine.adjust_intensity(-INTENSITY_STEP),
+            PlayerMessage::Stop => {}
+        }
+    }
+}
+
+impl WorkoutProfile {
+    fn new(engine: &WorkoutEngine, elapsed: Duration, zones: &[Zone]) -> WorkoutProfile {
+        let mut segments = Vec::new();
+        let mut start = 0.;
+        for step in &engine.steps {
+            let duration = step.duration.as_secs_f32();
+            let target = match step.target {
+                Target::FreeRide | Target::MaxEffort => None,
+                _ => {
+                    let from = step.target_watts(Duration::default(), engine.ftp).unwrap_or_default();
+                    let to = step.target_watts(step.duration, engine.ftp).unwrap_or_default();
+                    Some((from * engine.intensity, to * engine.intensity))
+                }
+            };
+            segments.push((start, duration, target));
+            start += duration;
+        }
+
+        return WorkoutProfile {
+            segments,
+            zones: zones.to_vec(),
+            position: engine.position(elapsed).as_secs_f32(),
+            duration: start.max(1.)
+        };
+    }
+}
+
+impl<Message> canvas::Program<Message> for WorkoutProfile {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: Cursor
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+
+        let max_value = self.segments.iter()
+            .filter_map(|(_, _, target)| *target)
+            .map(|(from, to)| from.max(to))
+            .fold(1., f32::max) * 1.1;
+
+        let width = bounds.width;
+        let height = bounds.height;
+        let to_x = |time: f32| time / self.duration * width;
+        let to_y = |value: f32| height - (value / max_value).min(1.) * height;
+
+        for (start, duration, target) in &self.segments {
+            let (from, to, color) = match target {
+                Some((from, to)) => {
+                    let zone = find_zone(&self.zones, (from + to) / 2.).unwrap_or(0);
+                    (*from, *to, zone_color(zone))
+                }
+                // free segments are drawn as a low grey block
+                None => (max_value * 0.3, max_value * 0.3, Color::from_rgb8(0xbd, 0xbd, 0xbd))
+            };
+
+            let block = Path::new(|builder| {
+                builder.move_to(Point::new(to_x(*start), height));
+                builder.line_to(Point::new(to_x(*start), to_y(from)));
+                builder.line_to(Point::new(to_x(start + duration), to_y(to)));
+                builder.line_to(Point::new(to_x(start + duration), height));
+                builder.close();
+            });
+            frame.fill(&block, color);
+        }
+
+        let x = to_x(self.position);
+        let marker = Path::line(Point::new(x, 0.), Point::new(x, height));
+        frame.stroke(&marker, Stroke::default().with_width(2.).with_color(Color::BLACK));
+
+        return vec![frame.into_geometry()];
+    }
+}
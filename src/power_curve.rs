@@ -0,0 +1,290 @@
+// mean-maximal power and critical power models, power samples are expected once per second
+
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Effort {
+    // seconds
+    pub duration: u32,
+    pub power: f32,
+    pub recorded_at: SystemTime
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PowerCurve {
+    // sorted by duration
+    pub efforts: Vec<Effort>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CriticalPower {
+    pub cp: f32,
+    // J
+    pub w_prime: f32,
+    // only the 3 parameter model has a finite maximal power
+    pub p_max: Option<f32>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateRange {
+    SixWeeks,
+    ThreeMonths,
+    Year,
+    AllTime
+}
+
+// every second up to this duration, then a geometric progression
+const DENSE_DURATIONS: u32 = 30;
+const DURATION_GROWTH: f32 = 1.05;
+// efforts used to fit the models
+const TWO_PARAMETER_RANGE: (u32, u32) = (120, 1200);
+const THREE_PARAMETER_RANGE: (u32, u32) = (10, 1200);
+const MIN_FIT_POINTS: usize = 3;
+
+impl PowerCurve {
+    pub fn new(power: &[f32], recorded_at: SystemTime) -> PowerCurve {
+        // prefix sums make every window average O(1)
+        let mut sums = vec![0.; power.len() + 1];
+        for (index, value) in power.iter().enumerate() {
+            sums[index + 1] = sums[index] + *value as f64;
+        }
+
+        let efforts = durations(power.len() as u32)
+            .into_iter()
+            .map(|duration| {
+                let window = duration as usize;
+                let best = (window..sums.len())
+                    .map(|end| sums[end] - sums[end - window])
+                    .fold(0., f64::max);
+                Effort { duration, power: (best / window as f64) as f32, recorded_at }
+            })
+            .collect();
+
+        return PowerCurve { efforts };
+    }
+
+    // keeps the best effort for every duration
+    pub fn merge(&mut self, other: &PowerCurve) {
+        for effort in &other.efforts {
+            match self.efforts.binary_search_by_key(&effort.duration, |x| x.duration) {
+                Ok(index) => {
+                    if effort.power > self.efforts[index].power {
+                        self.efforts[index] = *effort;
+                    }
+                }
+                Err(index) => self.efforts.insert(index, *effort)
+            }
+        }
+    }
+
+    // best effort of at least the given duration
+    pub fn best(&self, duration: u32) -> Option<&Effort> {
+        return self.efforts
+            .iter()
+            .filter(|x| x.duration >= duration)
+            .max_by(|a, b| a.power.total_cmp(&b.power));
+    }
+
+    fn points(&self, (from, to): (u32, u32)) -> Vec<(f64, f64)> {
+        return self.efforts
+            .iter()
+            .filter(|x| x.duration >= from && x.duration <= to && x.power > 0.)
+            .map(|x| (x.duration as f64, x.power as f64))
+            .collect();
+    }
+}
+
+impl CriticalPower {
+    // work = CP * t + W'
+    pub fn fit_two_parameter(curve: &PowerCurve) -> Option<CriticalPower> {
+        let points: Vec<(f64, f64)> = curve.points(TWO_PARAMETER_RANGE)
+            .into_iter()
+            .map(|(duration, power)| (duration, power * duration))
+            .collect();
+        if points.len() < MIN_FIT_POINTS {
+            return None;
+        }
+
+        let (cp, w_prime, _) = linear_fit(&points)?;
+        return CriticalPower::valid(cp, w_prime, None);
+    }
+
+    // P = CP + W' / (t + k), k = W' / (Pmax - CP) is found with a grid search
+    pub fn fit_three_parameter(curve: &PowerCurve) -> Option<CriticalPower> {
+        let points = curve.points(THREE_PARAMETER_RANGE);
+        if points.len() < MIN_FIT_POINTS {
+            return None;
+        }
+
+        let mut best: Option<(f64, f64, f64, f64)> = None;
+        for step in 1..=240 {
+            let k = step as f64 * 0.5;
+            let transformed: Vec<(f64, f64)> = points.iter()
+                .map(|(duration, power)| (1. / (duration + k), *power))
+                .collect();
+
+            let Some((w_prime, cp, error)) = linear_fit(&transformed) else {
+                continue;
+            };
+            let better = match best {
+                Some(x) => error < x.3,
+                None => true
+            };
+            if better {
+                best = Some((cp, w_prime, k, error));
+            }
+        }
+
+        let (cp, w_prime, k, _) = best?;
+        return CriticalPower::valid(cp, w_prime, Some(cp + w_prime / k));
+    }
+
+    pub fn power(&self, duration: f32) -> f32 {
+        let k = match self.p_max {
+            Some(p_max) if p_max > self.cp => self.w_prime / (p_max - self.cp),
+            _ => 0.
+        };
+        return self.cp + self.w_prime / (duration + k).max(1.);
+    }
+
+    fn valid(cp: f64, w_prime: f64, p_max: Option<f64>) -> Option<CriticalPower> {
+        if cp <= 0. || w_prime <= 0. {
+            return None;
+        }
+        return Some(CriticalPower {
+            cp: cp as f32,
+            w_prime: w_prime as f32,
+            p_max: p_max.map(|x| x as f32)
+        });
+    }
+}
+
+impl DateRange {
+    pub const ALL: [DateRange; 4] = [DateRange::SixWeeks, DateRange::ThreeMonths, DateRange::Year, DateRange::AllTime];
+
+    pub fn contains(&self, time: SystemTime, now: SystemTime) -> bool {
+        let days = match self {
+            DateRange::SixWeeks => 42,
+            DateRange::ThreeMonths => 91,
+            DateRange::Year => 365,
+            DateRange::AllTime => return true
+        };
+        let age = now.duration_since(time).unwrap_or_default();
+        return age <= Duration::from_secs(days * 86_400);
+    }
+}
+
+impl fmt::Display for DateRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            DateRange::SixWeeks => "Last 6 weeks",
+            DateRange::ThreeMonths => "Last 3 months",
+            DateRange::Year => "Last year",
+            DateRange::AllTime => "All time"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+// 1 s to max, dense for short efforts where the curve changes quickly
+pub fn durations(max: u32) -> Vec<u32> {
+    let mut result: Vec<u32> = (1..=max.min(DENSE_DURATIONS)).collect();
+    let mut duration = DENSE_DURATIONS as f32;
+    loop {
+        duration = (duration * DURATION_GROWTH).ceil();
+        if duration as u32 >= max {
+            break;
+        }
+        result.push(duration as u32);
+    }
+    if max > DENSE_DURATIONS {
+        result.push(max);
+    }
+    return result;
+}
+
+// least squares y = slope * x + intercept, with the sum of squared errors
+fn linear_fit(points: &[(f64, f64)]) -> Option<(f64, f64, f64)> {
+    let count = points.len() as f64;
+    let mean_x = points.iter().map(|x| x.0).sum::<f64>() / count;
+    let mean_y = points.iter().map(|x| x.1).sum::<f64>() / count;
+
+    let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    if variance == 0. {
+        return None;
+    }
+
+    let slope = covariance / variance;
+    let intercept = mean_y - slope * mean_x;
+    let error = points.iter().map(|(x, y)| (y - slope * x - intercept).powi(2)).sum();
+    return Some((slope, intercept, error));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::power_curve::{durations, CriticalPower, Effort, PowerCurve};
+
+    fn modelled_curve(cp: f32, w_prime: f32, k: f32) -> PowerCurve {
+        return PowerCurve {
+            efforts: durations(3600)
+                .into_iter()
+                .map(|duration| Effort {
+                    duration,
+                    power: cp + w_prime / (duration as f32 + k),
+                    recorded_at: UNIX_EPOCH
+                })
+                .collect()
+        };
+    }
+
+    #[test]
+    fn finds_best_average_for_every_duration() {
+        let mut power = vec![100.; 600];
+        power[100] = 1000.;
+        power[200..500].fill(300.);
+        let curve = PowerCurve::new(&power, UNIX_EPOCH);
+
+        assert_eq!(curve.efforts[0].duration, 1);
+        assert_eq!(curve.efforts[0].power, 1000.);
+        assert_eq!(curve.efforts[29].power, 300.);
+        assert!(curve.best(240).unwrap().power >= 300.);
+        assert_eq!(curve.efforts.last().unwrap().duration, 600);
+        assert_eq!(curve.efforts.last().unwrap().power, 201.5);
+    }
+
+    #[test]
+    fn merge_keeps_the_best_efforts() {
+        let later = UNIX_EPOCH + Duration::from_secs(86_400);
+        let mut curve = PowerCurve::new(&[400., 100., 100.], UNIX_EPOCH);
+        curve.merge(&PowerCurve::new(&vec![200.; 60], later));
+
+        assert_eq!(curve.efforts[0].power, 400.);
+        assert_eq!(curve.efforts[0].recorded_at, UNIX_EPOCH);
+        assert_eq!(curve.efforts[2].power, 200.);
+        assert_eq!(curve.efforts.last().unwrap().duration, 60);
+        assert_eq!(curve.efforts.last().unwrap().recorded_at, later);
+    }
+
+    #[test]
+    fn fits_two_parameter_model() {
+        let model = CriticalPower::fit_two_parameter(&modelled_curve(250., 20_000., 0.)).unwrap();
+
+        assert!((model.cp - 250.).abs() < 0.5, "cp {}", model.cp);
+        assert!((model.w_prime - 20_000.).abs() < 100., "w' {}", model.w_prime);
+        assert_eq!(model.p_max, None);
+    }
+
+    #[test]
+    fn fits_three_parameter_model() {
+        let model = CriticalPower::fit_three_parameter(&modelled_curve(250., 20_000., 20.)).unwrap();
+
+        assert!((model.cp - 250.).abs() < 0.5, "cp {}", model.cp);
+        assert!((model.w_prime - 20_000.).abs() < 100., "w' {}", model.w_prime);
+        assert!((model.p_max.unwrap() - 1250.).abs() < 5.);
+        assert!((model.power(60.) - 500.).abs() < 1.);
+    }
+}
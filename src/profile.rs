@@ -0,0 +1,615 @@
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+use crate::autopause::Trigger;
+use crate::dashboard::{self, COLUMNS, Dashboard};
+use crate::zones::{HeartRateZoneKind, HeartRateZoneModel, PowerZoneKind, PowerZoneModel, Zone, ZoneSettings};
+
+const KILOMETERS_PER_MILE: f32 = 1.609_344;
+const METERS_PER_FOOT: f32 = 0.3048;
+
+#[derive(Error, Debug, Clone)]
+pub enum ProfileError {
+    #[error("Config directory not found")]
+    ConfigDirNotFound,
+    #[error("Failed to read the profile: {0}")]
+    ReadFailed(String),
+    #[error("Failed to write the profile: {0}")]
+    WriteFailed(String),
+    #[error("Invalid value for {0}: {1}")]
+    InvalidValue(String, String),
+    #[error("Skipped line {0} of the profile: {1}")]
+    InvalidLine(usize, String)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Units {
+    Metric,
+    Imperial
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sex {
+    Male,
+    Female
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileField {
+    Name,
+    Weight,
+    BikeWeight,
+    Ftp,
+    CriticalPower,
+    WPrime,
+    MaxHeartRate,
+    RestingHeartRate,
+    Lthr,
+    WheelCircumference,
+    DragArea,
+    RollingResistance,
+    AirDensity,
+    DrivetrainLoss,
+    RouteDifficulty,
+    AutoPauseTrigger,
+    AutoPauseThreshold,
+    AutoPauseStartDelay,
+    AutoPauseStopDelay,
+    Units,
+    DateOfBirth,
+    Sex,
+    HeartRateZones,
+    PowerZones,
+    CustomPowerZones,
+    Dashboard,
+    DashboardColumns
+}
+
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub weight: f32, // kg
+    pub bike_weight: f32, // kg
+    pub ftp: u16,
+    pub critical_power: u16,
+    pub w_prime: u32, // J
+    pub max_heart_rate: u8,
+    pub resting_heart_rate: u8,
+    pub lthr: u8,
+    pub wheel_circumference: u16, // mm
+    pub drag_area: f32, // CdA, m²
+    pub rolling_resistance: f32, // Crr
+    pub air_density: f32, // kg/m³
+    pub drivetrain_loss: f32, // %
+    pub route_difficulty: u8, // % of the route grade sent to the trainer
+    pub auto_pause_trigger: Trigger,
+    pub auto_pause_threshold: f32, // W, rpm or km/h depending on the trigger
+    pub auto_pause_start_delay: u16, // s
+    pub auto_pause_stop_delay: u16, // s
+    pub units: Units,
+    pub date_of_birth: Option<Date>,
+    pub sex: Sex,
+    pub zones: ZoneSettings,
+    pub dashboard: Dashboard
+}
+
+impl Profile {
+    pub fn new() -> Profile {
+        return Profile {
+            name: String::from("Rider"),
+            weight: 75.,
+            bike_weight: 9.,
+            ftp: 200,
+            critical_power: 190,
+            w_prime: 20_000,
+            max_heart_rate: 190,
+            resting_heart_rate: 60,
+            lthr: 170,
+            wheel_circumference: 2105,
+            // road bike on the hoods
+            drag_area: 0.32,
+            rolling_resistance: 0.004,
+            air_density: 1.225,
+            drivetrain_loss: 3.,
+            route_difficulty: 50,
+            auto_pause_trigger: Trigger::Power,
+            auto_pause_threshold: 10.,
+            auto_pause_start_delay: 3,
+            auto_pause_stop_delay: 5,
+            units: Units::Metric,
+            date_of_birth: None,
+            sex: Sex::Male,
+            zones: ZoneSettings::new(),
+            dashboard: Dashboard::new()
+        }
+    }
+
+    pub fn heart_rate_zones(&self) -> Vec<Zone> {
+        let model = match self.zones.heart_rate {
+            HeartRateZoneKind::MaxHeartRate => HeartRateZoneModel::MaxHeartRate {
+                max: self.max_heart_rate
+            },
+            HeartRateZoneKind::LactateThreshold => HeartRateZoneModel::LactateThreshold {
+                lthr: self.lthr
+            },
+            HeartRateZoneKind::HeartRateReserve => HeartRateZoneModel::HeartRateReserve {
+                max: self.max_heart_rate,
+                resting: self.resting_heart_rate
+            }
+        };
+        return model.zones();
+    }
+
+    pub fn power_zones(&self) -> Vec<Zone> {
+        let model = match self.zones.power {
+            PowerZoneKind::Coggan => PowerZoneModel::Coggan { ftp: self.ftp },
+            PowerZoneKind::Custom => PowerZoneModel::Custom {
+                ftp: self.ftp,
+                bounds: self.zones.custom_power_bounds.clone()
+            }
+        };
+        return model.zones();
+    }
+
+    pub fn age(&self) -> Option<u32> {
+        return self.date_of_birth.map(|x| x.years_until(&Date::today()));
+    }
+
+    pub fn get(&self, field: ProfileField) -> String {
+        match field {
+            ProfileField::Name => self.name.clone(),
+            ProfileField::Weight => self.weight.to_string(),
+            ProfileField::BikeWeight => self.bike_weight.to_string(),
+            ProfileField::Ftp => self.ftp.to_string(),
+            ProfileField::CriticalPower => self.critical_power.to_string(),
+            ProfileField::WPrime => self.w_prime.to_string(),
+            ProfileField::MaxHeartRate => self.max_heart_rate.to_string(),
+            ProfileField::RestingHeartRate => self.resting_heart_rate.to_string(),
+            ProfileField::Lthr => self.lthr.to_string(),
+            ProfileField::WheelCircumference => self.wheel_circumference.to_string(),
+            ProfileField::DragArea => self.drag_area.to_string(),
+            ProfileField::RollingResistance => self.rolling_resistance.to_string(),
+            ProfileField::AirDensity => self.air_density.to_string(),
+            ProfileField::DrivetrainLoss => self.drivetrain_loss.to_string(),
+            ProfileField::RouteDifficulty => self.route_difficulty.to_string(),
+            ProfileField::AutoPauseTrigger => self.auto_pause_trigger.key().to_string(),
+            ProfileField::AutoPauseThreshold => self.auto_pause_threshold.to_string(),
+            ProfileField::AutoPauseStartDelay => self.auto_pause_start_delay.to_string(),
+            ProfileField::AutoPauseStopDelay => self.auto_pause_stop_delay.to_string(),
+            ProfileField::Units => self.units.key().to_string(),
+            ProfileField::DateOfBirth => self.date_of_birth.map_or(String::new(), |x| x.to_string()),
+            ProfileField::Sex => self.sex.key().to_string(),
+            ProfileField::HeartRateZones => self.zones.heart_rate.key().to_string(),
+            ProfileField::PowerZones => self.zones.power.key().to_string(),
+            ProfileField::CustomPowerZones => self.zones.custom_power_bounds
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+            ProfileField::Dashboard => self.dashboard.key(),
+            ProfileField::DashboardColumns => self.dashboard.columns.to_string()
+        }
+    }
+
+    pub fn set(&mut self, field: ProfileField, value: &str) -> Result<(), ProfileError> {
+        let value = value.trim();
+        match field {
+            ProfileField::Name => self.name = value.to_string(),
+            ProfileField::Weight => self.weight = parse(field, value)?,
+            ProfileField::BikeWeight => self.bike_weight = parse(field, value)?,
+            ProfileField::Ftp => self.ftp = parse(field, value)?,
+            ProfileField::CriticalPower => self.critical_power = parse(field, value)?,
+            ProfileField::WPrime => self.w_prime = parse(field, value)?,
+            ProfileField::MaxHeartRate => self.max_heart_rate = parse(field, value)?,
+            ProfileField::RestingHeartRate => self.resting_heart_rate = parse(field, value)?,
+            ProfileField::Lthr => self.lthr = parse(field, value)?,
+            ProfileField::WheelCircumference => self.wheel_circumference = parse(field, value)?,
+            ProfileField::DragArea => self.drag_area = parse(field, value)?,
+            ProfileField::RollingResistance => self.rolling_resistance = parse(field, value)?,
+            ProfileField::AirDensity => self.air_density = parse(field, value)?,
+            ProfileField::DrivetrainLoss => self.drivetrain_loss = parse(field, value)?,
+            ProfileField::RouteDifficulty => self.route_difficulty = parse(field, value)?,
+            ProfileField::AutoPauseTrigger => {
+                self.auto_pause_trigger = Trigger::ALL.into_iter()
+                    .find(|x| x.key() == value)
+                    .ok_or_else(|| invalid(field, value))?;
+            }
+            ProfileField::AutoPauseThreshold => self.auto_pause_threshold = parse(field, value)?,
+            ProfileField::AutoPauseStartDelay => self.auto_pause_start_delay = parse(field, value)?,
+            ProfileField::AutoPauseStopDelay => self.auto_pause_stop_delay = parse(field, value)?,
+            ProfileField::Units => {
+                self.units = Units::ALL.into_iter()
+                    .find(|x| x.key() == value)
+                    .ok_or_else(|| invalid(field, value))?;
+            }
+            ProfileField::DateOfBirth => {
+                self.date_of_birth = match value.is_empty() {
+                    true => None,
+                    false => Some(parse(field, value)?)
+                };
+            }
+            ProfileField::Sex => {
+                self.sex = Sex::ALL.into_iter()
+                    .find(|x| x.key() == value)
+                    .ok_or_else(|| invalid(field, value))?;
+            }
+            ProfileField::HeartRateZones => {
+                self.zones.heart_rate = HeartRateZoneKind::ALL.into_iter()
+                    .find(|x| x.key() == value)
+                    .ok_or_else(|| invalid(field, value))?;
+            }
+            ProfileField::PowerZones => {
+                self.zones.power = PowerZoneKind::ALL.into_iter()
+                    .find(|x| x.key() == value)
+                    .ok_or_else(|| invalid(field, value))?;
+            }
+            ProfileField::CustomPowerZones => {
+                self.zones.custom_power_bounds = value.split(',')
+                    .map(|x| parse(field, x.trim()))
+                    .collect::<Result<Vec<f32>, ProfileError>>()?;
+            }
+            ProfileField::Dashboard => {
+                self.dashboard.tiles = dashboard::parse_tiles(value).ok_or_else(|| invalid(field, value))?;
+            }
+            ProfileField::DashboardColumns => {
+                self.dashboard.columns = parse::<usize>(field, value)?.clamp(1, COLUMNS.len());
+            }
+        }
+
+        return Ok(());
+    }
+
+    // lines that fail to parse are reported and skipped, the rest of the profile is kept
+    pub fn load() -> Result<(Profile, Vec<ProfileError>), ProfileError> {
+        let path = profile_path().ok_or(ProfileError::ConfigDirNotFound)?;
+        if !path.exists() {
+            return Ok((Profile::new(), Vec::new()));
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|err| ProfileError::ReadFailed(err.to_string()))?;
+        return Ok(Profile::parse(&content));
+    }
+
+    pub fn parse(content: &str) -> (Profile, Vec<ProfileError>) {
+        let mut profile = Profile::new();
+        let mut errors = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            // unknown keys are ignored so older versions can read newer profiles
+            if let Some(field) = ProfileField::ALL.into_iter().find(|x| x.key() == key.trim()) {
+                if let Err(err) = profile.set(field, value) {
+                    errors.push(ProfileError::InvalidLine(index + 1, err.to_string()));
+                }
+            }
+        }
+
+        return (profile, errors);
+    }
+
+    pub fn save(&self) -> Result<(), ProfileError> {
+        let path = profile_path().ok_or(ProfileError::ConfigDirNotFound)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|err| ProfileError::WriteFailed(err.to_string()))?;
+        }
+
+        return fs::write(&path, self.content())
+            .map_err(|err| ProfileError::WriteFailed(err.to_string()));
+    }
+
+    // one key=value line per field
+    pub fn content(&self) -> String {
+        return ProfileField::ALL.into_iter()
+            .map(|field| format!("{}={}\n", field.key(), self.get(field)))
+            .collect();
+    }
+}
+
+impl ProfileField {
+    pub const ALL: [ProfileField; 27] = [
+        ProfileField::Name,
+        ProfileField::Weight,
+        ProfileField::BikeWeight,
+        ProfileField::Ftp,
+        ProfileField::CriticalPower,
+        ProfileField::WPrime,
+        ProfileField::MaxHeartRate,
+        ProfileField::RestingHeartRate,
+        ProfileField::Lthr,
+        ProfileField::WheelCircumference,
+        ProfileField::DragArea,
+        ProfileField::RollingResistance,
+        ProfileField::AirDensity,
+        ProfileField::DrivetrainLoss,
+        ProfileField::RouteDifficulty,
+        ProfileField::AutoPauseTrigger,
+        ProfileField::AutoPauseThreshold,
+        ProfileField::AutoPauseStartDelay,
+        ProfileField::AutoPauseStopDelay,
+        ProfileField::Units,
+        ProfileField::DateOfBirth,
+        ProfileField::Sex,
+        ProfileField::HeartRateZones,
+        ProfileField::PowerZones,
+        ProfileField::CustomPowerZones,
+        ProfileField::Dashboard,
+        ProfileField::DashboardColumns
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            ProfileField::Name => "name",
+            ProfileField::Weight => "weight",
+            ProfileField::BikeWeight => "bike_weight",
+            ProfileField::Ftp => "ftp",
+            ProfileField::CriticalPower => "critical_power",
+            ProfileField::WPrime => "w_prime",
+            ProfileField::MaxHeartRate => "max_heart_rate",
+            ProfileField::RestingHeartRate => "resting_heart_rate",
+            ProfileField::Lthr => "lthr",
+            ProfileField::WheelCircumference => "wheel_circumference",
+            ProfileField::DragArea => "drag_area",
+            ProfileField::RollingResistance => "rolling_resistance",
+            ProfileField::AirDensity => "air_density",
+            ProfileField::DrivetrainLoss => "drivetrain_loss",
+            ProfileField::RouteDifficulty => "route_difficulty",
+            ProfileField::AutoPauseTrigger => "auto_pause_trigger",
+            ProfileField::AutoPauseThreshold => "auto_pause_threshold",
+            ProfileField::AutoPauseStartDelay => "auto_pause_start_delay",
+            ProfileField::AutoPauseStopDelay => "auto_pause_stop_delay",
+            ProfileField::Units => "units",
+            ProfileField::DateOfBirth => "date_of_birth",
+            ProfileField::Sex => "sex",
+            ProfileField::HeartRateZones => "heart_rate_zones",
+            ProfileField::PowerZones => "power_zones",
+            ProfileField::CustomPowerZones => "custom_power_zones",
+            ProfileField::Dashboard => "dashboard",
+            ProfileField::DashboardColumns => "dashboard_columns"
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProfileField::Name => "Name",
+            ProfileField::Weight => "Weight (kg)",
+            ProfileField::BikeWeight => "Bike weight (kg)",
+            ProfileField::Ftp => "FTP (W)",
+            ProfileField::CriticalPower => "Critical power (W)",
+            ProfileField::WPrime => "W' (J)",
+            ProfileField::MaxHeartRate => "Max HR (bpm)",
+            ProfileField::RestingHeartRate => "Resting HR (bpm)",
+            ProfileField::Lthr => "LTHR (bpm)",
+            ProfileField::WheelCircumference => "Wheel circumference (mm)",
+            ProfileField::DragArea => "CdA (m²)",
+            ProfileField::RollingResistance => "Rolling resistance (Crr)",
+            ProfileField::AirDensity => "Air density (kg/m³)",
+            ProfileField::DrivetrainLoss => "Drivetrain loss (%)",
+            ProfileField::RouteDifficulty => "Route difficulty (%)",
+            ProfileField::AutoPauseTrigger => "Auto pause on",
+            ProfileField::AutoPauseThreshold => "Auto pause below",
+            ProfileField::AutoPauseStartDelay => "Auto start after (s)",
+            ProfileField::AutoPauseStopDelay => "Auto pause after (s)",
+            ProfileField::Units => "Units",
+            ProfileField::DateOfBirth => "Date of birth (YYYY-MM-DD)",
+            ProfileField::Sex => "Sex",
+            ProfileField::HeartRateZones => "Heart rate zones",
+            ProfileField::PowerZones => "Power zones",
+            ProfileField::CustomPowerZones => "Custom power zones (% FTP)",
+            ProfileField::Dashboard => "Dashboard tiles",
+            ProfileField::DashboardColumns => "Dashboard columns"
+        }
+    }
+}
+
+impl Units {
+    pub const ALL: [Units; 2] = [Units::Metric, Units::Imperial];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Units::Metric => "metric",
+            Units::Imperial => "imperial"
+        }
+    }
+
+    pub fn format_speed(&self, speed: f32) -> String {
+        return match self {
+            Units::Metric => format!("{:.1} km/h", speed),
+            Units::Imperial => format!("{:.1} mph", speed / KILOMETERS_PER_MILE)
+        };
+    }
+
+    pub fn format_distance(&self, meters: f32) -> String {
+        return match self {
+            Units::Metric => format!("{:.2} km", meters / 1000.),
+            Units::Imperial => format!("{:.2} mi", meters / 1000. / KILOMETERS_PER_MILE)
+        };
+    }
+
+    pub fn format_elevation(&self, meters: f32) -> String {
+        return match self {
+            Units::Metric => format!("{:.0} m", meters),
+            Units::Imperial => format!("{:.0} ft", meters / METERS_PER_FOOT)
+        };
+    }
+}
+
+impl fmt::Display for Units {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Units::Metric => "Metric",
+            Units::Imperial => "Imperial"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+impl Sex {
+    pub const ALL: [Sex; 2] = [Sex::Male, Sex::Female];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Sex::Male => "male",
+            Sex::Female => "female"
+        }
+    }
+}
+
+impl fmt::Display for Sex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Sex::Male => "Male",
+            Sex::Female => "Female"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+impl Date {
+    pub fn today() -> Date {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        return Date::from_unix_days((seconds / 86400) as i64);
+    }
+
+    // civil date from days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+    pub fn from_unix_days(days: i64) -> Date {
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+
+        return Date { year, month, day };
+    }
+
+    pub fn years_until(&self, other: &Date) -> u32 {
+        let mut years = other.year - self.year;
+        if (other.month, other.day) < (self.month, self.day) {
+            years -= 1;
+        }
+        return years.max(0) as u32;
+    }
+}
+
+impl FromStr for Date {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = value.split('-').collect();
+        if parts.len() != 3 {
+            return Err(());
+        }
+
+        let year = parts[0].parse::<i32>().map_err(|_| ())?;
+        let month = parts[1].parse::<u32>().map_err(|_| ())?;
+        let day = parts[2].parse::<u32>().map_err(|_| ())?;
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return Err(());
+        }
+
+        return Ok(Date { year, month, day });
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day);
+    }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    return dirs::config_dir().map(|dir| dir.join("cyclo"));
+}
+
+fn profile_path() -> Option<PathBuf> {
+    return config_dir().map(|dir| dir.join("profile.conf"));
+}
+
+fn parse<T: FromStr>(field: ProfileField, value: &str) -> Result<T, ProfileError> {
+    return value.parse::<T>().map_err(|_| invalid(field, value));
+}
+
+fn invalid(field: ProfileField, value: &str) -> ProfileError {
+    return ProfileError::InvalidValue(field.label().to_string(), value.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::autopause::Trigger;
+    use crate::dashboard::TileMetric;
+    use crate::profile::{Date, Profile, ProfileError, ProfileField, Sex, Units};
+    use crate::zones::PowerZoneKind;
+
+    #[test]
+    fn profile_round_trips() {
+        let mut profile = Profile::new();
+        profile.name = String::from("Jo");
+        profile.ftp = 275;
+        profile.units = Units::Imperial;
+        profile.date_of_birth = Some(Date { year: 1988, month: 2, day: 29 });
+        profile.sex = Sex::Female;
+        profile.auto_pause_trigger = Trigger::Speed;
+        profile.auto_pause_threshold = 4.5;
+        profile.zones.power = PowerZoneKind::Custom;
+        profile.zones.custom_power_bounds = vec![0.5, 0.7, 0.9];
+        profile.dashboard.tiles.remove(0);
+
+        let (parsed, errors) = Profile::parse(&profile.content());
+
+        assert!(errors.is_empty());
+        for field in ProfileField::ALL {
+            assert_eq!(parsed.get(field), profile.get(field));
+        }
+    }
+
+    #[test]
+    fn skips_invalid_lines() {
+        let content = "ftp=abc\nweight=68.5\ncolor=red\ndashboard=power:huge\nunits=imperial\n";
+        let (profile, errors) = Profile::parse(content);
+
+        assert_eq!(profile.ftp, Profile::new().ftp);
+        assert_eq!(profile.weight, 68.5);
+        assert_eq!(profile.units, Units::Imperial);
+        assert_eq!(profile.dashboard.tiles[0].metric, TileMetric::HeartRate);
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0], ProfileError::InvalidLine(1, _)));
+        assert!(matches!(errors[1], ProfileError::InvalidLine(4, _)));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(Date::from_unix_days(0), Date { year: 1970, month: 1, day: 1 });
+        assert_eq!(Date::from_unix_days(19_782), Date { year: 2024, month: 2, day: 29 });
+        assert_eq!(Date::from_unix_days(-1), Date { year: 1969, month: 12, day: 31 });
+
+        let birthday = Date::from_str("1990-06-15").unwrap();
+        assert_eq!(birthday.to_string(), "1990-06-15");
+        assert_eq!(birthday.years_until(&Date { year: 2024, month: 6, day: 14 }), 33);
+        assert_eq!(birthday.years_until(&Date { year: 2024, month: 6, day: 15 }), 34);
+
+        assert!(Date::from_str("1990-13-01").is_err());
+        assert!(Date::from_str("1990-06").is_err());
+        assert!(Date::from_str("June 15").is_err());
+    }
+}
@@ -0,0 +1,467 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+use crate::ftp_test::{FtpTest, FtpTestResult};
+
+#[derive(Error, Debug, Clone)]
+pub enum RecordingError {
+    #[error("Data directory not found")]
+    DataDirNotFound,
+    #[error("Failed to write the recording: {0}")]
+    WriteFailed(String),
+    #[error("Failed to read the recording: {0}")]
+    ReadFailed(String),
+    #[error("Failed to delete the recording: {0}")]
+    DeleteFailed(String),
+    #[error("Invalid line {0} in the recording: {1}")]
+    InvalidLine(usize, String)
+}
+
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub elapsed: Duration,
+    pub heart_rate: u8,
+    pub power: u16,
+    pub cadence: u8,
+    pub speed: f32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    Start,
+    Pause,
+    Resume,
+    Lap,
+    Finish
+}
+
+// what the rider was doing, set when the ride is started from a workout, test or route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RideKind {
+    FreeRide,
+    Workout,
+    FtpTest,
+    Route
+}
+
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub elapsed: Duration,
+    pub kind: EventKind
+}
+
+#[derive(Debug, Clone)]
+pub struct Lap {
+    pub start: Duration,
+    pub end: Duration
+}
+
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub started_at: SystemTime,
+    pub name: Option<String>,
+    pub kind: RideKind,
+    // serial of the head unit that recorded an imported ride
+    pub device_serial: Option<u32>,
+    pub samples: Vec<Sample>,
+    pub events: Vec<Event>,
+    pub laps: Vec<Lap>,
+    pub lap_start: Duration,
+    // moving time when the ride was paused and for how long, to get back the wall clock
+    pub pauses: Vec<(Duration, Duration)>,
+    // rider values at the time of the ride
+    pub ftp: Option<u16>,
+    pub weight: Option<f32>,
+    pub critical_power: Option<u16>,
+    pub w_prime: Option<u32>,
+    pub ftp_test: Option<FtpTestResult>,
+    // J left after every sample
+    pub w_prime_balance: Vec<(Duration, f32)>
+}
+
+impl Recording {
+    pub fn new() -> Recording {
+        return Recording {
+            started_at: SystemTime::now(),
+            name: None,
+            kind: RideKind::FreeRide,
+            device_serial: None,
+            samples: Vec::new(),
+            events: Vec::new(),
+            laps: Vec::new(),
+            lap_start: Duration::default(),
+            pauses: Vec::new(),
+            ftp: None,
+            weight: None,
+            critical_power: None,
+            w_prime: None,
+            ftp_test: None,
+            w_prime_balance: Vec::new()
+        }
+    }
+
+    pub fn add_sample(&mut self, sample: Sample) {
+        self.samples.push(sample);
+    }
+
+    pub fn add_event(&mut self, elapsed: Duration, kind: EventKind) {
+        self.events.push(Event { elapsed, kind });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.samples.is_empty();
+    }
+
+    // closes the current lap and starts a new one
+    pub fn lap(&mut self, elapsed: Duration) {
+        if elapsed <= self.lap_start {
+            return;
+        }
+
+        self.laps.push(Lap { start: self.lap_start, end: elapsed });
+        self.lap_start = elapsed;
+    }
+
+    pub fn add_pause(&mut self, elapsed: Duration, duration: Duration) {
+        if !duration.is_zero() {
+            self.pauses.push((elapsed, duration));
+        }
+    }
+
+    // wall clock time of a moment in moving time
+    pub fn wall_clock(&self, elapsed: Duration) -> SystemTime {
+        let paused: Duration = self.pauses.iter()
+            .filter(|(at, _)| *at < elapsed)
+            .map(|(_, duration)| *duration)
+            .sum();
+        return self.started_at + elapsed + paused;
+    }
+
+    pub fn current_lap(&self, elapsed: Duration) -> Duration {
+        return elapsed.saturating_sub(self.lap_start);
+    }
+
+    pub fn finish(&mut self, elapsed: Duration) {
+        self.lap(elapsed);
+        self.add_event(elapsed, EventKind::Finish);
+    }
+
+    pub fn title(&self) -> String {
+        return self.name.clone().unwrap_or_else(|| self.kind.to_string());
+    }
+
+    fn started_at_secs(&self) -> u64 {
+        return self.started_at.duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+    }
+
+    // rides are named after their start so saving again overwrites the same file,
+    // imported rides add the device so two head units of the same ride do not collide
+    pub fn path(&self) -> Option<PathBuf> {
+        let name = match self.device_serial {
+            Some(serial) => format!("ride-{}-{}.csv", self.started_at_secs(), serial),
+            None => format!("ride-{}.csv", self.started_at_secs())
+        };
+        return rides_dir().map(|dir| dir.join(name));
+    }
+
+    pub fn save(&self) -> Result<PathBuf, RecordingError> {
+        let path = self.path().ok_or(RecordingError::DataDirNotFound)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|err| RecordingError::WriteFailed(err.to_string()))?;
+        }
+
+        fs::write(&path, self.to_csv(self.started_at_secs()))
+            .map_err(|err| RecordingError::WriteFailed(err.to_string()))?;
+
+        return Ok(path);
+    }
+
+    pub fn delete(&self) -> Result<(), RecordingError> {
+        let path = self.path().ok_or(RecordingError::DataDirNotFound)?;
+        return fs::remove_file(path)
+            .map_err(|err| RecordingError::DeleteFailed(err.to_string()));
+    }
+
+    pub fn load(path: &Path) -> Result<Recording, RecordingError> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| RecordingError::ReadFailed(err.to_string()))?;
+        return Recording::parse(&content);
+    }
+
+    fn parse(content: &str) -> Result<Recording, RecordingError> {
+        let mut recording = Recording::new();
+        for (index, line) in content.lines().enumerate() {
+            let columns: Vec<&str> = line.trim().split(',').collect();
+            let invalid = || RecordingError::InvalidLine(index + 1, line.to_string());
+            let millis = |index: usize| -> Result<Duration, RecordingError> {
+                let value = columns.get(index).and_then(|x| x.parse::<u64>().ok()).ok_or_else(invalid)?;
+                return Ok(Duration::from_millis(value));
+            };
+            let value = |index: usize| columns.get(index).copied().ok_or_else(invalid);
+
+            match columns[0] {
+                "started_at" => {
+                    let seconds = value(1)?.parse::<u64>().map_err(|_| invalid())?;
+                    recording.started_at = UNIX_EPOCH + Duration::from_secs(seconds);
+                }
+                // the name may contain commas
+                "name" => recording.name = line.trim().split_once(',').map(|(_, name)| name.to_string()),
+                "kind" => recording.kind = RideKind::parse(value(1)?).ok_or_else(invalid)?,
+                "serial" => recording.device_serial = Some(value(1)?.parse().map_err(|_| invalid())?),
+                "ftp" => recording.ftp = Some(value(1)?.parse().map_err(|_| invalid())?),
+                "weight" => recording.weight = Some(value(1)?.parse().map_err(|_| invalid())?),
+                "cp" => recording.critical_power = Some(value(1)?.parse().map_err(|_| invalid())?),
+                "w_prime" => recording.w_prime = Some(value(1)?.parse().map_err(|_| invalid())?),
+                "ftp_test" => {
+                    let test = FtpTest::from_key(value(1)?).ok_or_else(invalid)?;
+                    let ftp = value(2)?.parse().map_err(|_| invalid())?;
+                    recording.ftp_test = Some(FtpTestResult { test, ftp });
+                }
+                "event" => {
+                    let kind = EventKind::parse(value(2)?).ok_or_else(invalid)?;
+                    recording.add_event(millis(1)?, kind);
+                }
+                "lap" => recording.laps.push(Lap { start: millis(1)?, end: millis(2)? }),
+                "pause" => recording.add_pause(millis(1)?, millis(2)?),
+                "sample" => recording.add_sample(Sample {
+                    elapsed: millis(1)?,
+                    heart_rate: value(2)?.parse().map_err(|_| invalid())?,
+                    power: value(3)?.parse().map_err(|_| invalid())?,
+                    cadence: value(4)?.parse().map_err(|_| invalid())?,
+                    speed: value(5)?.parse().map_err(|_| invalid())?
+                }),
+                "wbal" => {
+                    let balance = value(2)?.parse().map_err(|_| invalid())?;
+                    recording.w_prime_balance.push((millis(1)?, balance));
+                }
+                // rows written by newer versions are skipped
+                _ => {}
+            }
+        }
+
+        recording.lap_start = recording.laps.last().map_or(Duration::default(), |x| x.end);
+        return Ok(recording);
+    }
+
+    // every row is tagged with its type so events, laps and samples
+    // can live in the same file
+    fn to_csv(&self, started_at: u64) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        lines.push(format!("started_at,{}", started_at));
+        lines.push(format!("kind,{}", self.kind.key()));
+        if let Some(name) = &self.name {
+            lines.push(format!("name,{}", name.replace(['\n', '\r'], " ")));
+        }
+        if let Some(serial) = self.device_serial {
+            lines.push(format!("serial,{}", serial));
+        }
+        if let Some(ftp) = self.ftp {
+            lines.push(format!("ftp,{}", ftp));
+        }
+        if let Some(weight) = self.weight {
+            lines.push(format!("weight,{}", weight));
+        }
+        if let Some(critical_power) = self.critical_power {
+            lines.push(format!("cp,{}", critical_power));
+        }
+        if let Some(w_prime) = self.w_prime {
+            lines.push(format!("w_prime,{}", w_prime));
+        }
+        if let Some(result) = &self.ftp_test {
+            lines.push(format!("ftp_test,{},{}", result.test.key(), result.ftp));
+        }
+
+        for event in &self.events {
+            lines.push(format!("event,{},{}", event.elapsed.as_millis(), event.kind.as_str()));
+        }
+
+        for lap in &self.laps {
+            lines.push(format!("lap,{},{}", lap.start.as_millis(), lap.end.as_millis()));
+        }
+
+        for (elapsed, duration) in &self.pauses {
+            lines.push(format!("pause,{},{}", elapsed.as_millis(), duration.as_millis()));
+        }
+
+        for sample in &self.samples {
+            lines.push(format!(
+                "sample,{},{},{},{},{:.2}",
+                sample.elapsed.as_millis(),
+                sample.heart_rate,
+                sample.power,
+                sample.cadence,
+                sample.speed
+            ));
+        }
+
+        for (elapsed, balance) in &self.w_prime_balance {
+            lines.push(format!("wbal,{},{:.0}", elapsed.as_millis(), balance));
+        }
+
+        return lines.join("\n") + "\n";
+    }
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Start => "start",
+            EventKind::Pause => "pause",
+            EventKind::Resume => "resume",
+            EventKind::Lap => "lap",
+            EventKind::Finish => "finish"
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<EventKind> {
+        match value {
+            "start" => Some(EventKind::Start),
+            "pause" => Some(EventKind::Pause),
+            "resume" => Some(EventKind::Resume),
+            "lap" => Some(EventKind::Lap),
+            "finish" => Some(EventKind::Finish),
+            _ => None
+        }
+    }
+}
+
+impl RideKind {
+    pub const ALL: [RideKind; 4] = [RideKind::FreeRide, RideKind::Workout, RideKind::FtpTest, RideKind::Route];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            RideKind::FreeRide => "free_ride",
+            RideKind::Workout => "workout",
+            RideKind::FtpTest => "ftp_test",
+            RideKind::Route => "route"
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<RideKind> {
+        return RideKind::ALL.into_iter().find(|x| x.key() == value);
+    }
+}
+
+impl fmt::Display for RideKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            RideKind::FreeRide => "Free ride",
+            RideKind::Workout => "Workout",
+            RideKind::FtpTest => "FTP test",
+            RideKind::Route => "Route"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+pub fn rides_dir() -> Option<PathBuf> {
+    return dirs::data_dir().map(|dir| dir.join("cyclo").join("rides"));
+}
+
+// every saved ride, oldest first, with the files that could not be read
+pub fn load_rides() -> (Vec<Recording>, Vec<String>) {
+    let mut rides = Vec::new();
+    let mut errors = Vec::new();
+
+    let Some(dir) = rides_dir() else {
+        return (rides, errors);
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return (rides, errors);
+    };
+
+    for path in entries.filter_map(|x| x.ok()).map(|x| x.path()) {
+        if path.extension().and_then(|x| x.to_str()) != Some("csv") {
+            continue;
+        }
+        match Recording::load(&path) {
+            Ok(ride) => rides.push(ride),
+            Err(err) => errors.push(format!("{:?}: {}", path, err))
+        }
+    }
+
+    rides.sort_by_key(|x| x.started_at);
+    return (rides, errors);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::ftp_test::{FtpTest, FtpTestResult};
+    use crate::recording::{EventKind, Recording, RecordingError, RideKind, Sample};
+
+    #[test]
+    fn csv_round_trips() {
+        let mut recording = Recording::new();
+        recording.started_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        recording.ftp = Some(250);
+        recording.name = Some(String::from("Ramp, then spin"));
+        recording.kind = RideKind::FtpTest;
+        recording.device_serial = Some(3_912_345_678);
+        recording.ftp_test = Some(FtpTestResult { test: FtpTest::Ramp, ftp: 262 });
+        recording.add_event(Duration::default(), EventKind::Start);
+        recording.add_sample(Sample {
+            elapsed: Duration::from_secs(1),
+            heart_rate: 120,
+            power: 200,
+            cadence: 90,
+            speed: 30.5
+        });
+        recording.w_prime_balance.push((Duration::from_secs(1), 19_500.));
+        recording.add_pause(Duration::from_secs(1), Duration::from_secs(90));
+        recording.finish(Duration::from_secs(1));
+
+        let parsed = Recording::parse(&recording.to_csv(1_700_000_000)).unwrap();
+
+        assert_eq!(parsed.started_at, recording.started_at);
+        assert_eq!(parsed.name, recording.name);
+        assert_eq!(parsed.kind, RideKind::FtpTest);
+        assert_eq!(parsed.device_serial, Some(3_912_345_678));
+        assert_eq!(parsed.ftp, Some(250));
+        assert_eq!(parsed.ftp_test, recording.ftp_test);
+        assert_eq!(parsed.events.len(), 2);
+        assert_eq!(parsed.laps.len(), 1);
+        assert_eq!(parsed.samples[0].power, 200);
+        assert_eq!(parsed.samples[0].speed, 30.5);
+        assert_eq!(parsed.w_prime_balance, recording.w_prime_balance);
+        assert_eq!(parsed.pauses, recording.pauses);
+        assert_eq!(parsed.wall_clock(Duration::from_secs(1)), UNIX_EPOCH + Duration::from_secs(1_700_000_001));
+        assert_eq!(parsed.wall_clock(Duration::from_secs(2)), UNIX_EPOCH + Duration::from_secs(1_700_000_092));
+    }
+
+    #[test]
+    fn reports_invalid_lines() {
+        let result = Recording::parse("started_at,1700000000\nsample,1000,abc,200,90,30\n");
+
+        assert!(matches!(result, Err(RecordingError::InvalidLine(2, _))));
+    }
+
+    #[test]
+    fn laps_close_at_the_given_time() {
+        let mut recording = Recording::new();
+        recording.lap(Duration::default());
+        assert!(recording.laps.is_empty());
+
+        recording.lap(Duration::from_secs(300));
+        recording.lap(Duration::from_secs(300));
+        assert_eq!(recording.laps.len(), 1);
+        assert_eq!(recording.current_lap(Duration::from_secs(420)), Duration::from_secs(120));
+
+        recording.finish(Duration::from_secs(420));
+        assert_eq!(recording.laps[1].start, Duration::from_secs(300));
+        assert_eq!(recording.laps[1].end, Duration::from_secs(420));
+        assert_eq!(recording.events.last().map(|x| x.kind), Some(EventKind::Finish));
+
+        // a reloaded ride continues from the end of its last lap
+        let parsed = Recording::parse(&recording.to_csv(0)).unwrap();
+        assert_eq!(parsed.current_lap(Duration::from_secs(480)), Duration::from_secs(60));
+    }
+}
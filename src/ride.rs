@@ -0,0 +1,165 @@
+use std::time::Duration;
+
+use iced::widget::{column, pick_list, row, text};
+use iced::{Color, Element};
+
+use crate::chart::{Band, Chart, ChartWindow, Metric, Smoothing, zone_bands, zone_color};
+use crate::recording::{Recording, Sample};
+use crate::stopwatch::format_duration;
+use crate::wbal::{WPrimeBalance, WARNING_FRACTION};
+use crate::zones::{Zone, lap_samples, time_in_zones};
+
+#[derive(Debug, Clone)]
+pub enum RideMessage {
+    ToggleDashboardEditor,
+    ToggleDetails,
+    CancelDiscard,
+    ChartWindowSelected(ChartWindow),
+    ChartSmoothingSelected(Smoothing)
+}
+
+// how the ride screen is laid out, the ride itself belongs to the app
+#[derive(Debug, Clone)]
+pub struct RideScreen {
+    // tiles are being chosen and arranged
+    pub editing_dashboard: bool,
+    // discard was asked for and waits for the rider to confirm
+    pub confirm_discard: bool,
+    // charts, zones and laps below the controls, hidden by default
+    pub show_details: bool,
+    pub chart_window: ChartWindow,
+    pub chart_smoothing: Smoothing
+}
+
+// live values the details are drawn from
+pub struct Details<'a> {
+    pub recording: &'a Recording,
+    // samples to chart, the heart rate history before the ride has started
+    pub samples: &'a [Sample],
+    pub heart_rate_zones: &'a [Zone],
+    pub power_zones: &'a [Zone],
+    pub target_power: Option<f32>,
+    pub target_cadence: Option<f32>,
+    pub w_prime_balance: &'a WPrimeBalance
+}
+
+impl RideScreen {
+    pub fn new() -> RideScreen {
+        return RideScreen {
+            editing_dashboard: false,
+            confirm_discard: false,
+            show_details: false,
+            chart_window: ChartWindow::FiveMinutes,
+            chart_smoothing: Smoothing::ThreeSeconds
+        };
+    }
+
+    pub fn update(&mut self, message: RideMessage) {
+        match message {
+            RideMessage::ToggleDashboardEditor => {
+                self.editing_dashboard = !self.editing_dashboard;
+            }
+            RideMessage::ToggleDetails => {
+                self.show_details = !self.show_details;
+            }
+            RideMessage::CancelDiscard => {
+                self.confirm_discard = false;
+            }
+            RideMessage::ChartWindowSelected(value) => {
+                self.chart_window = value;
+            }
+            RideMessage::ChartSmoothingSelected(value) => {
+                self.chart_smoothing = value;
+            }
+        }
+    }
+
+    pub fn details<'a>(&self, details: Details) -> Element<'a, RideMessage> {
+        let recording = details.recording;
+
+        let chart_options = row![
+            pick_list(&ChartWindow::ALL[..], Some(self.chart_window), RideMessage::ChartWindowSelected),
+            pick_list(&Smoothing::ALL[..], Some(self.chart_smoothing), RideMessage::ChartSmoothingSelected)
+        ]
+        .spacing(10);
+
+        let charts = column![
+            chart_options,
+            Chart::new(Metric::HeartRate, self.chart_window, self.chart_smoothing, details.samples)
+                .with_bands(zone_bands(details.heart_rate_zones))
+                .view(),
+            Chart::new(Metric::Power, self.chart_window, self.chart_smoothing, details.samples)
+                .with_bands(zone_bands(details.power_zones))
+                .with_target(details.target_power)
+                .view(),
+            Chart::new(Metric::Cadence, self.chart_window, self.chart_smoothing, details.samples)
+                .with_target(details.target_cadence)
+                .view(),
+            Chart::from_points(
+                Color::from_rgb8(0x8e, 0x24, 0xaa),
+                self.chart_window,
+                Smoothing::Off,
+                recording.w_prime_balance
+                    .iter()
+                    .map(|(elapsed, balance)| (elapsed.as_secs_f32(), *balance))
+                    .collect()
+            )
+            .with_bands(vec![Band {
+                from: 0.,
+                to: details.w_prime_balance.w_prime * WARNING_FRACTION,
+                color: Color::from_rgba8(0xe5, 0x39, 0x35, 0.15)
+            }])
+            .view()
+        ]
+        .spacing(10);
+
+        let ride_zones = column![
+            row![
+                text("HR"),
+                time_in_zones_row(&time_in_zones(&recording.samples, details.heart_rate_zones, |x| x.heart_rate as f32))
+            ]
+            .spacing(10),
+            row![
+                text("Power"),
+                time_in_zones_row(&time_in_zones(&recording.samples, details.power_zones, |x| x.power as f32))
+            ]
+            .spacing(10)
+        ]
+        .spacing(5);
+
+        let laps = column(
+            recording.laps
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(index, lap)| {
+                    let samples = lap_samples(&recording.samples, lap);
+                    let power = time_in_zones(samples, details.power_zones, |x| x.power as f32);
+                    row![
+                        text(format!("Lap {} {}", index + 1, format_duration(lap.end - lap.start))),
+                        time_in_zones_row(&power)
+                    ]
+                    .spacing(10)
+                    .into()
+                }).collect()
+        );
+
+        return column![charts, ride_zones, laps]
+            .spacing(10)
+            .into();
+    }
+}
+
+fn time_in_zones_row<'a>(durations: &[Duration]) -> Element<'a, RideMessage> {
+    return row(
+        durations.iter()
+            .enumerate()
+            .map(|(index, duration)| {
+                text(format!("Z{} {}", index + 1, format_duration(*duration)))
+                    .style(zone_color(index))
+                    .into()
+            }).collect()
+    )
+    .spacing(10)
+    .into();
+}
@@ -0,0 +1,296 @@
+// courses from GPX and TCX files, distances along the route are in meters
+// and grades are fractions (0.05 is 5%)
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use roxmltree::{Document, Node};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum RouteError {
+    #[error("Failed to read the route file: {0}")]
+    ReadFailed(String),
+    #[error("Invalid XML: {0}")]
+    InvalidXml(String),
+    #[error("Unsupported route file {0}")]
+    UnsupportedFile(String),
+    #[error("Invalid track point: {0}")]
+    InvalidPoint(String),
+    #[error("The route needs at least two distinct points")]
+    NotEnoughPoints
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+    // m
+    pub elevation: f32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoutePoint {
+    pub distance: f32,
+    pub elevation: f32
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub name: String,
+    pub points: Vec<RoutePoint>
+}
+
+// where the rider is on the route
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteProgress {
+    pub route: Route,
+    pub distance: f32
+}
+
+const EARTH_RADIUS: f64 = 6_371_000.;
+
+impl Route {
+    pub fn from_track(name: String, track: &[TrackPoint]) -> Result<Route, RouteError> {
+        let mut points: Vec<RoutePoint> = Vec::new();
+        let mut distance = 0.;
+        for (index, point) in track.iter().enumerate() {
+            if index > 0 {
+                let step = haversine(&track[index - 1], point);
+                // repeated points would make a vertical step
+                if step <= 0. {
+                    continue;
+                }
+                distance += step;
+            }
+            points.push(RoutePoint { distance, elevation: point.elevation });
+        }
+
+        if points.len() < 2 {
+            return Err(RouteError::NotEnoughPoints);
+        }
+        return Ok(Route { name, points });
+    }
+
+    pub fn length(&self) -> f32 {
+        return self.points.last().map_or(0., |x| x.distance);
+    }
+
+    // index of the segment containing the distance
+    fn segment(&self, distance: f32) -> usize {
+        let index = self.points.partition_point(|x| x.distance <= distance);
+        return index.clamp(1, self.points.len() - 1) - 1;
+    }
+
+    pub fn elevation_at(&self, distance: f32) -> f32 {
+        let index = self.segment(distance);
+        let (from, to) = (self.points[index], self.points[index + 1]);
+        let fraction = ((distance - from.distance) / (to.distance - from.distance)).clamp(0., 1.);
+        return from.elevation + (to.elevation - from.elevation) * fraction;
+    }
+
+    pub fn grade_at(&self, distance: f32) -> f32 {
+        let index = self.segment(distance);
+        let (from, to) = (self.points[index], self.points[index + 1]);
+        return (to.elevation - from.elevation) / (to.distance - from.distance);
+    }
+}
+
+impl RouteProgress {
+    pub fn new(route: Route) -> RouteProgress {
+        return RouteProgress { route, distance: 0. };
+    }
+
+    // speed in km/h
+    pub fn advance(&mut self, speed: f32, duration: Duration) {
+        self.distance = (self.distance + speed / 3.6 * duration.as_secs_f32()).min(self.route.length());
+    }
+
+    pub fn grade(&self) -> f32 {
+        return self.route.grade_at(self.distance);
+    }
+
+    pub fn elevation(&self) -> f32 {
+        return self.route.elevation_at(self.distance);
+    }
+
+    pub fn remaining(&self) -> f32 {
+        return self.route.length() - self.distance;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        return self.remaining() <= 0.;
+    }
+}
+
+// grade sent to the trainer, difficulty in % flattens the route like a lower trainer difficulty
+pub fn trainer_grade(grade: f32, difficulty: u8) -> f32 {
+    return grade * difficulty.min(100) as f32 / 100.;
+}
+
+pub fn load(path: &Path) -> Result<Route, RouteError> {
+    let extension = path.extension()
+        .and_then(|x| x.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let content = fs::read_to_string(path)
+        .map_err(|err| RouteError::ReadFailed(err.to_string()))?;
+    let fallback = path.file_stem()
+        .and_then(|x| x.to_str())
+        .unwrap_or("Route")
+        .to_string();
+
+    return match extension.as_str() {
+        "gpx" => parse_gpx(&content, fallback),
+        "tcx" => parse_tcx(&content, fallback),
+        _ => Err(RouteError::UnsupportedFile(format!("{:?}", path)))
+    };
+}
+
+// track points, falling back to route points for planned routes
+pub fn parse_gpx(content: &str, fallback: String) -> Result<Route, RouteError> {
+    let document = Document::parse(content)
+        .map_err(|err| RouteError::InvalidXml(err.to_string()))?;
+
+    let mut points: Vec<Node> = document.descendants().filter(|x| x.has_tag_name("trkpt")).collect();
+    if points.is_empty() {
+        points = document.descendants().filter(|x| x.has_tag_name("rtept")).collect();
+    }
+
+    let mut track = Vec::new();
+    for point in points {
+        let previous = track.last().map_or(0., |x: &TrackPoint| x.elevation);
+        track.push(TrackPoint {
+            latitude: coordinate(point.attribute("lat"))?,
+            longitude: coordinate(point.attribute("lon"))?,
+            // courses without elevation are ridden flat
+            elevation: child_text(point, "ele").and_then(|x| x.parse().ok()).unwrap_or(previous)
+        });
+    }
+
+    let name = document.descendants()
+        .find(|x| x.has_tag_name("name"))
+        .and_then(|x| x.text())
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+        .unwrap_or(fallback);
+    return Route::from_track(name, &track);
+}
+
+// course or activity track points, points without a position only carry sensor data
+pub fn parse_tcx(content: &str, fallback: String) -> Result<Route, RouteError> {
+    let document = Document::parse(content)
+        .map_err(|err| RouteError::InvalidXml(err.to_string()))?;
+
+    let mut track = Vec::new();
+    for point in document.descendants().filter(|x| x.has_tag_name("Trackpoint")) {
+        let Some(position) = point.children().find(|x| x.has_tag_name("Position")) else {
+            continue;
+        };
+
+        let previous = track.last().map_or(0., |x: &TrackPoint| x.elevation);
+        track.push(TrackPoint {
+            latitude: coordinate(child_text(position, "LatitudeDegrees").as_deref())?,
+            longitude: coordinate(child_text(position, "LongitudeDegrees").as_deref())?,
+            elevation: child_text(point, "AltitudeMeters").and_then(|x| x.parse().ok()).unwrap_or(previous)
+        });
+    }
+
+    let name = document.descendants()
+        .find(|x| x.has_tag_name("Course"))
+        .and_then(|x| child_text(x, "Name"))
+        .unwrap_or(fallback);
+    return Route::from_track(name, &track);
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    return node.children()
+        .find(|x| x.tag_name().name() == name)
+        .and_then(|x| x.text())
+        .map(|x| x.trim().to_string());
+}
+
+fn coordinate(value: Option<&str>) -> Result<f64, RouteError> {
+    let value = value.ok_or(RouteError::InvalidPoint(String::from("missing coordinate")))?;
+    return value.trim().parse().map_err(|_| RouteError::InvalidPoint(value.to_string()));
+}
+
+// great circle distance in meters
+fn haversine(from: &TrackPoint, to: &TrackPoint) -> f32 {
+    let (from_latitude, to_latitude) = (from.latitude.to_radians(), to.latitude.to_radians());
+    let latitude = to_latitude - from_latitude;
+    let longitude = (to.longitude - from.longitude).to_radians();
+
+    let a = (latitude / 2.).sin().powi(2) + from_latitude.cos() * to_latitude.cos() * (longitude / 2.).sin().powi(2);
+    return (2. * EARTH_RADIUS * a.sqrt().asin()) as f32;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::route::{parse_gpx, parse_tcx, trainer_grade, RouteError, RouteProgress};
+
+    // one degree of latitude is 111.2 km, 0.001° is 111.2 m
+    const GPX: &str = r#"<?xml version="1.0"?>
+        <gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1">
+            <trk>
+                <name>Hill repeat</name>
+                <trkseg>
+                    <trkpt lat="45.000" lon="7.0"><ele>100</ele></trkpt>
+                    <trkpt lat="45.001" lon="7.0"><ele>105.56</ele></trkpt>
+                    <trkpt lat="45.001" lon="7.0"><ele>105.56</ele></trkpt>
+                    <trkpt lat="45.002" lon="7.0"><ele>105.56</ele></trkpt>
+                </trkseg>
+            </trk>
+        </gpx>"#;
+
+    #[test]
+    fn parses_gpx_grades() {
+        let route = parse_gpx(GPX, String::from("fallback")).unwrap();
+
+        assert_eq!(route.name, "Hill repeat");
+        // the repeated point is dropped
+        assert_eq!(route.points.len(), 3);
+        assert!((route.length() - 222.4).abs() < 0.5, "{}", route.length());
+        assert!((route.grade_at(50.) - 0.05).abs() < 0.001);
+        assert_eq!(route.grade_at(150.), 0.);
+        assert!((route.elevation_at(55.6) - 102.78).abs() < 0.05);
+    }
+
+    #[test]
+    fn parses_tcx_courses() {
+        let tcx = r#"<TrainingCenterDatabase xmlns="http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2">
+            <Courses><Course><Name>Loop</Name><Track>
+                <Trackpoint><Position><LatitudeDegrees>45.0</LatitudeDegrees><LongitudeDegrees>7.0</LongitudeDegrees></Position><AltitudeMeters>200</AltitudeMeters></Trackpoint>
+                <Trackpoint><HeartRateBpm><Value>120</Value></HeartRateBpm></Trackpoint>
+                <Trackpoint><Position><LatitudeDegrees>45.001</LatitudeDegrees><LongitudeDegrees>7.0</LongitudeDegrees></Position><AltitudeMeters>194.44</AltitudeMeters></Trackpoint>
+            </Track></Course></Courses>
+        </TrainingCenterDatabase>"#;
+
+        let route = parse_tcx(tcx, String::from("fallback")).unwrap();
+
+        assert_eq!(route.name, "Loop");
+        assert_eq!(route.points.len(), 2);
+        assert!((route.grade_at(0.) + 0.05).abs() < 0.001);
+        assert_eq!(parse_tcx("<TrainingCenterDatabase/>", String::new()), Err(RouteError::NotEnoughPoints));
+    }
+
+    #[test]
+    fn progress_follows_speed() {
+        let mut progress = RouteProgress::new(parse_gpx(GPX, String::new()).unwrap());
+
+        // 36 km/h is 10 m/s
+        progress.advance(36., Duration::from_secs(10));
+        assert_eq!(progress.distance, 100.);
+        assert!((progress.grade() - 0.05).abs() < 0.001);
+        assert!((progress.elevation() - 105.).abs() < 0.01);
+        assert!(!progress.is_finished());
+
+        progress.advance(36., Duration::from_secs(60));
+        assert!(progress.is_finished());
+        assert_eq!(trainer_grade(0.08, 50), 0.04);
+    }
+}
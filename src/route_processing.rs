@@ -0,0 +1,236 @@
+// turns noisy GPS elevation into a profile a trainer can follow smoothly:
+// resample to a fixed interval, median filter out spikes, low-pass the rest,
+// then clamp and rate-limit the grade
+
+use crate::route::{Route, RoutePoint};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessingConfig {
+    // m between resampled points
+    pub interval: f32,
+    // points, odd
+    pub median_window: usize,
+    // m, distance constant of the low-pass filter
+    pub smoothing_distance: f32,
+    // fraction
+    pub max_grade: f32,
+    // fraction per m
+    pub max_grade_change: f32
+}
+
+impl ProcessingConfig {
+    pub fn new() -> ProcessingConfig {
+        return ProcessingConfig {
+            interval: 10.,
+            median_window: 5,
+            smoothing_distance: 80.,
+            max_grade: 0.2,
+            // 1% every 10 m
+            max_grade_change: 0.001
+        };
+    }
+}
+
+pub fn process(route: &Route, config: &ProcessingConfig) -> Route {
+    let resampled = resample(&route.points, config.interval);
+    if resampled.len() < 2 {
+        return route.clone();
+    }
+
+    let elevations: Vec<f32> = resampled.iter().map(|x| x.elevation).collect();
+    let smoothed = low_pass(&median_filter(&elevations, config.median_window), config.interval, config.smoothing_distance);
+    let grades: Vec<f32> = resampled.windows(2)
+        .zip(smoothed.windows(2))
+        .map(|(points, elevation)| (elevation[1] - elevation[0]) / (points[1].distance - points[0].distance))
+        .collect();
+    let grades = limit_grades(&grades, &resampled, config.max_grade, config.max_grade_change);
+
+    // elevations are rebuilt from the limited grades so the profile matches what the trainer simulates
+    let mut elevation = smoothed[0];
+    let mut points = vec![RoutePoint { distance: resampled[0].distance, elevation }];
+    for (pair, grade) in resampled.windows(2).zip(grades) {
+        elevation += grade * (pair[1].distance - pair[0].distance);
+        points.push(RoutePoint { distance: pair[1].distance, elevation });
+    }
+
+    return Route { name: route.name.clone(), points };
+}
+
+// linear interpolation every interval meters, the last point is kept and
+// absorbs the remainder so there are no tiny segments with wild grades
+pub fn resample(points: &[RoutePoint], interval: f32) -> Vec<RoutePoint> {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return Vec::new();
+    };
+    if interval <= 0. || points.len() < 2 {
+        return points.to_vec();
+    }
+
+    let mut result = Vec::new();
+    let mut index = 0;
+    let mut step = 0;
+    loop {
+        let distance = first.distance + step as f32 * interval;
+        if step > 0 && distance > last.distance - interval / 2. {
+            break;
+        }
+
+        while index + 2 < points.len() && points[index + 1].distance < distance {
+            index += 1;
+        }
+        let (from, to) = (points[index], points[index + 1]);
+        let fraction = ((distance - from.distance) / (to.distance - from.distance).max(f32::EPSILON)).clamp(0., 1.);
+        result.push(RoutePoint {
+            distance,
+            elevation: from.elevation + (to.elevation - from.elevation) * fraction
+        });
+        step += 1;
+    }
+
+    result.push(*last);
+    return result;
+}
+
+// removes single point spikes, the window shrinks at the ends
+pub fn median_filter(values: &[f32], window: usize) -> Vec<f32> {
+    let half = window / 2;
+    return (0..values.len())
+        .map(|index| {
+            let mut neighbours = values[index.saturating_sub(half)..(index + half + 1).min(values.len())].to_vec();
+            neighbours.sort_by(|a, b| a.total_cmp(b));
+            neighbours[neighbours.len() / 2]
+        })
+        .collect();
+}
+
+// exponential smoothing run forwards then backwards so climbs do not shift along the route
+pub fn low_pass(values: &[f32], interval: f32, distance: f32) -> Vec<f32> {
+    if values.is_empty() || distance <= 0. {
+        return values.to_vec();
+    }
+
+    let alpha = interval / (distance + interval);
+    let mut result = values.to_vec();
+    for index in 1..result.len() {
+        result[index] = result[index - 1] + alpha * (result[index] - result[index - 1]);
+    }
+    for index in (0..result.len() - 1).rev() {
+        result[index] = result[index + 1] + alpha * (result[index] - result[index + 1]);
+    }
+    return result;
+}
+
+// one grade per segment between the points
+pub fn limit_grades(grades: &[f32], points: &[RoutePoint], max_grade: f32, max_change: f32) -> Vec<f32> {
+    let mut result: Vec<f32> = Vec::with_capacity(grades.len());
+    for (index, grade) in grades.iter().enumerate() {
+        let grade = grade.clamp(-max_grade, max_grade);
+        let limited = match result.last() {
+            Some(previous) => {
+                let length = points[index + 1].distance - points[index].distance;
+                let change = max_change * length;
+                grade.clamp(previous - change, previous + change)
+            }
+            None => grade
+        };
+        result.push(limited);
+    }
+    return result;
+}
+
+// m climbed
+pub fn total_ascent(points: &[RoutePoint]) -> f32 {
+    return points.windows(2)
+        .map(|pair| (pair[1].elevation - pair[0].elevation).max(0.))
+        .sum();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::route::{Route, RoutePoint};
+    use crate::route_processing::{median_filter, process, resample, total_ascent, ProcessingConfig};
+
+    // deterministic noise in -1..1
+    fn noise(seed: &mut u32) -> f32 {
+        *seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        return ((*seed >> 16) & 0x7fff) as f32 / 16_383.5 - 1.;
+    }
+
+    // GPS points every 3 to 7 m along a climb with ±3 m elevation noise and the odd spike
+    fn noisy_climb(grade: f32, length: f32) -> Route {
+        let mut seed = 42;
+        let mut points = Vec::new();
+        let mut distance = 0.;
+        let mut index = 0;
+        while distance < length {
+            let spike = if index % 97 == 50 { 25. } else { 0. };
+            points.push(RoutePoint {
+                distance,
+                elevation: 100. + distance * grade + 3. * noise(&mut seed) + spike
+            });
+            distance += 5. + 2. * noise(&mut seed);
+            index += 1;
+        }
+        points.push(RoutePoint { distance: length, elevation: 100. + length * grade });
+        return Route { name: String::from("Noisy climb"), points };
+    }
+
+    fn grades(route: &Route) -> Vec<f32> {
+        return route.points.windows(2)
+            .map(|x| (x[1].elevation - x[0].elevation) / (x[1].distance - x[0].distance))
+            .collect();
+    }
+
+    #[test]
+    fn resamples_to_fixed_intervals() {
+        let points = vec![
+            RoutePoint { distance: 0., elevation: 0. },
+            RoutePoint { distance: 7., elevation: 0.35 },
+            RoutePoint { distance: 31., elevation: 1.55 },
+            RoutePoint { distance: 44., elevation: 2.2 }
+        ];
+
+        let result = resample(&points, 10.);
+
+        let distances: Vec<f32> = result.iter().map(|x| x.distance).collect();
+        // the 4 m remainder is merged into the last segment
+        assert_eq!(distances, vec![0., 10., 20., 30., 44.]);
+        assert!(grades(&Route { name: String::new(), points: result }).iter().all(|x| (x - 0.05).abs() < 0.0001));
+    }
+
+    #[test]
+    fn median_removes_spikes() {
+        assert_eq!(median_filter(&[1., 2., 50., 4., 5.], 3), vec![2., 2., 4., 5., 5.]);
+    }
+
+    #[test]
+    fn smooths_a_noisy_climb() {
+        let raw = noisy_climb(0.06, 2000.);
+        let processed = process(&raw, &ProcessingConfig::new());
+        let processed_grades = grades(&processed);
+
+        // the noise makes the raw track swing between steep ramps and descents
+        assert!(grades(&raw).iter().any(|x| x.abs() > 0.5));
+        assert!(total_ascent(&raw.points) > 300.);
+
+        assert!((total_ascent(&processed.points) - 120.).abs() < 10., "{}", total_ascent(&processed.points));
+        assert!((processed.points.last().unwrap().distance - 2000.).abs() < 0.01);
+        // away from the ends the grade stays close to the real one
+        let middle = &processed_grades[20..processed_grades.len() - 20];
+        assert!(middle.iter().all(|x| (x - 0.06).abs() < 0.0125), "{:?}", middle);
+    }
+
+    #[test]
+    fn clamps_and_rate_limits_walls() {
+        // flat, then a 40% wall
+        let mut points: Vec<RoutePoint> = (0..=50).map(|x| RoutePoint { distance: x as f32 * 10., elevation: 0. }).collect();
+        points.extend((1..=40).map(|x| RoutePoint { distance: 500. + x as f32 * 10., elevation: x as f32 * 4. }));
+        let config = ProcessingConfig::new();
+
+        let processed_grades = grades(&process(&Route { name: String::new(), points }, &config));
+
+        assert!(processed_grades.iter().all(|x| x.abs() <= config.max_grade + 0.0001));
+        assert!(processed_grades.windows(2).all(|x| (x[1] - x[0]).abs() <= config.max_grade_change * 10. + 0.0001));
+        assert_eq!(processed_grades.iter().cloned().fold(0., f32::max), config.max_grade);
+    }
+}
@@ -0,0 +1,93 @@
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use iced::widget::{button, column, row, text};
+use iced::{Alignment, Element};
+
+use crate::device::Device;
+use crate::hrv::{HrvMetrics, HrvRecord, Readiness};
+use crate::profile::Date;
+use crate::stopwatch::format_duration;
+
+#[derive(Debug, Clone)]
+pub enum SensorsMessage {
+    Scan,
+    Listen,
+    StartReadiness,
+    CancelReadiness
+}
+
+// scanned devices, live HRV from the strap and the morning readiness check
+pub fn view<'a>(
+    devices: &[Device],
+    windows: &[Duration],
+    live_hrv: &[Option<HrvMetrics>],
+    readiness: Option<&Readiness>,
+    last: Option<&HrvRecord>
+) -> Element<'a, SensorsMessage> {
+    let scanned_devices = column(
+        devices
+            .iter()
+            .map(|device| {
+                row![text(format!("{} {} {}", device.name, device.address, device.is_connected))]
+                    .spacing(10)
+                    .into()
+            }).collect()
+    );
+
+    let live_hrv = column(
+        windows.iter()
+            .zip(live_hrv)
+            .map(|(window, metrics)| {
+                let value = metrics.as_ref().map_or(String::from("-"), |x| format!(
+                    "RMSSD {:.0} ms  SDNN {:.0} ms  pNN50 {:.0}%",
+                    x.rmssd,
+                    x.sdnn,
+                    x.pnn50
+                ));
+                text(format!("HRV {} min: {}", window.as_secs() / 60, value)).into()
+            })
+            .collect()
+    )
+    .spacing(5);
+
+    let readiness = match readiness {
+        Some(readiness) => row![
+            text(format!("Lie still... {}", format_duration(readiness.remaining(Instant::now())))),
+            button("Cancel").on_press(SensorsMessage::CancelReadiness).padding(5.)
+        ],
+        None => {
+            let last = last.map_or(String::new(), |x| {
+                let days = x.recorded_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86_400;
+                format!(
+                    "Last readiness {}: RMSSD {:.0} ms, HR {:.0} bpm, {} artifacts",
+                    Date::from_unix_days(days as i64),
+                    x.metrics.rmssd,
+                    x.metrics.heart_rate(),
+                    x.metrics.artifacts
+                )
+            });
+            row![
+                button("Morning readiness").on_press(SensorsMessage::StartReadiness).padding(5.),
+                text(last)
+            ]
+        }
+    }
+    .spacing(10)
+    .align_items(Alignment::Center);
+
+    //todo: start listening automatically when device is connected
+    return column![
+        text("Devices").size(24),
+        row![
+            button("Scan").on_press(SensorsMessage::Scan).padding(5.),
+            button("Listen").on_press(SensorsMessage::Listen).padding(5.)
+        ]
+        .spacing(10),
+        scanned_devices,
+        text("Heart rate variability").size(24),
+        live_hrv,
+        readiness
+    ]
+    .spacing(10)
+    .into();
+}
@@ -0,0 +1,167 @@
+use iced::widget::{button, column, pick_list, row, text, text_input};
+use iced::{Alignment, Element, Length};
+
+use crate::autopause::Trigger;
+use crate::profile::{Profile, ProfileError, ProfileField, Sex, Units};
+use crate::zones::{HeartRateZoneKind, PowerZoneKind};
+
+// fields edited as free text, the rest are pick lists
+const TEXT_FIELDS: [ProfileField; 20] = [
+    ProfileField::Name,
+    ProfileField::Weight,
+    ProfileField::BikeWeight,
+    ProfileField::Ftp,
+    ProfileField::CriticalPower,
+    ProfileField::WPrime,
+    ProfileField::MaxHeartRate,
+    ProfileField::RestingHeartRate,
+    ProfileField::Lthr,
+    ProfileField::WheelCircumference,
+    ProfileField::DragArea,
+    ProfileField::RollingResistance,
+    ProfileField::AirDensity,
+    ProfileField::DrivetrainLoss,
+    ProfileField::RouteDifficulty,
+    ProfileField::AutoPauseThreshold,
+    ProfileField::AutoPauseStartDelay,
+    ProfileField::AutoPauseStopDelay,
+    ProfileField::DateOfBirth,
+    ProfileField::CustomPowerZones
+];
+
+#[derive(Debug, Clone)]
+pub enum SettingsMessage {
+    FieldChanged(ProfileField, String),
+    UnitsSelected(Units),
+    SexSelected(Sex),
+    AutoPauseTriggerSelected(Trigger),
+    HeartRateZonesSelected(HeartRateZoneKind),
+    PowerZonesSelected(PowerZoneKind),
+    Save,
+    Cancel
+}
+
+#[derive(Debug, Clone)]
+pub enum SettingsEvent {
+    Saved(Profile),
+    Closed
+}
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    draft: Profile,
+    inputs: Vec<(ProfileField, String)>
+}
+
+impl Settings {
+    pub fn new(profile: &Profile) -> Settings {
+        return Settings {
+            draft: profile.clone(),
+            inputs: TEXT_FIELDS.into_iter()
+                .map(|field| (field, profile.get(field)))
+                .collect()
+        }
+    }
+
+    pub fn update(&mut self, message: SettingsMessage) -> Result<Option<SettingsEvent>, ProfileError> {
+        match message {
+            SettingsMessage::FieldChanged(field, value) => {
+                if let Some(input) = self.inputs.iter_mut().find(|(x, _)| *x == field) {
+                    input.1 = value;
+                }
+            }
+            SettingsMessage::UnitsSelected(value) => {
+                self.draft.units = value;
+            }
+            SettingsMessage::SexSelected(value) => {
+                self.draft.sex = value;
+            }
+            SettingsMessage::AutoPauseTriggerSelected(value) => {
+                self.draft.auto_pause_trigger = value;
+            }
+            SettingsMessage::HeartRateZonesSelected(value) => {
+                self.draft.zones.heart_rate = value;
+            }
+            SettingsMessage::PowerZonesSelected(value) => {
+                self.draft.zones.power = value;
+            }
+            SettingsMessage::Save => {
+                let mut profile = self.draft.clone();
+                for (field, value) in &self.inputs {
+                    profile.set(*field, value)?;
+                }
+
+                profile.save()?;
+                return Ok(Some(SettingsEvent::Saved(profile)));
+            }
+            SettingsMessage::Cancel => {
+                return Ok(Some(SettingsEvent::Closed));
+            }
+        }
+
+        return Ok(None);
+    }
+
+    pub fn view(&self) -> Element<SettingsMessage> {
+        let inputs = column(
+            self.inputs
+                .iter()
+                .map(|(field, value)| {
+                    let field = *field;
+                    row![
+                        text(field.label()).width(Length::Fixed(220.)),
+                        text_input(field.label(), value, move |x| SettingsMessage::FieldChanged(field, x))
+                            .padding(5.)
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .into()
+                }).collect()
+        )
+        .spacing(5);
+
+        let pick_lists = column![
+            row![
+                text(ProfileField::Units.label()).width(Length::Fixed(220.)),
+                pick_list(&Units::ALL[..], Some(self.draft.units), SettingsMessage::UnitsSelected)
+            ]
+            .spacing(10),
+            row![
+                text(ProfileField::Sex.label()).width(Length::Fixed(220.)),
+                pick_list(&Sex::ALL[..], Some(self.draft.sex), SettingsMessage::SexSelected)
+            ]
+            .spacing(10),
+            row![
+                text(ProfileField::AutoPauseTrigger.label()).width(Length::Fixed(220.)),
+                pick_list(&Trigger::ALL[..], Some(self.draft.auto_pause_trigger), SettingsMessage::AutoPauseTriggerSelected)
+            ]
+            .spacing(10),
+            row![
+                text(ProfileField::HeartRateZones.label()).width(Length::Fixed(220.)),
+                pick_list(
+                    &HeartRateZoneKind::ALL[..],
+                    Some(self.draft.zones.heart_rate),
+                    SettingsMessage::HeartRateZonesSelected
+                )
+            ]
+            .spacing(10),
+            row![
+                text(ProfileField::PowerZones.label()).width(Length::Fixed(220.)),
+                pick_list(&PowerZoneKind::ALL[..], Some(self.draft.zones.power), SettingsMessage::PowerZonesSelected)
+            ]
+            .spacing(10)
+        ]
+        .spacing(5);
+
+        let buttons = row![
+            button("Save").on_press(SettingsMessage::Save).padding(5.),
+            button("Cancel").on_press(SettingsMessage::Cancel).padding(5.)
+        ]
+        .spacing(10);
+
+        return column![text("Rider profile").size(30), inputs, pick_lists, buttons]
+            .width(Length::Fixed(500.))
+            .spacing(10)
+            .into();
+    }
+}
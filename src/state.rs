@@ -1,17 +1,41 @@
+use std::time::Instant;
+
+use crate::bluetoothctl::Trainer;
+
 #[derive(Clone, Debug)]
 pub struct State {
     pub connected_devices: Vec<String>,
+    pub battery_levels: Vec<(String, u8)>, // percent
     pub heart_rate: u8,
-    pub heart_rate_history: Vec<u8>
-
+    pub heart_rate_history: Vec<u8>,
+    pub rr_intervals: Vec<f32>, // ms
+    // filled from the trainer's FTMS indoor bike data, see bluetoothctl,
+    // left at zero and None without a trainer
+    pub power: u16,
+    pub cadence: u8,
+    pub speed: f32, // km/h
+    pub wheel_rate: Option<f32>, // revolutions per second
+    pub trainer_updated_at: Option<Instant>,
+    pub trainer: Option<Trainer>,
+    // failures in the listener threads, shown by the app
+    pub errors: Vec<String>
 }
 
 impl State {
     pub fn new() -> State {
         return State { 
             connected_devices: Vec::new(),
+            battery_levels: Vec::new(),
             heart_rate: 0,
-            heart_rate_history: Vec::new()
+            heart_rate_history: Vec::new(),
+            rr_intervals: Vec::new(),
+            power: 0,
+            cadence: 0,
+            speed: 0.,
+            wheel_rate: None,
+            trainer_updated_at: None,
+            trainer: None,
+            errors: Vec::new()
         }
     }
 }
@@ -0,0 +1,65 @@
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub struct Stopwatch {
+    pub duration: Duration,
+    pub state: StopwatchState
+}
+
+#[derive(Debug, Clone)]
+pub enum StopwatchState {
+    Idle,
+    Ticking { last_tick: Instant },
+    Paused
+}
+
+impl Stopwatch {
+    pub fn new() -> Stopwatch {
+        return Stopwatch {
+            duration: Duration::default(),
+            state: StopwatchState::Idle
+        }
+    }
+
+    pub fn start(&mut self, now: Instant) {
+        self.state = StopwatchState::Ticking { last_tick: now };
+    }
+
+    pub fn pause(&mut self, now: Instant) {
+        // count the time since the last tick before freezing the clock
+        self.tick(now);
+        self.state = StopwatchState::Paused;
+    }
+
+    pub fn tick(&mut self, now: Instant) {
+        if let StopwatchState::Ticking { last_tick } = &mut self.state {
+            self.duration += now - *last_tick;
+            *last_tick = now;
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        return matches!(self.state, StopwatchState::Idle);
+    }
+
+    pub fn is_ticking(&self) -> bool {
+        return matches!(self.state, StopwatchState::Ticking { .. });
+    }
+
+    pub fn is_paused(&self) -> bool {
+        return matches!(self.state, StopwatchState::Paused);
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+
+    let seconds = duration.as_secs();
+    return format!(
+        "{:0>2}:{:0>2}:{:0>2}",
+        seconds / HOUR,
+        (seconds % HOUR) / MINUTE,
+        seconds % MINUTE
+    );
+}
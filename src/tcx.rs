@@ -0,0 +1,143 @@
+// Garmin Training Center activities, understood by most training platforms
+// REF: https://www8.garmin.com/xmlschemas/TrainingCenterDatabasev2.xsd
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::analytics::average;
+use crate::profile::{Date, Profile};
+use crate::recording::{Lap, Recording, RecordingError};
+use crate::trimp;
+
+pub fn export(recording: &Recording, profile: &Profile) -> Result<PathBuf, RecordingError> {
+    let dir = dirs::data_dir()
+        .map(|dir| dir.join("cyclo").join("exports"))
+        .ok_or(RecordingError::DataDirNotFound)?;
+    fs::create_dir_all(&dir).map_err(|err| RecordingError::WriteFailed(err.to_string()))?;
+
+    let started_at = recording.started_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let path = dir.join(format!("ride-{}.tcx", started_at));
+    fs::write(&path, to_tcx(recording, profile)).map_err(|err| RecordingError::WriteFailed(err.to_string()))?;
+    return Ok(path);
+}
+
+// timestamps are wall clock, pauses show up as gaps between trackpoints
+pub fn to_tcx(recording: &Recording, profile: &Profile) -> String {
+    let mut laps = recording.laps.clone();
+    if laps.is_empty() {
+        let end = recording.samples.last().map_or(Duration::default(), |x| x.elapsed);
+        laps.push(Lap { start: Duration::default(), end });
+    }
+
+    let mut lines = vec![
+        String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#),
+        String::from(r#"<TrainingCenterDatabase xmlns="http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2" xmlns:ns3="http://www.garmin.com/xmlschemas/ActivityExtension/v2">"#),
+        String::from("  <Activities>"),
+        String::from(r#"    <Activity Sport="Biking">"#),
+        format!("      <Id>{}</Id>", timestamp(recording.started_at)),
+    ];
+
+    let mut distance = 0.;
+    for lap in &laps {
+        let samples: Vec<_> = recording.samples.iter()
+            .filter(|x| x.elapsed > lap.start && x.elapsed <= lap.end)
+            .cloned()
+            .collect();
+        let heart_rate: Vec<f32> = samples.iter().filter(|x| x.heart_rate > 0).map(|x| x.heart_rate as f32).collect();
+
+        lines.push(format!(r#"      <Lap StartTime="{}">"#, timestamp(recording.wall_clock(lap.start))));
+        lines.push(format!("        <TotalTimeSeconds>{}</TotalTimeSeconds>", (lap.end - lap.start).as_secs()));
+        lines.push(format!("        <DistanceMeters>{:.1}</DistanceMeters>", samples.iter().map(|x| x.speed / 3.6).sum::<f32>()));
+        lines.push(format!("        <Calories>{:.0}</Calories>", trimp::calories(&samples, profile).unwrap_or_default()));
+        if let Some(heart_rate) = average(&heart_rate) {
+            lines.push(format!("        <AverageHeartRateBpm><Value>{:.0}</Value></AverageHeartRateBpm>", heart_rate));
+        }
+        lines.push(String::from("        <Intensity>Active</Intensity>"));
+        lines.push(String::from("        <TriggerMethod>Manual</TriggerMethod>"));
+        lines.push(String::from("        <Track>"));
+
+        for sample in &samples {
+            distance += sample.speed / 3.6;
+            lines.push(String::from("          <Trackpoint>"));
+            lines.push(format!("            <Time>{}</Time>", timestamp(recording.wall_clock(sample.elapsed))));
+            lines.push(format!("            <DistanceMeters>{:.1}</DistanceMeters>", distance));
+            if sample.heart_rate > 0 {
+                lines.push(format!("            <HeartRateBpm><Value>{}</Value></HeartRateBpm>", sample.heart_rate));
+            }
+            lines.push(format!("            <Cadence>{}</Cadence>", sample.cadence));
+            lines.push(format!(
+                "            <Extensions><ns3:TPX><ns3:Speed>{:.2}</ns3:Speed><ns3:Watts>{}</ns3:Watts></ns3:TPX></Extensions>",
+                sample.speed / 3.6,
+                sample.power
+            ));
+            lines.push(String::from("          </Trackpoint>"));
+        }
+
+        lines.push(String::from("        </Track>"));
+        lines.push(String::from("      </Lap>"));
+    }
+
+    lines.push(String::from("    </Activity>"));
+    lines.push(String::from("  </Activities>"));
+    lines.push(String::from("</TrainingCenterDatabase>"));
+    return lines.join("\n") + "\n";
+}
+
+// ISO 8601 in UTC
+fn timestamp(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let time_of_day = seconds % 86_400;
+    return format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        Date::from_unix_days((seconds / 86_400) as i64),
+        time_of_day / 3600,
+        time_of_day % 3600 / 60,
+        time_of_day % 60
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::profile::Profile;
+    use crate::recording::{Recording, Sample};
+    use crate::route::{parse_tcx, RouteError};
+    use crate::tcx::to_tcx;
+
+    #[test]
+    fn exports_laps_and_trackpoints() {
+        let mut recording = Recording::new();
+        recording.started_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for elapsed in 1..=4 {
+            recording.add_sample(Sample {
+                elapsed: Duration::from_secs(elapsed),
+                heart_rate: 130,
+                power: 200,
+                cadence: 90,
+                speed: 36.
+            });
+            if elapsed == 2 {
+                recording.lap(Duration::from_secs(2));
+            }
+            if elapsed == 3 {
+                recording.add_pause(Duration::from_secs(3), Duration::from_secs(60));
+            }
+        }
+        recording.finish(Duration::from_secs(4));
+
+        let tcx = to_tcx(&recording, &Profile::new());
+
+        assert!(tcx.contains("<Id>2023-11-14T22:13:20Z</Id>"));
+        assert_eq!(tcx.matches("<Lap ").count(), 2);
+        assert_eq!(tcx.matches("<Trackpoint>").count(), 4);
+        // the last trackpoint comes after the minute long pause
+        assert!(tcx.contains("<Time>2023-11-14T22:13:23Z</Time>"));
+        assert!(tcx.contains("<Time>2023-11-14T22:14:24Z</Time>"));
+        assert!(tcx.contains("<DistanceMeters>40.0</DistanceMeters>"));
+        assert!(tcx.contains("<ns3:Watts>200</ns3:Watts>"));
+        // well formed, though without positions there is no route in it
+        assert_eq!(parse_tcx(&tcx, String::new()), Err(RouteError::NotEnoughPoints));
+    }
+}
@@ -0,0 +1,79 @@
+use iced::widget::{button, column, container, pick_list, row, text};
+use iced::{theme, Alignment, Element, Length};
+
+use crate::chart::zone_color;
+use crate::dashboard::{COLUMNS, Dashboard, DashboardMessage, Readings, Tile, TileMetric, TileSize};
+use crate::profile::Units;
+
+pub fn view<'a, Message: 'a>(dashboard: &Dashboard, readings: &Readings, units: Units) -> Element<'a, Message> {
+    return column(
+        dashboard.tiles
+            .chunks(dashboard.columns.max(1))
+            .map(|tiles| {
+                row(tiles.iter().map(|x| tile(x, readings, units)).collect())
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .into()
+            })
+            .collect()
+    )
+    .spacing(10)
+    .align_items(Alignment::Center)
+    .into();
+}
+
+pub fn editor(dashboard: &Dashboard) -> Element<DashboardMessage> {
+    let tiles = column(
+        dashboard.tiles
+            .iter()
+            .enumerate()
+            .map(|(index, tile)| {
+                row![
+                    text(tile.metric).width(Length::Fixed(150.)),
+                    pick_list(&TileSize::ALL[..], Some(tile.size), move |x| DashboardMessage::SizeSelected(index, x)),
+                    button("<").on_press(DashboardMessage::MoveLeft(index)).padding(5.),
+                    button(">").on_press(DashboardMessage::MoveRight(index)).padding(5.),
+                    button("Remove").on_press(DashboardMessage::Remove(index)).padding(5.)
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .into()
+            })
+            .collect()
+    )
+    .spacing(5);
+
+    return column![
+        tiles,
+        row![
+            pick_list(dashboard.available(), None::<TileMetric>, DashboardMessage::Add).placeholder("Add tile"),
+            text("Columns"),
+            pick_list(&COLUMNS[..], Some(dashboard.columns), DashboardMessage::ColumnsSelected),
+            button("Reset").on_press(DashboardMessage::Reset).padding(5.)
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)
+    ]
+    .spacing(10)
+    .into();
+}
+
+fn tile<'a, Message: 'a>(tile: &Tile, readings: &Readings, units: Units) -> Element<'a, Message> {
+    let (width, size) = tile.size.dimensions();
+    let mut value = text(tile.metric.value(readings, units)).size(size);
+    // the zone tile takes the color of the power zone
+    if let (TileMetric::Zone, Some(zone)) = (tile.metric, readings.power_zone) {
+        value = value.style(zone_color(zone));
+    }
+
+    return container(
+        column![text(tile.metric).size(16), value]
+            .spacing(5)
+            .align_items(Alignment::Center)
+    )
+    .style(theme::Container::Box)
+    .width(Length::Fixed(width))
+    .padding(10)
+    .center_x()
+    .into();
+}
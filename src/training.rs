@@ -0,0 +1,244 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use iced::widget::canvas::{self, Canvas, Cursor, Frame, Geometry, Path, Stroke};
+use iced::widget::{button, column, pick_list, row, text, text_input};
+use iced::{Alignment, Color, Element, Length, Point, Rectangle, Renderer, Theme};
+
+use crate::power_curve::DateRange;
+use crate::profile::{Date, Profile};
+use crate::recording::Recording;
+use crate::training_load::{self, LoadPoint, PlannedWorkout, TrainingLoadError};
+use crate::workout::Workout;
+
+#[derive(Debug, Clone)]
+pub enum TrainingMessage {
+    RangeSelected(DateRange),
+    WorkoutSelected(String),
+    DaysAheadChanged(String),
+    AddPlanned,
+    RemovePlanned(usize),
+    Close
+}
+
+#[derive(Debug, Clone)]
+pub enum TrainingEvent {
+    Closed
+}
+
+// performance management chart over the ride history
+#[derive(Debug, Clone)]
+pub struct Training {
+    // (day, stress) per ride
+    rides: Vec<(i64, f32)>,
+    plan: Vec<PlannedWorkout>,
+    // (name, expected stress)
+    workouts: Vec<(String, f32)>,
+    range: DateRange,
+    selected_workout: Option<String>,
+    days_ahead: String,
+    points: Vec<LoadPoint>
+}
+
+struct PerformanceChart {
+    points: Vec<LoadPoint>
+}
+
+const CTL_COLOR: Color = Color { r: 0.12, g: 0.53, b: 0.9, a: 1. };
+const ATL_COLOR: Color = Color { r: 0.85, g: 0.11, b: 0.38, a: 1. };
+const TSB_COLOR: Color = Color { r: 0.98, g: 0.55, b: 0., a: 1. };
+
+impl Training {
+    pub fn new(rides: &[Recording], plan: Vec<PlannedWorkout>, workouts: &[Workout], profile: &Profile) -> Training {
+        let mut training = Training {
+            rides: rides.iter()
+                .filter_map(|ride| {
+                    let stress = training_load::ride_stress(ride, profile)?;
+                    Some((training_load::day(ride.started_at), stress))
+                })
+                .collect(),
+            plan,
+            workouts: workouts.iter()
+                .map(|x| (x.name.clone(), training_load::planned_stress(x, profile.ftp)))
+                .collect(),
+            range: DateRange::ThreeMonths,
+            selected_workout: None,
+            days_ahead: String::from("1"),
+            points: Vec::new()
+        };
+        training.refresh();
+        return training;
+    }
+
+    pub fn update(&mut self, message: TrainingMessage) -> Result<Option<TrainingEvent>, TrainingLoadError> {
+        match message {
+            TrainingMessage::RangeSelected(range) => {
+                self.range = range;
+            }
+            TrainingMessage::WorkoutSelected(name) => {
+                self.selected_workout = Some(name);
+            }
+            TrainingMessage::DaysAheadChanged(value) => {
+                self.days_ahead = value;
+            }
+            TrainingMessage::AddPlanned => {
+                let workout = self.workouts.iter().find(|(name, _)| Some(name) == self.selected_workout.as_ref());
+                if let (Some((name, stress)), Ok(days)) = (workout, self.days_ahead.trim().parse::<i64>()) {
+                    self.plan.push(PlannedWorkout {
+                        day: training_load::today() + days.max(1),
+                        name: name.clone(),
+                        stress: *stress
+                    });
+                    self.plan.sort_by_key(|x| x.day);
+                    training_load::save_plan(&self.plan)?;
+                }
+            }
+            TrainingMessage::RemovePlanned(index) => {
+                if index < self.plan.len() {
+                    self.plan.remove(index);
+                    training_load::save_plan(&self.plan)?;
+                }
+            }
+            TrainingMessage::Close => return Ok(Some(TrainingEvent::Closed))
+        }
+
+        self.refresh();
+        return Ok(None);
+    }
+
+    fn refresh(&mut self) {
+        let today = training_load::today();
+        let now = SystemTime::now();
+        // past workouts drop out of the plan
+        let plan: Vec<PlannedWorkout> = self.plan.iter().filter(|x| x.day > today).cloned().collect();
+
+        // the whole history is needed to build up the load, the range only limits what is shown
+        self.points = training_load::performance(&self.rides, &plan, today)
+            .into_iter()
+            .filter(|x| x.projected || self.range.contains(UNIX_EPOCH + Duration::from_secs(x.day as u64 * 86_400), now))
+            .collect();
+    }
+
+    pub fn view(&self) -> Element<TrainingMessage> {
+        let today = training_load::today();
+        let current = self.points.iter().rev().find(|x| !x.projected);
+        let summary = match current {
+            Some(point) => row![
+                text(format!("Fitness (CTL) {:.0}", point.ctl)).style(CTL_COLOR),
+                text(format!("Fatigue (ATL) {:.0}", point.atl)).style(ATL_COLOR),
+                text(format!("Form (TSB) {:.0}", point.tsb)).style(TSB_COLOR)
+            ],
+            None => row![text("No rides with power or heart rate yet")]
+        }
+        .spacing(20);
+
+        let names: Vec<String> = self.workouts.iter().map(|(name, _)| name.clone()).collect();
+        let mut add_btn = button("Add to plan").padding(5.);
+        if self.selected_workout.is_some() {
+            add_btn = add_btn.on_press(TrainingMessage::AddPlanned);
+        }
+
+        let planner = row![
+            pick_list(names, self.selected_workout.clone(), TrainingMessage::WorkoutSelected),
+            text("in"),
+            text_input("days", &self.days_ahead, TrainingMessage::DaysAheadChanged)
+                .padding(5.)
+                .width(Length::Fixed(60.)),
+            text("days"),
+            add_btn
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let plan = column(
+            self.plan
+                .iter()
+                .enumerate()
+                .filter(|(_, x)| x.day > today)
+                .map(|(index, x)| {
+                    row![
+                        text(format!("{}  {}  TSS {:.0}", Date::from_unix_days(x.day), x.name, x.stress)),
+                        button("Remove").on_press(TrainingMessage::RemovePlanned(index)).padding(5.)
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .into()
+                })
+                .collect()
+        )
+        .spacing(5);
+
+        return column![
+            row![
+                text("Training load").size(30),
+                pick_list(&DateRange::ALL[..], Some(self.range), TrainingMessage::RangeSelected),
+                button("Close").on_press(TrainingMessage::Close).padding(5.)
+            ]
+            .spacing(20)
+            .align_items(Alignment::Center),
+            summary,
+            Canvas::new(PerformanceChart { points: self.points.clone() })
+                .width(Length::Fill)
+                .height(Length::Fixed(300.)),
+            planner,
+            plan
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .into();
+    }
+}
+
+impl<Message> canvas::Program<Message> for PerformanceChart {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: Cursor
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        if self.points.len() < 2 {
+            return vec![frame.into_geometry()];
+        }
+
+        let max_value = self.points.iter().map(|x| x.ctl.max(x.atl)).fold(1., f32::max) * 1.1;
+        let min_value = self.points.iter().map(|x| x.tsb).fold(0., f32::min) * 1.1;
+        let span = max_value - min_value;
+
+        let width = bounds.width;
+        let height = bounds.height;
+        let first = self.points[0].day as f32;
+        let days = (self.points.len() - 1) as f32;
+        let to_x = |day: i64| (day as f32 - first) / days * width;
+        let to_y = |value: f32| height - (value - min_value) / span * height;
+
+        let zero = Path::line(Point::new(0., to_y(0.)), Point::new(width, to_y(0.)));
+        frame.stroke(&zero, Stroke::default().with_width(1.).with_color(Color::from_rgb8(0xbd, 0xbd, 0xbd)));
+
+        let series: [(fn(&LoadPoint) -> f32, Color); 3] = [
+            (|x| x.ctl, CTL_COLOR),
+            (|x| x.atl, ATL_COLOR),
+            (|x| x.tsb, TSB_COLOR)
+        ];
+
+        for (value, color) in series {
+            // projected days are drawn faded
+            for pair in self.points.windows(2) {
+                let line = Path::line(
+                    Point::new(to_x(pair[0].day), to_y(value(&pair[0]))),
+                    Point::new(to_x(pair[1].day), to_y(value(&pair[1])))
+                );
+                let color = match pair[1].projected {
+                    true => Color { a: 0.4, ..color },
+                    false => color
+                };
+                frame.stroke(&line, Stroke::default().with_width(2.).with_color(color));
+            }
+        }
+
+        return vec![frame.into_geometry()];
+    }
+}
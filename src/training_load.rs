@@ -0,0 +1,242 @@
+// chronic (fitness) and acute (fatigue) training load as exponentially
+// weighted averages of the daily stress, form is their difference
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+use crate::analytics::{normalized_power, training_stress_score};
+use crate::profile::Profile;
+use crate::recording::Recording;
+use crate::trimp;
+use crate::workout::{Target, Workout};
+
+#[derive(Error, Debug, Clone)]
+pub enum TrainingLoadError {
+    #[error("Data directory not found")]
+    DataDirNotFound,
+    #[error("Failed to read the training plan: {0}")]
+    ReadFailed(String),
+    #[error("Failed to write the training plan: {0}")]
+    WriteFailed(String)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoadPoint {
+    // days since 1970-01-01
+    pub day: i64,
+    pub stress: f32,
+    // fitness
+    pub ctl: f32,
+    // fatigue
+    pub atl: f32,
+    // form going into the day
+    pub tsb: f32,
+    pub projected: bool
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedWorkout {
+    pub day: i64,
+    pub name: String,
+    pub stress: f32
+}
+
+const CTL_DAYS: f32 = 42.;
+const ATL_DAYS: f32 = 7.;
+// targets assumed for steps the rider paces on their own
+const FREE_RIDE_INTENSITY: f32 = 0.6;
+const MAX_EFFORT_INTENSITY: f32 = 1.2;
+
+pub fn day(time: SystemTime) -> i64 {
+    return (time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86_400) as i64;
+}
+
+pub fn today() -> i64 {
+    return day(SystemTime::now());
+}
+
+// TSS when the ride has power, otherwise heart rate stress scaled so an
+// hour at threshold heart rate is worth 100 like an hour at FTP
+pub fn ride_stress(ride: &Recording, profile: &Profile) -> Option<f32> {
+    let samples = &ride.samples;
+    if samples.iter().any(|x| x.power > 0) {
+        let ftp = ride.ftp.unwrap_or(profile.ftp);
+        let power: Vec<f32> = samples.iter().map(|x| x.power as f32).collect();
+        let np = normalized_power(&power)?;
+        if ftp == 0 {
+            return None;
+        }
+        return Some(training_stress_score(Duration::from_secs(samples.len() as u64), np, np / ftp as f32, ftp));
+    }
+
+    let threshold = trimp::banister_rate(profile.lthr as f32, profile) * 60.;
+    if threshold <= 0. || samples.iter().all(|x| x.heart_rate == 0) {
+        return None;
+    }
+    return Some(trimp::banister(samples, profile) / threshold * 100.);
+}
+
+// expected TSS of riding the workout as prescribed
+pub fn planned_stress(workout: &Workout, ftp: u16) -> f32 {
+    let mut power = Vec::new();
+    for step in workout.steps() {
+        for second in 0..step.duration.as_secs() {
+            let watts = match step.target {
+                Target::FreeRide => ftp as f32 * FREE_RIDE_INTENSITY,
+                Target::MaxEffort => ftp as f32 * MAX_EFFORT_INTENSITY,
+                _ => step.target_watts(Duration::from_secs(second), ftp).unwrap_or_default()
+            };
+            power.push(watts);
+        }
+    }
+
+    let Some(np) = normalized_power(&power) else {
+        return 0.;
+    };
+    if ftp == 0 {
+        return 0.;
+    }
+    return training_stress_score(Duration::from_secs(power.len() as u64), np, np / ftp as f32, ftp);
+}
+
+// one point per day from the first ride, through today, to the last planned workout
+pub fn performance(rides: &[(i64, f32)], plan: &[PlannedWorkout], today: i64) -> Vec<LoadPoint> {
+    let Some(first) = rides.iter().map(|(day, _)| *day).chain(plan.iter().map(|x| x.day)).min() else {
+        return Vec::new();
+    };
+    let last = plan.iter().map(|x| x.day).max().unwrap_or(today).max(today);
+
+    let mut result = Vec::new();
+    let mut ctl = 0.;
+    let mut atl = 0.;
+    for day in first..=last {
+        let projected = day > today;
+        let stress: f32 = match projected {
+            true => plan.iter().filter(|x| x.day == day).map(|x| x.stress).sum(),
+            false => rides.iter().filter(|(x, _)| *x == day).map(|(_, stress)| stress).sum()
+        };
+
+        let tsb = ctl - atl;
+        ctl += (stress - ctl) / CTL_DAYS;
+        atl += (stress - atl) / ATL_DAYS;
+        result.push(LoadPoint { day, stress, ctl, atl, tsb, projected });
+    }
+
+    return result;
+}
+
+pub fn load_plan() -> Result<Vec<PlannedWorkout>, TrainingLoadError> {
+    let path = plan_path().ok_or(TrainingLoadError::DataDirNotFound)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|err| TrainingLoadError::ReadFailed(err.to_string()))?;
+
+    // day,stress,name - the name may contain commas
+    return Ok(content.lines()
+        .filter_map(|line| {
+            let mut columns = line.splitn(3, ',');
+            Some(PlannedWorkout {
+                day: columns.next()?.parse().ok()?,
+                stress: columns.next()?.parse().ok()?,
+                name: columns.next()?.to_string()
+            })
+        })
+        .collect());
+}
+
+pub fn save_plan(plan: &[PlannedWorkout]) -> Result<(), TrainingLoadError> {
+    let path = plan_path().ok_or(TrainingLoadError::DataDirNotFound)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|err| TrainingLoadError::WriteFailed(err.to_string()))?;
+    }
+
+    let content: String = plan.iter()
+        .map(|x| format!("{},{:.1},{}\n", x.day, x.stress, x.name))
+        .collect();
+
+    return fs::write(&path, content)
+        .map_err(|err| TrainingLoadError::WriteFailed(err.to_string()));
+}
+
+fn plan_path() -> Option<PathBuf> {
+    return dirs::data_dir().map(|dir| dir.join("cyclo").join("plan.csv"));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::profile::Profile;
+    use crate::recording::{Recording, Sample};
+    use crate::training_load::{performance, planned_stress, ride_stress, PlannedWorkout};
+    use crate::workout::{Block, Power, Step, Workout};
+
+    fn ride(power: u16, heart_rate: u8, seconds: u64) -> Recording {
+        let mut recording = Recording::new();
+        recording.ftp = Some(250);
+        for elapsed in 1..=seconds {
+            recording.add_sample(Sample {
+                elapsed: Duration::from_secs(elapsed),
+                heart_rate,
+                power,
+                cadence: 90,
+                speed: 0.
+            });
+        }
+        return recording;
+    }
+
+    #[test]
+    fn stress_falls_back_to_heart_rate() {
+        let profile = Profile::new();
+
+        let power = ride_stress(&ride(250, 150, 3600), &profile).unwrap();
+        let heart_rate = ride_stress(&ride(0, profile.lthr, 3600), &profile).unwrap();
+
+        assert!((power - 100.).abs() < 0.1);
+        assert!((heart_rate - 100.).abs() < 0.5, "{}", heart_rate);
+        assert_eq!(ride_stress(&ride(0, 0, 3600), &profile), None);
+    }
+
+    #[test]
+    fn fitness_and_fatigue_follow_daily_stress() {
+        let rides: Vec<(i64, f32)> = (0..7).map(|day| (day, 100.)).collect();
+        let points = performance(&rides, &[], 6);
+
+        assert_eq!(points.len(), 7);
+        // after one day
+        assert!((points[0].ctl - 100. / 42.).abs() < 0.01);
+        assert!((points[0].atl - 100. / 7.).abs() < 0.01);
+        assert_eq!(points[0].tsb, 0.);
+        // a week of 100 TSS days, 1 - (6/7)^7 of the way to 100
+        assert!((points[6].atl - 66.01).abs() < 0.05, "{}", points[6].atl);
+        assert!(points[6].tsb < 0.);
+    }
+
+    #[test]
+    fn plan_is_projected_after_today() {
+        let workout = Workout {
+            name: String::from("Threshold hour"),
+            description: String::new(),
+            blocks: vec![Block::Step(Step::steady(Duration::from_secs(3600), Power::Ftp(1.)))]
+        };
+        let stress = planned_stress(&workout, 250);
+        let plan = vec![PlannedWorkout { day: 12, name: workout.name.clone(), stress }];
+
+        let points = performance(&[(10, 50.)], &plan, 10);
+
+        assert!((stress - 100.).abs() < 0.1);
+        assert_eq!(points.len(), 3);
+        assert!(!points[0].projected);
+        assert!(points[2].projected);
+        assert_eq!(points[2].stress, stress);
+        assert_eq!(points[1].stress, 0.);
+    }
+}
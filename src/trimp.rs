@@ -0,0 +1,151 @@
+// heart rate based training load (TRIMP) and energy expenditure, samples are expected once per second
+
+use crate::profile::{Profile, Sex};
+use crate::recording::Sample;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingImpulse {
+    pub banister: f32,
+    pub edwards: f32,
+    // kcal, None when neither power nor the rider's age is known
+    pub calories: Option<f32>
+}
+
+// share of max heart rate where each Edwards zone starts, weighted 1 to 5
+const EDWARDS_ZONES: [f32; 5] = [0.5, 0.6, 0.7, 0.8, 0.9];
+// gross efficiency of a cyclist, the rest of the energy turns into heat
+const GROSS_EFFICIENCY: f32 = 0.24;
+const JOULES_PER_CALORIE: f32 = 4.184;
+
+impl TrainingImpulse {
+    pub fn new(samples: &[Sample], profile: &Profile) -> TrainingImpulse {
+        return TrainingImpulse {
+            banister: banister(samples, profile),
+            edwards: edwards(samples, profile),
+            calories: calories(samples, profile)
+        };
+    }
+}
+
+// minutes weighted by an exponential of the heart rate reserve
+pub fn banister(samples: &[Sample], profile: &Profile) -> f32 {
+    return samples.iter()
+        .filter(|x| x.heart_rate > 0)
+        .map(|x| banister_rate(x.heart_rate as f32, profile) / 60.)
+        .sum();
+}
+
+// TRIMP per minute at the given heart rate
+pub fn banister_rate(heart_rate: f32, profile: &Profile) -> f32 {
+    let reserve = profile.max_heart_rate as f32 - profile.resting_heart_rate as f32;
+    if reserve <= 0. {
+        return 0.;
+    }
+
+    let fraction = ((heart_rate - profile.resting_heart_rate as f32) / reserve).clamp(0., 1.);
+    let (a, b) = match profile.sex {
+        Sex::Male => (0.64, 1.92),
+        Sex::Female => (0.86, 1.67)
+    };
+    return fraction * a * (b * fraction).exp();
+}
+
+// minutes in each zone of max heart rate times the zone number
+pub fn edwards(samples: &[Sample], profile: &Profile) -> f32 {
+    let max = profile.max_heart_rate as f32;
+    if max <= 0. {
+        return 0.;
+    }
+
+    return samples.iter()
+        .map(|x| EDWARDS_ZONES.iter().filter(|from| x.heart_rate as f32 >= *from * max).count() as f32 / 60.)
+        .sum();
+}
+
+// from the work done when there is power, otherwise from heart rate
+// REF: Keytel et al. 2005, "Prediction of energy expenditure from heart rate monitoring during submaximal exercise"
+pub fn calories(samples: &[Sample], profile: &Profile) -> Option<f32> {
+    if samples.iter().any(|x| x.power > 0) {
+        let work: f32 = samples.iter().map(|x| x.power as f32).sum();
+        return Some(work / GROSS_EFFICIENCY / JOULES_PER_CALORIE / 1000.);
+    }
+
+    let age = profile.age()? as f32;
+    let weight = profile.weight;
+    let kilojoules_per_minute = |heart_rate: f32| match profile.sex {
+        Sex::Male => -55.0969 + 0.6309 * heart_rate + 0.1988 * weight + 0.2017 * age,
+        Sex::Female => -20.4022 + 0.4472 * heart_rate - 0.1263 * weight + 0.074 * age
+    };
+
+    let total: f32 = samples.iter()
+        .filter(|x| x.heart_rate > 0)
+        .map(|x| kilojoules_per_minute(x.heart_rate as f32).max(0.) / 60.)
+        .sum();
+    return Some(total / JOULES_PER_CALORIE);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::profile::{Date, Profile, Sex};
+    use crate::recording::Sample;
+    use crate::trimp::{banister, calories, edwards};
+
+    fn samples(heart_rate: u8, power: u16, seconds: u64) -> Vec<Sample> {
+        return (1..=seconds)
+            .map(|x| Sample {
+                elapsed: Duration::from_secs(x),
+                heart_rate,
+                power,
+                cadence: 0,
+                speed: 0.
+            })
+            .collect();
+    }
+
+    fn profile(sex: Sex) -> Profile {
+        let mut profile = Profile::new();
+        profile.max_heart_rate = 200;
+        profile.resting_heart_rate = 50;
+        profile.weight = 70.;
+        profile.sex = sex;
+        let today = Date::today();
+        profile.date_of_birth = Some(Date { year: today.year - 30, ..today });
+        return profile;
+    }
+
+    #[test]
+    fn banister_weights_by_heart_rate_reserve() {
+        // 125 bpm is half the reserve
+        let ride = samples(125, 0, 3600);
+
+        assert!((banister(&ride, &profile(Sex::Male)) - 60. * 0.5 * 0.64 * 0.96_f32.exp()).abs() < 0.1);
+        assert!((banister(&ride, &profile(Sex::Female)) - 60. * 0.5 * 0.86 * 0.835_f32.exp()).abs() < 0.1);
+    }
+
+    #[test]
+    fn edwards_counts_minutes_per_zone() {
+        let mut ride = samples(150, 0, 600);
+        ride.extend(samples(185, 0, 600));
+
+        // 10 min in zone 3 and 10 min in zone 5
+        assert!((edwards(&ride, &profile(Sex::Male)) - 80.).abs() < 0.01);
+        assert_eq!(edwards(&samples(90, 0, 600), &profile(Sex::Male)), 0.);
+    }
+
+    #[test]
+    fn calories_prefer_power() {
+        // 200 W for an hour is 720 kJ of work
+        let with_power = calories(&samples(140, 200, 3600), &profile(Sex::Male)).unwrap();
+        let heart_rate = calories(&samples(140, 0, 3600), &profile(Sex::Male)).unwrap();
+
+        assert!((with_power - 717.02).abs() < 0.5, "{}", with_power);
+        // Keytel: (-55.0969 + 0.6309 * 140 + 0.1988 * 70 + 0.2017 * 30) / 4.184 * 60
+        assert!((heart_rate - 762.85).abs() < 0.5, "{}", heart_rate);
+
+        let mut unknown_age = profile(Sex::Male);
+        unknown_age.date_of_birth = None;
+        assert_eq!(calories(&samples(140, 0, 60), &unknown_age), None);
+    }
+}
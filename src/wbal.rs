@@ -0,0 +1,80 @@
+// W' balance, Skiba's differential model
+// REF: Skiba et al. 2015, "Intramuscular determinants of the ability to recover work capacity above critical power"
+
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WPrimeBalance {
+    pub critical_power: f32,
+    // J
+    pub w_prime: f32,
+    pub balance: f32
+}
+
+// share of W' left below which the rider is warned
+pub const WARNING_FRACTION: f32 = 0.2;
+
+impl WPrimeBalance {
+    pub fn new(critical_power: u16, w_prime: u32) -> WPrimeBalance {
+        return WPrimeBalance {
+            critical_power: critical_power as f32,
+            w_prime: w_prime as f32,
+            balance: w_prime as f32
+        };
+    }
+
+    // above CP W' is spent joule for joule, below it recovers exponentially
+    // at a rate proportional to how far below CP the rider is
+    pub fn update(&mut self, power: f32, duration: Duration) -> f32 {
+        let seconds = duration.as_secs_f32();
+        if self.w_prime <= 0. {
+            return self.balance;
+        }
+
+        if power > self.critical_power {
+            self.balance -= (power - self.critical_power) * seconds;
+        } else {
+            let below = self.critical_power - power;
+            let depleted = self.w_prime - self.balance;
+            self.balance = self.w_prime - depleted * (-below * seconds / self.w_prime).exp();
+        }
+
+        return self.balance;
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.w_prime <= 0. {
+            return 0.;
+        }
+        return (self.balance / self.w_prime).clamp(0., 1.);
+    }
+
+    pub fn is_low(&self) -> bool {
+        return self.fraction() < WARNING_FRACTION;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::wbal::WPrimeBalance;
+
+    #[test]
+    fn depletes_above_and_recovers_below_critical_power() {
+        let mut balance = WPrimeBalance::new(250, 20_000);
+
+        // 60 s at 350 W spends 6 kJ
+        balance.update(350., Duration::from_secs(60));
+        assert_eq!(balance.balance, 14_000.);
+
+        // 100 W below CP for W' / 100 seconds recovers 1 - 1/e of the deficit
+        balance.update(150., Duration::from_secs(200));
+        assert!((balance.balance - (20_000. - 6_000. / std::f32::consts::E)).abs() < 1.);
+        assert!(!balance.is_low());
+
+        balance.update(550., Duration::from_secs(60));
+        assert!(balance.is_low());
+        assert_eq!(balance.fraction(), 0.);
+    }
+}
@@ -0,0 +1,488 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::erg::{self, Format};
+use crate::ftms::{self, TrainerCommand};
+use crate::zwo;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Power {
+    Watts(u16),
+    // fraction of FTP, 0.75 = 75%
+    Ftp(f32)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Steady(Power),
+    Ramp { from: Power, to: Power },
+    FreeRide,
+    MaxEffort
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub offset: Duration, // from the start of the step
+    pub message: String
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub duration: Duration,
+    pub target: Target,
+    pub cadence: Option<u8>,
+    pub cues: Vec<Cue>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Step(Step),
+    Repeat { count: u32, steps: Vec<Step> }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Workout {
+    pub name: String,
+    pub description: String,
+    pub blocks: Vec<Block>
+}
+
+#[derive(Debug, Clone)]
+pub struct ActiveStep<'a> {
+    pub index: usize,
+    pub step: &'a Step,
+    pub elapsed: Duration,
+    pub remaining: Duration
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compliance {
+    OnTarget,
+    Close,
+    Off
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkoutEngine {
+    pub workout: Workout,
+    pub steps: Vec<Step>,
+    pub ftp: u16,
+    // session time when the workout was started
+    pub started_at: Duration,
+    // workout time skipped over by the rider
+    pub skipped: Duration,
+    // scales every target, 1.05 = 5% harder
+    pub intensity: f32
+}
+
+impl Power {
+    pub fn watts(&self, ftp: u16) -> f32 {
+        match self {
+            Power::Watts(watts) => *watts as f32,
+            Power::Ftp(fraction) => fraction * ftp as f32
+        }
+    }
+}
+
+impl Step {
+    pub fn steady(duration: Duration, power: Power) -> Step {
+        return Step { duration, target: Target::Steady(power), cadence: None, cues: Vec::new() };
+    }
+
+    pub fn ramp(duration: Duration, from: Power, to: Power) -> Step {
+        return Step { duration, target: Target::Ramp { from, to }, cadence: None, cues: Vec::new() };
+    }
+
+    pub fn free_ride(duration: Duration) -> Step {
+        return Step { duration, target: Target::FreeRide, cadence: None, cues: Vec::new() };
+    }
+
+    // intensity scales the targets like the engine does
+    pub fn describe(&self, ftp: u16, intensity: f32) -> String {
+        let target = match &self.target {
+            Target::Steady(power) => format!("{:.0} W", power.watts(ftp) * intensity),
+            Target::Ramp { from, to } => format!("{:.0}-{:.0} W", from.watts(ftp) * intensity, to.watts(ftp) * intensity),
+            Target::FreeRide => String::from("Free ride"),
+            Target::MaxEffort => String::from("Max effort")
+        };
+
+        return match self.cadence {
+            Some(cadence) => format!("{} @ {} rpm", target, cadence),
+            None => target
+        };
+    }
+
+    // target at the given time into the step, None when the rider sets the pace
+    pub fn target_watts(&self, elapsed: Duration, ftp: u16) -> Option<f32> {
+        match &self.target {
+            Target::Steady(power) => Some(power.watts(ftp)),
+            Target::Ramp { from, to } => {
+                let progress = match self.duration.is_zero() {
+                    true => 1.,
+                    false => (elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.)
+                };
+                let from = from.watts(ftp);
+                let to = to.watts(ftp);
+                Some(from + (to - from) * progress)
+            }
+            Target::FreeRide | Target::MaxEffort => None
+        }
+    }
+}
+
+impl Workout {
+    pub fn steps(&self) -> Vec<Step> {
+        let mut result = Vec::new();
+        for block in &self.blocks {
+            match block {
+                Block::Step(step) => result.push(step.clone()),
+                Block::Repeat { count, steps } => {
+                    for _ in 0..*count {
+                        result.extend(steps.iter().cloned());
+                    }
+                }
+            }
+        }
+        return result;
+    }
+
+    pub fn duration(&self) -> Duration {
+        return self.steps().iter().map(|x| x.duration).sum();
+    }
+}
+
+impl WorkoutEngine {
+    pub fn new(workout: Workout, ftp: u16, started_at: Duration) -> WorkoutEngine {
+        let steps = workout.steps();
+        return WorkoutEngine { workout, steps, ftp, started_at, skipped: Duration::default(), intensity: 1. };
+    }
+
+    // position in the workout for the given session time
+    pub fn position(&self, elapsed: Duration) -> Duration {
+        return elapsed.saturating_sub(self.started_at) + self.skipped;
+    }
+
+    pub fn skip(&mut self, elapsed: Duration) {
+        if let Some(current) = self.current(elapsed) {
+            self.skipped += current.remaining;
+        }
+    }
+
+    // jumps forward to the start of the given step
+    pub fn skip_to(&mut self, elapsed: Duration, index: usize) {
+        let start: Duration = self.steps.iter().take(index).map(|x| x.duration).sum();
+        self.skipped += start.saturating_sub(self.position(elapsed));
+    }
+
+    pub fn extend(&mut self, elapsed: Duration, duration: Duration) {
+        if let Some(index) = self.current(elapsed).map(|x| x.index) {
+            self.steps[index].duration += duration;
+        }
+    }
+
+    pub fn adjust_intensity(&mut self, change: f32) {
+        self.intensity = (self.intensity + change).clamp(0.5, 1.5);
+    }
+
+    pub fn duration(&self) -> Duration {
+        return self.steps.iter().map(|x| x.duration).sum();
+    }
+
+    // elapsed is the session clock, the workout advances only while the ride is running
+    pub fn current(&self, elapsed: Duration) -> Option<ActiveStep<'_>> {
+        let mut position = self.position(elapsed);
+        for (index, step) in self.steps.iter().enumerate() {
+            if position < step.duration {
+                return Some(ActiveStep {
+                    index,
+                    step,
+                    elapsed: position,
+                    remaining: step.duration - position
+                });
+            }
+            position -= step.duration;
+        }
+        return None;
+    }
+
+    pub fn next_step(&self, elapsed: Duration) -> Option<&Step> {
+        let current = self.current(elapsed)?;
+        return self.steps.get(current.index + 1);
+    }
+
+    pub fn remaining(&self, elapsed: Duration) -> Duration {
+        return self.duration().saturating_sub(self.position(elapsed));
+    }
+
+    pub fn is_finished(&self, elapsed: Duration) -> bool {
+        return self.current(elapsed).is_none();
+    }
+
+    pub fn target_power(&self, elapsed: Duration) -> Option<u16> {
+        let current = self.current(elapsed)?;
+        return current.step
+            .target_watts(current.elapsed, self.ftp)
+            .map(|x| (x * self.intensity).round() as u16);
+    }
+
+    // ERG for steps with a target, free ride and max effort steps are ridden on a flat road
+    pub fn trainer_command(&self, elapsed: Duration) -> Option<TrainerCommand> {
+        self.current(elapsed)?;
+        return match self.target_power(elapsed) {
+            Some(watts) => Some(TrainerCommand::TargetPower(watts)),
+            None => Some(ftms::RELEASE)
+        };
+    }
+
+    // cues that became due after `from` and up to `to`
+    pub fn cues(&self, from: Duration, to: Duration) -> Vec<&Cue> {
+        let from = self.position(from);
+        let to = self.position(to);
+        let mut result = Vec::new();
+        let mut step_start = Duration::default();
+        for step in &self.steps {
+            for cue in &step.cues {
+                let at = step_start + cue.offset;
+                // the start of the workout counts as due, or a cue at 0 s would never fire
+                let is_due = match from.is_zero() {
+                    true => at <= to && !to.is_zero(),
+                    false => at > from && at <= to
+                };
+                if is_due {
+                    result.push(cue);
+                }
+            }
+            step_start += step.duration;
+        }
+        return result;
+    }
+}
+
+impl Compliance {
+    // how close the actual power is to the target
+    pub fn new(actual: f32, target: f32) -> Compliance {
+        if target <= 0. {
+            return Compliance::OnTarget;
+        }
+
+        let deviation = ((actual - target) / target).abs();
+        if deviation <= 0.05 {
+            return Compliance::OnTarget;
+        }
+        if deviation <= 0.1 {
+            return Compliance::Close;
+        }
+        return Compliance::Off;
+    }
+}
+
+// a few workouts to ride without importing anything
+pub fn library() -> Vec<Workout> {
+    let minutes = |x: u64| Duration::from_secs(x * 60);
+
+    return vec![
+        Workout {
+            name: String::from("Endurance 45"),
+            description: String::from("45 minutes of steady zone 2"),
+            blocks: vec![
+                Block::Step(Step::ramp(minutes(5), Power::Ftp(0.45), Power::Ftp(0.65))),
+                Block::Step(Step::steady(minutes(35), Power::Ftp(0.68))),
+                Block::Step(Step::ramp(minutes(5), Power::Ftp(0.6), Power::Ftp(0.4)))
+            ]
+        },
+        Workout {
+            name: String::from("Sweet spot 3x10"),
+            description: String::from("3 x 10 minutes at 90% FTP with 5 minute recoveries"),
+            blocks: vec![
+                Block::Step(Step::ramp(minutes(10), Power::Ftp(0.45), Power::Ftp(0.75))),
+                Block::Repeat {
+                    count: 3,
+                    steps: vec![
+                        Step::steady(minutes(10), Power::Ftp(0.9)),
+                        Step::steady(minutes(5), Power::Ftp(0.5))
+                    ]
+                },
+                Block::Step(Step::ramp(minutes(5), Power::Ftp(0.6), Power::Ftp(0.4)))
+            ]
+        },
+        Workout {
+            name: String::from("VO2 max 5x3"),
+            description: String::from("5 x 3 minutes at 115% FTP with 3 minute recoveries"),
+            blocks: vec![
+                Block::Step(Step::ramp(minutes(10), Power::Ftp(0.45), Power::Ftp(0.75))),
+                Block::Repeat {
+                    count: 5,
+                    steps: vec![
+                        Step {
+                            duration: minutes(3),
+                            target: Target::Steady(Power::Ftp(1.15)),
+                            cadence: Some(100),
+                            cues: vec![Cue { offset: Duration::default(), message: String::from("Go!") }]
+                        },
+                        Step::steady(minutes(3), Power::Ftp(0.5))
+                    ]
+                },
+                Block::Step(Step::free_ride(minutes(10)))
+            ]
+        }
+    ];
+}
+
+pub fn workouts_dir() -> Option<PathBuf> {
+    return dirs::data_dir().map(|dir| dir.join("cyclo").join("workouts"));
+}
+
+pub fn load(path: &Path) -> Result<Workout, String> {
+    let extension = path.extension()
+        .and_then(|x| x.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    if extension == "zwo" {
+        return zwo::load(path).map_err(|err| err.to_string());
+    }
+
+    return match Format::from_extension(&extension) {
+        Some(format) => erg::load(path, format).map_err(|err| err.to_string()),
+        None => Err(format!("Unsupported workout file {:?}", path))
+    };
+}
+
+pub fn export(workout: &Workout, format: Format, ftp: u16) -> Result<PathBuf, String> {
+    let dir = dirs::data_dir()
+        .map(|dir| dir.join("cyclo").join("exports"))
+        .ok_or(String::from("Data directory not found"))?;
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+
+    let file_name: String = workout.name
+        .chars()
+        .map(|x| if x.is_alphanumeric() || x == '-' { x } else { '_' })
+        .collect();
+    let path = dir.join(format!("{}.{}", file_name, format.extension()));
+
+    fs::write(&path, erg::export(workout, format, ftp)).map_err(|err| err.to_string())?;
+    return Ok(path);
+}
+
+// built-in workouts followed by the ones imported into the workouts directory
+pub fn load_workouts() -> (Vec<Workout>, Vec<String>) {
+    let mut workouts = library();
+    let mut errors = Vec::new();
+
+    let Some(entries) = workouts_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return (workouts, errors);
+    };
+
+    let mut paths: Vec<PathBuf> = entries.filter_map(|x| x.ok()).map(|x| x.path()).collect();
+    paths.sort();
+    for path in paths {
+        match load(&path) {
+            Ok(workout) => workouts.push(workout),
+            Err(err) => errors.push(format!("{:?}: {}", path, err))
+        }
+    }
+
+    return (workouts, errors);
+}
+
+// parses the file first so broken files never end up in the workouts directory
+pub fn import(path: &Path) -> Result<Workout, String> {
+    let workout = load(path)?;
+    let dir = workouts_dir().ok_or(String::from("Data directory not found"))?;
+    let file_name = path.file_name().ok_or_else(|| format!("Invalid path {:?}", path))?;
+
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    fs::copy(path, dir.join(file_name)).map_err(|err| err.to_string())?;
+
+    return Ok(workout);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::ftms::{RELEASE, TrainerCommand};
+    use crate::workout::{Block, Cue, Power, Step, Target, Workout, WorkoutEngine};
+
+    #[test]
+    fn engine_follows_the_session_clock() {
+        let workout = Workout {
+            name: String::from("test"),
+            description: String::new(),
+            blocks: vec![
+                Block::Step(Step::ramp(Duration::from_secs(100), Power::Watts(100), Power::Watts(200))),
+                Block::Repeat { count: 2, steps: vec![Step::steady(Duration::from_secs(60), Power::Ftp(1.))] }
+            ]
+        };
+        let engine = WorkoutEngine::new(workout, 250, Duration::from_secs(10));
+
+        assert_eq!(engine.duration(), Duration::from_secs(220));
+        assert_eq!(engine.target_power(Duration::from_secs(60)), Some(150));
+        assert_eq!(engine.current(Duration::from_secs(180)).unwrap().index, 2);
+        assert_eq!(engine.target_power(Duration::from_secs(180)), Some(250));
+        assert!(engine.is_finished(Duration::from_secs(230)));
+    }
+
+    #[test]
+    fn skip_extend_and_intensity_change_the_plan() {
+        let workout = Workout {
+            name: String::from("test"),
+            description: String::new(),
+            blocks: vec![
+                Block::Step(Step::steady(Duration::from_secs(60), Power::Watts(100))),
+                Block::Step(Step::steady(Duration::from_secs(60), Power::Watts(200)))
+            ]
+        };
+        let mut engine = WorkoutEngine::new(workout, 250, Duration::default());
+
+        engine.extend(Duration::from_secs(30), Duration::from_secs(60));
+        assert_eq!(engine.target_power(Duration::from_secs(90)), Some(100));
+
+        engine.skip(Duration::from_secs(90));
+        assert_eq!(engine.current(Duration::from_secs(90)).unwrap().index, 1);
+
+        engine.adjust_intensity(0.05);
+        assert_eq!(engine.target_power(Duration::from_secs(90)), Some(210));
+        assert_eq!(engine.current(Duration::from_secs(90)).unwrap().step.describe(engine.ftp, engine.intensity), "210 W");
+        assert_eq!(engine.remaining(Duration::from_secs(90)), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn free_ride_steps_release_erg() {
+        let workout = Workout {
+            name: String::from("test"),
+            description: String::new(),
+            blocks: vec![
+                Block::Step(Step::steady(Duration::from_secs(60), Power::Watts(180))),
+                Block::Step(Step::free_ride(Duration::from_secs(60)))
+            ]
+        };
+        let engine = WorkoutEngine::new(workout, 250, Duration::default());
+
+        assert_eq!(engine.trainer_command(Duration::from_secs(30)), Some(TrainerCommand::TargetPower(180)));
+        assert_eq!(engine.trainer_command(Duration::from_secs(90)), Some(RELEASE));
+        assert_eq!(engine.trainer_command(Duration::from_secs(150)), None);
+    }
+
+    #[test]
+    fn cues_fire_at_the_start_of_every_step() {
+        let go = |duration| Step {
+            duration,
+            target: Target::Steady(Power::Watts(300)),
+            cadence: None,
+            cues: vec![Cue { offset: Duration::default(), message: String::from("Go!") }]
+        };
+        let workout = Workout {
+            name: String::from("test"),
+            description: String::new(),
+            blocks: vec![Block::Step(go(Duration::from_secs(60))), Block::Step(go(Duration::from_secs(60)))]
+        };
+        let engine = WorkoutEngine::new(workout, 250, Duration::from_secs(30));
+
+        assert!(engine.cues(Duration::from_secs(30), Duration::from_secs(30)).is_empty());
+        assert_eq!(engine.cues(Duration::from_secs(30), Duration::from_secs(31)).len(), 1);
+        assert!(engine.cues(Duration::from_secs(31), Duration::from_secs(89)).is_empty());
+        assert_eq!(engine.cues(Duration::from_secs(89), Duration::from_secs(90)).len(), 1);
+    }
+}
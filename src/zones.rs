@@ -0,0 +1,257 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::recording::{Lap, Sample};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Zone {
+    pub name: String,
+    pub from: f32,
+    pub to: f32
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeartRateZoneModel {
+    // percentage of max heart rate
+    MaxHeartRate { max: u8 },
+    // Friel zones from lactate threshold heart rate
+    LactateThreshold { lthr: u8 },
+    // Karvonen, percentage of the heart rate reserve on top of resting heart rate
+    HeartRateReserve { max: u8, resting: u8 }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PowerZoneModel {
+    Coggan { ftp: u16 },
+    // upper bounds of every zone but the last one, in percent of FTP
+    Custom { ftp: u16, bounds: Vec<f32> }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartRateZoneKind {
+    MaxHeartRate,
+    LactateThreshold,
+    HeartRateReserve
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerZoneKind {
+    Coggan,
+    Custom
+}
+
+// which zone models to use, the thresholds come from the rider profile
+#[derive(Debug, Clone)]
+pub struct ZoneSettings {
+    pub heart_rate: HeartRateZoneKind,
+    pub power: PowerZoneKind,
+    pub custom_power_bounds: Vec<f32>
+}
+
+const HEART_RATE_ZONE_NAMES: [&str; 5] = ["Recovery", "Endurance", "Tempo", "Threshold", "VO2 max"];
+const POWER_ZONE_NAMES: [&str; 7] = [
+    "Active recovery",
+    "Endurance",
+    "Tempo",
+    "Threshold",
+    "VO2 max",
+    "Anaerobic",
+    "Neuromuscular"
+];
+
+impl ZoneSettings {
+    pub fn new() -> ZoneSettings {
+        return ZoneSettings {
+            heart_rate: HeartRateZoneKind::MaxHeartRate,
+            power: PowerZoneKind::Coggan,
+            custom_power_bounds: vec![55., 75., 90., 105., 120.]
+        }
+    }
+}
+
+impl HeartRateZoneModel {
+    pub fn zones(&self) -> Vec<Zone> {
+        match self {
+            HeartRateZoneModel::MaxHeartRate { max } => {
+                let bounds = [0.6, 0.7, 0.8, 0.9];
+                return build_zones(&HEART_RATE_ZONE_NAMES, &bounds, |x| x * *max as f32);
+            }
+            HeartRateZoneModel::LactateThreshold { lthr } => {
+                let bounds = [0.85, 0.9, 0.95, 1.0];
+                return build_zones(&HEART_RATE_ZONE_NAMES, &bounds, |x| x * *lthr as f32);
+            }
+            HeartRateZoneModel::HeartRateReserve { max, resting } => {
+                let bounds = [0.6, 0.7, 0.8, 0.9];
+                let reserve = max.saturating_sub(*resting) as f32;
+                return build_zones(&HEART_RATE_ZONE_NAMES, &bounds, |x| *resting as f32 + x * reserve);
+            }
+        }
+    }
+}
+
+impl PowerZoneModel {
+    pub fn zones(&self) -> Vec<Zone> {
+        match self {
+            PowerZoneModel::Coggan { ftp } => {
+                let bounds = [0.55, 0.75, 0.9, 1.05, 1.2, 1.5];
+                return build_zones(&POWER_ZONE_NAMES, &bounds, |x| x * *ftp as f32);
+            }
+            PowerZoneModel::Custom { ftp, bounds } => {
+                let names: Vec<String> = (1..=bounds.len() + 1)
+                    .map(|x| format!("Zone {}", x))
+                    .collect();
+                let names: Vec<&str> = names.iter().map(|x| x.as_str()).collect();
+                let bounds: Vec<f32> = bounds.iter().map(|x| x / 100.).collect();
+                return build_zones(&names, &bounds, |x| x * *ftp as f32);
+            }
+        }
+    }
+}
+
+impl HeartRateZoneKind {
+    pub const ALL: [HeartRateZoneKind; 3] = [
+        HeartRateZoneKind::MaxHeartRate,
+        HeartRateZoneKind::LactateThreshold,
+        HeartRateZoneKind::HeartRateReserve
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            HeartRateZoneKind::MaxHeartRate => "max_hr",
+            HeartRateZoneKind::LactateThreshold => "lthr",
+            HeartRateZoneKind::HeartRateReserve => "hrr"
+        }
+    }
+}
+
+impl PowerZoneKind {
+    pub const ALL: [PowerZoneKind; 2] = [PowerZoneKind::Coggan, PowerZoneKind::Custom];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            PowerZoneKind::Coggan => "coggan",
+            PowerZoneKind::Custom => "custom"
+        }
+    }
+}
+
+impl fmt::Display for HeartRateZoneKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            HeartRateZoneKind::MaxHeartRate => "% of max HR",
+            HeartRateZoneKind::LactateThreshold => "% of LTHR",
+            HeartRateZoneKind::HeartRateReserve => "HR reserve (Karvonen)"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+impl fmt::Display for PowerZoneKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            PowerZoneKind::Coggan => "Coggan",
+            PowerZoneKind::Custom => "Custom"
+        };
+        return write!(f, "{}", value);
+    }
+}
+
+// bounds are the upper limits of every zone but the last one
+fn build_zones<F: Fn(f32) -> f32>(names: &[&str], bounds: &[f32], scale: F) -> Vec<Zone> {
+    let mut zones = Vec::new();
+    let mut from = 0.;
+
+    for (index, name) in names.iter().enumerate() {
+        let to = match bounds.get(index) {
+            Some(bound) => scale(*bound),
+            None => f32::MAX
+        };
+
+        zones.push(Zone { name: name.to_string(), from, to });
+        from = to;
+    }
+
+    return zones;
+}
+
+// returns the index of the zone, no data (0) is not in any zone
+pub fn find_zone(zones: &[Zone], value: f32) -> Option<usize> {
+    if value <= 0. {
+        return None;
+    }
+
+    return zones.iter().position(|zone| value >= zone.from && value < zone.to);
+}
+
+pub fn time_in_zones<F: Fn(&Sample) -> f32>(samples: &[Sample], zones: &[Zone], value: F) -> Vec<Duration> {
+    let mut result = vec![Duration::default(); zones.len()];
+    // samples are recorded every second, so the first one covers the second before it
+    let mut previous = samples.first()
+        .map_or(Duration::default(), |x| x.elapsed.saturating_sub(Duration::from_secs(1)));
+
+    for sample in samples {
+        let elapsed = sample.elapsed.saturating_sub(previous);
+        previous = sample.elapsed;
+
+        if let Some(index) = find_zone(zones, value(sample)) {
+            result[index] += elapsed;
+        }
+    }
+
+    return result;
+}
+
+pub fn lap_samples<'a>(samples: &'a [Sample], lap: &Lap) -> &'a [Sample] {
+    let from = samples.partition_point(|sample| sample.elapsed <= lap.start);
+    let to = samples.partition_point(|sample| sample.elapsed <= lap.end);
+    return &samples[from..to];
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::recording::Sample;
+    use crate::zones::{find_zone, time_in_zones, HeartRateZoneModel, PowerZoneModel};
+
+    fn sample(second: u64, power: u16) -> Sample {
+        return Sample {
+            elapsed: Duration::from_secs(second),
+            heart_rate: 0,
+            power,
+            cadence: 0,
+            speed: 0.
+        }
+    }
+
+    #[test]
+    fn coggan_zones_from_ftp() {
+        let zones = PowerZoneModel::Coggan { ftp: 200 }.zones();
+
+        assert_eq!(zones.len(), 7);
+        assert_eq!(find_zone(&zones, 100.), Some(0));
+        assert_eq!(find_zone(&zones, 200.), Some(3));
+        assert_eq!(find_zone(&zones, 1000.), Some(6));
+        assert_eq!(find_zone(&zones, 0.), None);
+    }
+
+    #[test]
+    fn karvonen_zones_start_from_resting_heart_rate() {
+        let zones = HeartRateZoneModel::HeartRateReserve { max: 190, resting: 50 }.zones();
+
+        // 50 + 0.6 * 140
+        assert_eq!(zones[0].to, 134.);
+        assert_eq!(find_zone(&zones, 140.), Some(1));
+    }
+
+    #[test]
+    fn time_in_zones_counts_time_between_samples() {
+        let zones = PowerZoneModel::Coggan { ftp: 200 }.zones();
+        let samples = vec![sample(1, 100), sample(2, 100), sample(3, 200), sample(4, 0)];
+
+        let result = time_in_zones(&samples, &zones, |x| x.power as f32);
+
+        assert_eq!(result[0], Duration::from_secs(2));
+        assert_eq!(result[3], Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,271 @@
+// Zwift workout files (.zwo)
+// powers are fractions of FTP, durations are in seconds
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use roxmltree::{Document, Node};
+use thiserror::Error;
+
+use crate::workout::{Block, Cue, Power, Step, Target, Workout};
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ZwoError {
+    #[error("Failed to read the workout file: {0}")]
+    ReadFailed(String),
+    #[error("Invalid XML: {0}")]
+    InvalidXml(String),
+    #[error("The file has no <workout> element")]
+    MissingWorkout,
+    #[error("Unsupported element <{0}>")]
+    UnsupportedElement(String),
+    #[error("<{0}> is missing the {1} attribute")]
+    MissingAttribute(String, String),
+    #[error("<{0}> has an invalid {1} attribute: {2}")]
+    InvalidAttribute(String, String, String)
+}
+
+pub fn load(path: &Path) -> Result<Workout, ZwoError> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| ZwoError::ReadFailed(err.to_string()))?;
+    return parse(&content);
+}
+
+pub fn parse(content: &str) -> Result<Workout, ZwoError> {
+    let document = Document::parse(content)
+        .map_err(|err| ZwoError::InvalidXml(err.to_string()))?;
+    let root = document.root_element();
+
+    let workout = child(root, "workout").ok_or(ZwoError::MissingWorkout)?;
+    let mut blocks = Vec::new();
+    for element in workout.children().filter(|x| x.is_element()) {
+        blocks.extend(parse_element(element)?);
+    }
+
+    return Ok(Workout {
+        name: child_text(root, "name").unwrap_or_else(|| String::from("Unnamed workout")),
+        description: child_text(root, "description").unwrap_or_default(),
+        blocks
+    });
+}
+
+fn parse_element(element: Node) -> Result<Vec<Block>, ZwoError> {
+    let name = element.tag_name().name();
+    let cues = text_events(element)?;
+
+    // tag names are not consistently cased in files found in the wild
+    let step = match name.to_lowercase().as_str() {
+        "steadystate" | "solidstate" => {
+            let power = match element.attribute("Power") {
+                Some(_) => attribute(element, "Power")?,
+                // some editors write steady states as a ramp with equal ends
+                None => attribute(element, "PowerLow")?
+            };
+            Step {
+                duration: duration(element, "Duration")?,
+                target: Target::Steady(Power::Ftp(power)),
+                cadence: cadence(element, "Cadence")?,
+                cues
+            }
+        }
+        "warmup" | "cooldown" | "ramp" => Step {
+            duration: duration(element, "Duration")?,
+            target: Target::Ramp {
+                from: Power::Ftp(attribute(element, "PowerLow")?),
+                to: Power::Ftp(attribute(element, "PowerHigh")?)
+            },
+            cadence: cadence(element, "Cadence")?,
+            cues
+        },
+        "freeride" => Step {
+            duration: duration(element, "Duration")?,
+            target: Target::FreeRide,
+            cadence: cadence(element, "Cadence")?,
+            cues
+        },
+        "maxeffort" => Step {
+            duration: duration(element, "Duration")?,
+            target: Target::MaxEffort,
+            cadence: None,
+            cues
+        },
+        "intervalst" => return parse_intervals(element, cues),
+        _ => return Err(ZwoError::UnsupportedElement(name.to_string()))
+    };
+
+    return Ok(vec![Block::Step(step)]);
+}
+
+fn parse_intervals(element: Node, cues: Vec<Cue>) -> Result<Vec<Block>, ZwoError> {
+    let count: u32 = attribute(element, "Repeat")?;
+    if count == 0 {
+        return Err(ZwoError::InvalidAttribute(
+            element.tag_name().name().to_string(),
+            String::from("Repeat"),
+            count.to_string()
+        ));
+    }
+    let on = Step {
+        duration: duration(element, "OnDuration")?,
+        target: Target::Steady(Power::Ftp(attribute(element, "OnPower")?)),
+        cadence: cadence(element, "Cadence")?,
+        cues: Vec::new()
+    };
+    let off = Step {
+        duration: duration(element, "OffDuration")?,
+        target: Target::Steady(Power::Ftp(attribute(element, "OffPower")?)),
+        cadence: cadence(element, "CadenceResting")?,
+        cues: Vec::new()
+    };
+
+    if cues.is_empty() {
+        return Ok(vec![Block::Repeat { count, steps: vec![on, off] }]);
+    }
+
+    // text events are timed from the start of the whole block, so the
+    // repeats are unrolled to put every cue on the step it belongs to
+    let mut steps: Vec<Step> = (0..count).flat_map(|_| [on.clone(), off.clone()]).collect();
+    for cue in cues {
+        let mut offset = cue.offset;
+        let last = steps.len() - 1;
+        for (index, step) in steps.iter_mut().enumerate() {
+            if offset < step.duration || index == last {
+                step.cues.push(Cue { offset, message: cue.message });
+                break;
+            }
+            offset -= step.duration;
+        }
+    }
+
+    return Ok(steps.into_iter().map(Block::Step).collect());
+}
+
+fn text_events(element: Node) -> Result<Vec<Cue>, ZwoError> {
+    let mut cues = Vec::new();
+    for event in element.children().filter(|x| x.tag_name().name().eq_ignore_ascii_case("textevent")) {
+        cues.push(Cue {
+            offset: duration(event, "timeoffset")?,
+            message: event.attribute("message").unwrap_or_default().to_string()
+        });
+    }
+    return Ok(cues);
+}
+
+fn attribute<T: std::str::FromStr>(element: Node, name: &str) -> Result<T, ZwoError> {
+    let element_name = element.tag_name().name().to_string();
+    let value = element.attribute(name)
+        .ok_or_else(|| ZwoError::MissingAttribute(element_name.clone(), name.to_string()))?;
+
+    return value.trim().parse::<T>()
+        .map_err(|_| ZwoError::InvalidAttribute(element_name, name.to_string(), value.to_string()));
+}
+
+// durations are sometimes written as floats
+fn duration(element: Node, name: &str) -> Result<Duration, ZwoError> {
+    let seconds: f32 = attribute(element, name)?;
+    return Ok(Duration::from_secs(seconds.max(0.).round() as u64));
+}
+
+fn cadence(element: Node, name: &str) -> Result<Option<u8>, ZwoError> {
+    if element.attribute(name).is_none() {
+        return Ok(None);
+    }
+    let value: f32 = attribute(element, name)?;
+    return Ok(Some(value.round() as u8));
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    return node.children().find(|x| x.tag_name().name() == name);
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    return child(node, name)
+        .and_then(|x| x.text())
+        .map(|x| x.trim().to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::workout::{Block, Power, Target};
+    use crate::zwo::{parse, ZwoError};
+
+    #[test]
+    fn parses_zwift_export() {
+        let workout = parse(include_str!("../tests/fixtures/mixed_session.zwo")).unwrap();
+
+        assert_eq!(workout.name, "Mixed Session");
+        assert_eq!(workout.duration(), Duration::from_secs(60 * 60));
+        assert_eq!(workout.blocks.len(), 8);
+
+        let Block::Step(warmup) = &workout.blocks[0] else { panic!("expected a step") };
+        assert_eq!(warmup.target, Target::Ramp { from: Power::Ftp(0.25), to: Power::Ftp(0.75) });
+
+        let Block::Repeat { count, steps } = &workout.blocks[2] else { panic!("expected a repeat") };
+        assert_eq!(*count, 5);
+        assert_eq!(steps[0].cadence, Some(100));
+        assert_eq!(steps[1].cadence, Some(85));
+    }
+
+    #[test]
+    fn unrolls_intervals_with_text_events() {
+        let workout = parse(include_str!("../tests/fixtures/over_unders.zwo")).unwrap();
+        let steps = workout.steps();
+
+        assert_eq!(steps.len(), 1 + 3 * 2 + 2);
+        assert_eq!(steps[1].cues[0].message, "First over");
+        // 210 s into the block is 30 s into the second over
+        assert_eq!(steps[3].cues[0].message, "Second over");
+        assert_eq!(steps[3].cues[0].offset, Duration::from_secs(30));
+        assert_eq!(steps[7].target, Target::FreeRide);
+        assert_eq!(steps[8].target, Target::MaxEffort);
+    }
+
+    #[test]
+    fn parses_editor_output() {
+        // CRLF line endings, leading dot floats and the extra header elements and attributes editors write
+        let workout = parse(include_str!("../tests/fixtures/sweet_spot_editor.zwo")).unwrap();
+        let steps = workout.steps();
+
+        assert_eq!(workout.name, "Sweet Spot 3x10");
+        assert_eq!(workout.duration(), Duration::from_secs(60 * 60));
+        assert_eq!(steps.len(), 1 + 3 * 2 + 2);
+        assert_eq!(steps[0].cues[0].message, "Easy spin to start");
+        let Target::Steady(Power::Ftp(on)) = steps[1].target else { panic!("expected a steady target") };
+        assert!((on - 0.9).abs() < 0.001);
+        assert_eq!(steps[7].target, Target::FreeRide);
+    }
+
+    #[test]
+    fn reports_unsupported_elements() {
+        let content = r#"<workout_file><workout><SteadyState Duration="60" Power="0.5"/><Run Duration="60"/></workout></workout_file>"#;
+
+        assert_eq!(parse(content), Err(ZwoError::UnsupportedElement(String::from("Run"))));
+    }
+
+    #[test]
+    fn reports_missing_attributes() {
+        let content = r#"<workout_file><workout><Ramp Duration="60" PowerLow="0.5"/></workout></workout_file>"#;
+
+        assert_eq!(
+            parse(content),
+            Err(ZwoError::MissingAttribute(String::from("Ramp"), String::from("PowerHigh")))
+        );
+    }
+
+    #[test]
+    fn rejects_intervals_without_repeats() {
+        let content = r#"<workout_file><workout>
+            <IntervalsT Repeat="0" OnDuration="60" OffDuration="60" OnPower="1.1" OffPower="0.5">
+                <textevent timeoffset="10" message="Go"/>
+            </IntervalsT>
+        </workout></workout_file>"#;
+
+        assert_eq!(
+            parse(content),
+            Err(ZwoError::InvalidAttribute(String::from("IntervalsT"), String::from("Repeat"), String::from("0")))
+        );
+    }
+}